@@ -0,0 +1,451 @@
+use std::collections::HashMap;
+
+use crate::graph::graph::*;
+use crate::graph::attributes::{Attributes, AttributeValue};
+use crate::graph::directed_graph::DirectedGraph;
+use crate::graph::mutable_graph::MutableGraph;
+
+/// A directed graph where each vertex and each edge holds [`Attributes`].
+///
+/// If the graph contains the same edge more than once, all these edges share the same attributes.
+///
+/// [`Attributes`]: ../attributes/type.Attributes.html
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributedGraph {
+    graph: DirectedGraph,
+    // Every vertex of the graph has an entry in this map, possibly with empty attributes
+    vertex_attributes: HashMap<VertexId, Attributes>,
+    // Every edge of the graph has an entry in this map, possibly with empty attributes
+    edge_attributes: HashMap<Edge, Attributes>,
+}
+
+impl AttributedGraph {
+
+    /// Creates an empty `AttributedGraph`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::graph::attributed_graph::AttributedGraph;
+    ///
+    /// let mut graph = AttributedGraph::new();
+    /// ```
+    pub fn new() -> AttributedGraph {
+        AttributedGraph {
+            graph: DirectedGraph::new(),
+            vertex_attributes: HashMap::new(),
+            edge_attributes: HashMap::new(),
+        }
+    }
+
+    /// Returns the structure of the graph, without the attributes.
+    pub fn graph(&self) -> &DirectedGraph {
+        &self.graph
+    }
+
+    /// An iterator visiting all vertices of the graph in arbitrary order.
+    /// The iterator element type is `&VertexId`
+    pub fn vertices(&self) -> impl Iterator<Item=&VertexId> {
+        self.graph.vertices()
+    }
+
+    /// An iterator visiting all the edges of the graph in arbitrary order.
+    /// The iterator element type is `&Edge`
+    pub fn edges(&self) -> impl Iterator<Item=&Edge> {
+        self.graph.edges()
+    }
+
+    /// Returns the attributes of `vertex_id`, or `None` if the graph does not contain `vertex_id`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::graph::attributed_graph::AttributedGraph;
+    /// use histo_graph_core::graph::graph::VertexId;
+    ///
+    /// let mut g = AttributedGraph::new();
+    /// assert!(g.vertex_attributes(VertexId(1)).is_none());
+    /// g.add_vertex(VertexId(1));
+    /// assert!(g.vertex_attributes(VertexId(1)).unwrap().is_empty());
+    /// ```
+    pub fn vertex_attributes(&self, vertex_id: VertexId) -> Option<&Attributes> {
+        self.vertex_attributes.get(&vertex_id)
+    }
+
+    /// Returns the value of the attribute `key` of `vertex_id`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::graph::attributed_graph::AttributedGraph;
+    /// use histo_graph_core::graph::attributes::AttributeValue;
+    /// use histo_graph_core::graph::graph::VertexId;
+    ///
+    /// let mut g = AttributedGraph::new();
+    /// g.set_vertex_attribute(VertexId(1), "name".to_string(), "one".into());
+    /// assert_eq!(g.vertex_attribute(VertexId(1), "name"), Some(&AttributeValue::Str("one".to_string())));
+    /// assert_eq!(g.vertex_attribute(VertexId(1), "color"), None);
+    /// ```
+    pub fn vertex_attribute(&self, vertex_id: VertexId, key: &str) -> Option<&AttributeValue> {
+        self.vertex_attributes
+            .get(&vertex_id)
+            .and_then(|attributes| attributes.get(key))
+    }
+
+    /// Returns the attributes of `edge`, or `None` if the graph does not contain `edge`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::graph::attributed_graph::AttributedGraph;
+    /// use histo_graph_core::graph::graph::{VertexId, Edge};
+    ///
+    /// let mut g = AttributedGraph::new();
+    /// assert!(g.edge_attributes(Edge(VertexId(1), VertexId(2))).is_none());
+    /// g.add_edge(Edge(VertexId(1), VertexId(2)));
+    /// assert!(g.edge_attributes(Edge(VertexId(1), VertexId(2))).unwrap().is_empty());
+    /// ```
+    pub fn edge_attributes(&self, edge: Edge) -> Option<&Attributes> {
+        self.edge_attributes.get(&edge)
+    }
+
+    /// Returns the value of the attribute `key` of `edge`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::graph::attributed_graph::AttributedGraph;
+    /// use histo_graph_core::graph::attributes::AttributeValue;
+    /// use histo_graph_core::graph::graph::{VertexId, Edge};
+    ///
+    /// let mut g = AttributedGraph::new();
+    /// let edge = Edge(VertexId(1), VertexId(2));
+    /// g.set_edge_attribute(edge, "label".to_string(), "knows".into());
+    /// assert_eq!(g.edge_attribute(edge, "label"), Some(&AttributeValue::Str("knows".to_string())));
+    /// assert_eq!(g.edge_attribute(edge, "weight"), None);
+    /// ```
+    pub fn edge_attribute(&self, edge: Edge, key: &str) -> Option<&AttributeValue> {
+        self.edge_attributes
+            .get(&edge)
+            .and_then(|attributes| attributes.get(key))
+    }
+
+    /// Adds a vertex to the graph. Returns true if the graph already contained `vertex_id `.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::graph::attributed_graph::AttributedGraph;
+    /// use histo_graph_core::graph::graph::VertexId;
+    ///
+    /// let mut g = AttributedGraph::new();
+    /// assert!(!g.add_vertex(VertexId(1)));
+    /// assert!(g.add_vertex(VertexId(1)));
+    /// ```
+    pub fn add_vertex(&mut self, vertex_id: VertexId) -> bool {
+        self.vertex_attributes
+            .entry(vertex_id)
+            .or_default();
+        self.graph.add_vertex(vertex_id)
+    }
+
+    /// Adds a vertex with the provided `attributes` to the graph. If the graph already contained
+    /// `vertex_id`, its attributes are replaced.
+    pub fn add_vertex_with_attributes(&mut self, vertex_id: VertexId, attributes: Attributes) {
+        self.vertex_attributes.insert(vertex_id, attributes);
+        self.graph.add_vertex(vertex_id);
+    }
+
+    /// Removes a vertex, its attributes and all its edges from the graph.
+    /// Returns true if the graph contained `vertex_id` before the removal.
+    pub fn remove_vertex(&mut self, vertex_id: VertexId) -> bool {
+        let edges: Vec<Edge> = self.graph
+            .outbound_edges(vertex_id)
+            .chain(self.graph.inbound_edges(vertex_id))
+            .cloned()
+            .collect();
+        for edge in edges {
+            self.edge_attributes.remove(&edge);
+        }
+        self.vertex_attributes.remove(&vertex_id);
+        self.graph.remove_vertex(vertex_id)
+    }
+
+    /// Adds an edge to the graph. Vertices that are not yet in the graph are added without
+    /// attributes. If the graph does not yet contain the edge, it is added without attributes,
+    /// otherwise it shares the attributes of the contained edge.
+    pub fn add_edge(&mut self, edge: Edge) {
+        let Edge(v1, v2) = edge;
+        self.add_vertex(v1);
+        self.add_vertex(v2);
+        self.edge_attributes
+            .entry(edge)
+            .or_default();
+        self.graph.add_edge(edge);
+    }
+
+    /// Adds an edge with the provided `attributes` to the graph. If the graph already contained
+    /// the edge, the attributes of all these edges are replaced.
+    pub fn add_edge_with_attributes(&mut self, edge: Edge, attributes: Attributes) {
+        let Edge(v1, v2) = edge;
+        self.add_vertex(v1);
+        self.add_vertex(v2);
+        self.edge_attributes.insert(edge, attributes);
+        self.graph.add_edge(edge);
+    }
+
+    /// Removes and edge from the graph. The attributes of the edge are removed together with the
+    /// last one of the equal edges.
+    /// Returns true if that graph contained the `edge` before the removal.
+    pub fn remove_edge(&mut self, edge: Edge) -> bool {
+        let found = self.graph.remove_edge(edge);
+        if !self.graph.contains_edge(edge) {
+            self.edge_attributes.remove(&edge);
+        }
+        found
+    }
+
+    /// Sets the attribute `key` of `vertex_id` to `value`, and returns the previous value of the
+    /// attribute, if any. The vertex is added to the graph, if it is not yet contained.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::graph::attributed_graph::AttributedGraph;
+    /// use histo_graph_core::graph::attributes::AttributeValue;
+    /// use histo_graph_core::graph::graph::VertexId;
+    ///
+    /// let mut g = AttributedGraph::new();
+    /// assert_eq!(g.set_vertex_attribute(VertexId(1), "weight".to_string(), 1i64.into()), None);
+    /// assert_eq!(g.set_vertex_attribute(VertexId(1), "weight".to_string(), 2i64.into()), Some(AttributeValue::Int(1)));
+    /// ```
+    pub fn set_vertex_attribute(&mut self, vertex_id: VertexId, key: String, value: AttributeValue) -> Option<AttributeValue> {
+        self.graph.add_vertex(vertex_id);
+        self.vertex_attributes
+            .entry(vertex_id)
+            .or_default()
+            .insert(key, value)
+    }
+
+    /// Sets the attribute `key` of `edge` to `value`, and returns the previous value of the
+    /// attribute, if any. The edge is added to the graph, if it is not yet contained.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::graph::attributed_graph::AttributedGraph;
+    /// use histo_graph_core::graph::attributes::AttributeValue;
+    /// use histo_graph_core::graph::graph::{VertexId, Edge};
+    ///
+    /// let mut g = AttributedGraph::new();
+    /// let edge = Edge(VertexId(1), VertexId(2));
+    /// assert_eq!(g.set_edge_attribute(edge, "weight".to_string(), 0.5.into()), None);
+    /// assert_eq!(g.set_edge_attribute(edge, "weight".to_string(), 1.5.into()), Some(AttributeValue::Float(0.5)));
+    /// assert_eq!(g.graph().edge_count(), 1);
+    /// ```
+    pub fn set_edge_attribute(&mut self, edge: Edge, key: String, value: AttributeValue) -> Option<AttributeValue> {
+        if !self.graph.contains_edge(edge) {
+            self.add_edge(edge);
+        }
+        self.edge_attributes
+            .entry(edge)
+            .or_default()
+            .insert(key, value)
+    }
+
+    /// Removes the attribute `key` of `edge`, and returns its value, if any.
+    pub fn remove_edge_attribute(&mut self, edge: Edge, key: &str) -> Option<AttributeValue> {
+        self.edge_attributes
+            .get_mut(&edge)
+            .and_then(|attributes| attributes.remove(key))
+    }
+
+    /// Removes the attribute `key` of `vertex_id`, and returns its value, if any.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::graph::attributed_graph::AttributedGraph;
+    /// use histo_graph_core::graph::attributes::AttributeValue;
+    /// use histo_graph_core::graph::graph::VertexId;
+    ///
+    /// let mut g = AttributedGraph::new();
+    /// g.set_vertex_attribute(VertexId(1), "flag".to_string(), true.into());
+    /// assert_eq!(g.remove_vertex_attribute(VertexId(1), "flag"), Some(AttributeValue::Bool(true)));
+    /// assert_eq!(g.remove_vertex_attribute(VertexId(1), "flag"), None);
+    /// ```
+    pub fn remove_vertex_attribute(&mut self, vertex_id: VertexId, key: &str) -> Option<AttributeValue> {
+        self.vertex_attributes
+            .get_mut(&vertex_id)
+            .and_then(|attributes| attributes.remove(key))
+    }
+}
+
+impl Default for AttributedGraph {
+    fn default() -> AttributedGraph {
+        AttributedGraph::new()
+    }
+}
+
+impl From<DirectedGraph> for AttributedGraph {
+
+    /// Creates an `AttributedGraph` where every vertex and every edge has empty attributes.
+    fn from(graph: DirectedGraph) -> AttributedGraph {
+        let vertex_attributes = graph
+            .vertices()
+            .map(|&vertex_id| (vertex_id, Attributes::new()))
+            .collect();
+
+        let edge_attributes = graph
+            .edges()
+            .map(|&edge| (edge, Attributes::new()))
+            .collect();
+
+        AttributedGraph {
+            graph,
+            vertex_attributes,
+            edge_attributes,
+        }
+    }
+}
+
+impl MutableGraph for AttributedGraph {
+    fn add_vertex(&mut self, vertex_id: VertexId) -> bool {
+        AttributedGraph::add_vertex(self, vertex_id)
+    }
+
+    fn remove_vertex(&mut self, vertex_id: VertexId) -> bool {
+        AttributedGraph::remove_vertex(self, vertex_id)
+    }
+
+    fn add_edge(&mut self, edge: Edge) {
+        AttributedGraph::add_edge(self, edge)
+    }
+
+    fn remove_edge(&mut self, edge: Edge) -> bool {
+        AttributedGraph::remove_edge(self, edge)
+    }
+
+    fn set_vertex_attribute(&mut self, vertex_id: VertexId, key: String, value: AttributeValue) {
+        AttributedGraph::set_vertex_attribute(self, vertex_id, key, value);
+    }
+
+    fn remove_vertex_attribute(&mut self, vertex_id: VertexId, key: &str) {
+        AttributedGraph::remove_vertex_attribute(self, vertex_id, key);
+    }
+
+    fn set_edge_attribute(&mut self, edge: Edge, key: String, value: AttributeValue) {
+        AttributedGraph::set_edge_attribute(self, edge, key, value);
+    }
+
+    fn remove_edge_attribute(&mut self, edge: Edge, key: &str) {
+        AttributedGraph::remove_edge_attribute(self, edge, key);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::AttributedGraph;
+    use crate::graph::attributes::AttributeValue;
+    use crate::graph::command::GraphCommand;
+    use crate::graph::directed_graph::DirectedGraph;
+    use crate::graph::graph::{Edge, VertexId};
+
+    #[test]
+    fn test_remove_vertex_removes_attributes() {
+        let mut graph = AttributedGraph::new();
+        graph.set_vertex_attribute(VertexId(1), "name".to_string(), "one".into());
+        graph.remove_vertex(VertexId(1));
+        graph.add_vertex(VertexId(1));
+
+        assert_eq!(graph.vertex_attribute(VertexId(1), "name"), None);
+    }
+
+    #[test]
+    fn test_edge_attributes_removed_with_last_edge() {
+        let edge = Edge(VertexId(1), VertexId(2));
+        let mut graph = AttributedGraph::new();
+        graph.add_edge(edge);
+        graph.set_edge_attribute(edge, "label".to_string(), "knows".into());
+        graph.add_edge(edge);
+
+        graph.remove_edge(edge);
+        assert_eq!(graph.edge_attribute(edge, "label"), Some(&AttributeValue::Str("knows".to_string())));
+
+        graph.remove_edge(edge);
+        assert_eq!(graph.edge_attributes(edge), None);
+    }
+
+    #[test]
+    fn test_remove_vertex_removes_edge_attributes() {
+        let edge = Edge(VertexId(1), VertexId(2));
+        let mut graph = AttributedGraph::new();
+        graph.set_edge_attribute(edge, "label".to_string(), "knows".into());
+        graph.remove_vertex(VertexId(2));
+
+        let mut expected = AttributedGraph::new();
+        expected.add_vertex(VertexId(1));
+
+        assert_eq!(graph, expected);
+    }
+
+    #[test]
+    fn test_ne_attributes() {
+        let mut graph_1 = AttributedGraph::new();
+        graph_1.add_edge(Edge(VertexId(1), VertexId(2)));
+        graph_1.set_vertex_attribute(VertexId(1), "weight".to_string(), AttributeValue::Float(1.5));
+
+        let mut graph_2 = AttributedGraph::new();
+        graph_2.add_edge(Edge(VertexId(1), VertexId(2)));
+        graph_2.set_vertex_attribute(VertexId(1), "weight".to_string(), AttributeValue::Float(2.5));
+
+        assert_ne!(graph_1, graph_2);
+        assert_eq!(graph_1.graph(), graph_2.graph());
+    }
+
+    #[test]
+    fn test_from_directed_graph() {
+        let mut graph = DirectedGraph::new();
+        graph.add_edge(Edge(VertexId(1), VertexId(2)));
+
+        let mut expected = AttributedGraph::new();
+        expected.add_edge(Edge(VertexId(1), VertexId(2)));
+
+        assert_eq!(AttributedGraph::from(graph), expected);
+    }
+
+    #[test]
+    fn test_apply_and_revert_attribute_commands() {
+        let mut graph = AttributedGraph::new();
+        graph.add_vertex(VertexId(1));
+
+        let command = GraphCommand::SetVertexAttribute(VertexId(1), "name".to_string(), "one".into(), None);
+        command.apply_to(&mut graph);
+        assert_eq!(graph.vertex_attribute(VertexId(1), "name"), Some(&AttributeValue::Str("one".to_string())));
+
+        // a value that overwrites another one is reverted to the one before
+        let overwrite = GraphCommand::SetVertexAttribute(VertexId(1), "name".to_string(), "uno".into(), Some("one".into()));
+        overwrite.apply_to(&mut graph);
+        GraphCommand::revert(overwrite).apply_to(&mut graph);
+        assert_eq!(graph.vertex_attribute(VertexId(1), "name"), Some(&AttributeValue::Str("one".to_string())));
+
+        GraphCommand::revert(command).apply_to(&mut graph);
+        assert_eq!(graph.vertex_attribute(VertexId(1), "name"), None);
+
+        let edge = Edge(VertexId(1), VertexId(2));
+        graph.add_edge(edge);
+        graph.set_edge_attribute(edge, "weight".to_string(), 1.5.into());
+        let overwrite = GraphCommand::SetEdgeAttribute(VertexId(1), VertexId(2), "weight".to_string(), 2.5.into(), Some(1.5.into()));
+        overwrite.apply_to(&mut graph);
+        assert_eq!(graph.edge_attribute(edge, "weight"), Some(&AttributeValue::Float(2.5)));
+        GraphCommand::revert(overwrite).apply_to(&mut graph);
+        assert_eq!(graph.edge_attribute(edge, "weight"), Some(&AttributeValue::Float(1.5)));
+
+        // commands are Eq and Hash, also with float values
+        let commands: std::collections::HashSet<GraphCommand> = vec![
+            GraphCommand::SetEdgeAttribute(VertexId(1), VertexId(2), "weight".to_string(), f64::NAN.into(), None),
+            GraphCommand::SetEdgeAttribute(VertexId(1), VertexId(2), "weight".to_string(), f64::NAN.into(), None),
+        ].into_iter().collect();
+        assert_eq!(commands.len(), 1);
+    }
+}
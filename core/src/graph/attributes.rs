@@ -0,0 +1,124 @@
+//! This module defines the attributes that can be attached to the vertices and edges of a graph.
+//!
+//! [`Attributes`] are a map of string keys to typed [`AttributeValue`]s.
+//!
+//! [`Attributes`]: type.Attributes.html
+//! [`AttributeValue`]: enum.AttributeValue.html
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+/// The value of an attribute.
+///
+/// Floats are compared and hashed by their bits, so that values are `Eq` and `Hash`, like the
+/// [`GraphCommand`]s that hold them: `NaN` equals itself, and `0.0` differs from `-0.0`. This is
+/// also how the values are told apart when they are stored.
+///
+/// [`GraphCommand`]: ../command/enum.GraphCommand.html
+#[derive(Clone, Debug)]
+pub enum AttributeValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+}
+
+impl AttributeValue {
+
+    /// The position of the variant, that orders values of different types.
+    fn rank(&self) -> u8 {
+        match self {
+            AttributeValue::Bool(_) => 0,
+            AttributeValue::Int(_) => 1,
+            AttributeValue::Float(_) => 2,
+            AttributeValue::Str(_) => 3,
+        }
+    }
+}
+
+impl PartialEq for AttributeValue {
+    fn eq(&self, other: &AttributeValue) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for AttributeValue {}
+
+impl PartialOrd for AttributeValue {
+    fn partial_cmp(&self, other: &AttributeValue) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for AttributeValue {
+    fn cmp(&self, other: &AttributeValue) -> Ordering {
+        match (self, other) {
+            (AttributeValue::Bool(a), AttributeValue::Bool(b)) => a.cmp(b),
+            (AttributeValue::Int(a), AttributeValue::Int(b)) => a.cmp(b),
+            (AttributeValue::Float(a), AttributeValue::Float(b)) => a.total_cmp(b),
+            (AttributeValue::Str(a), AttributeValue::Str(b)) => a.cmp(b),
+            (a, b) => a.rank().cmp(&b.rank()),
+        }
+    }
+}
+
+impl Hash for AttributeValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.rank().hash(state);
+        match self {
+            AttributeValue::Bool(b) => b.hash(state),
+            AttributeValue::Int(i) => i.hash(state),
+            AttributeValue::Float(x) => x.to_bits().hash(state),
+            AttributeValue::Str(s) => s.hash(state),
+        }
+    }
+}
+
+/// A map of keys to [`AttributeValue`]s.
+/// The keys are kept in sorted order, so that the attributes can be serialized deterministically.
+///
+/// [`AttributeValue`]: enum.AttributeValue.html
+pub type Attributes = BTreeMap<String, AttributeValue>;
+
+impl fmt::Display for AttributeValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AttributeValue::Bool(b) => write!(f, "{}", b),
+            AttributeValue::Int(i) => write!(f, "{}", i),
+            AttributeValue::Float(x) => write!(f, "{}", x),
+            AttributeValue::Str(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+impl From<bool> for AttributeValue {
+    fn from(b: bool) -> AttributeValue {
+        AttributeValue::Bool(b)
+    }
+}
+
+impl From<i64> for AttributeValue {
+    fn from(i: i64) -> AttributeValue {
+        AttributeValue::Int(i)
+    }
+}
+
+impl From<f64> for AttributeValue {
+    fn from(f: f64) -> AttributeValue {
+        AttributeValue::Float(f)
+    }
+}
+
+impl From<String> for AttributeValue {
+    fn from(s: String) -> AttributeValue {
+        AttributeValue::Str(s)
+    }
+}
+
+impl From<&str> for AttributeValue {
+    fn from(s: &str) -> AttributeValue {
+        AttributeValue::Str(s.to_string())
+    }
+}
@@ -1,10 +1,12 @@
 use crate::graph::graph::{VertexId, Edge};
+use crate::graph::attributes::AttributeValue;
 use crate::graph::directed_graph::DirectedGraph;
+use crate::graph::mutable_graph::MutableGraph;
 
-/// A command to manipulate a [`DirectedGraph`]
+/// A command to manipulate a [`MutableGraph`]
 ///
-/// [`DirectedGraph`]: ../directed_graph/struct.DirectedGraph.html
-#[derive(PartialEq, Eq, Clone, Copy, Hash, Debug)]
+/// [`MutableGraph`]: ../mutable_graph/trait.MutableGraph.html
+#[derive(PartialEq, Eq, Clone, Hash, Debug)]
 pub enum GraphCommand {
 
     /// Adds a vertex to a graph.
@@ -18,6 +20,22 @@ pub enum GraphCommand {
 
     /// Removes an edge from a graph.
     RemoveEdge(VertexId, VertexId),
+
+    /// Sets an attribute of a vertex to a value. The last value is the one that the attribute had
+    /// before, if any, so that the command can be reverted.
+    SetVertexAttribute(VertexId, String, AttributeValue, Option<AttributeValue>),
+
+    /// Removes an attribute from a vertex. The value is the one that the attribute had before
+    /// the removal, so that the command can be reverted.
+    RemoveVertexAttribute(VertexId, String, AttributeValue),
+
+    /// Sets an attribute of an edge to a value. The last value is the one that the attribute had
+    /// before, if any, so that the command can be reverted.
+    SetEdgeAttribute(VertexId, VertexId, String, AttributeValue, Option<AttributeValue>),
+
+    /// Removes an attribute from an edge. The value is the one that the attribute had before
+    /// the removal, so that the command can be reverted.
+    RemoveEdgeAttribute(VertexId, VertexId, String, AttributeValue),
 }
 
 impl GraphCommand {
@@ -30,24 +48,34 @@ impl GraphCommand {
             RemoveVertex(v) => AddVertex(v),
             AddEdge(v1, v2) => RemoveEdge(v1, v2),
             RemoveEdge(v1, v2) => AddEdge(v1, v2),
+            SetVertexAttribute(v, key, value, Some(previous)) => SetVertexAttribute(v, key, previous, Some(value)),
+            SetVertexAttribute(v, key, value, None) => RemoveVertexAttribute(v, key, value),
+            RemoveVertexAttribute(v, key, value) => SetVertexAttribute(v, key, value, None),
+            SetEdgeAttribute(v1, v2, key, value, Some(previous)) => SetEdgeAttribute(v1, v2, key, previous, Some(value)),
+            SetEdgeAttribute(v1, v2, key, value, None) => RemoveEdgeAttribute(v1, v2, key, value),
+            RemoveEdgeAttribute(v1, v2, key, value) => SetEdgeAttribute(v1, v2, key, value, None),
         }
     }
 
     /// Applies a vector of commands to a MutableGraph
-    pub fn apply_commands(commands: Vec<GraphCommand>, graph: &mut DirectedGraph) -> () {
+    pub fn apply_commands<G: MutableGraph>(commands: Vec<GraphCommand>, graph: &mut G) -> () {
         for command in commands.iter() {
             command.apply_to(graph);
         }
     }
 
     /// Applies the command to a MutableGraph.
-    pub fn apply_to(&self, graph: &mut DirectedGraph) {
+    pub fn apply_to<G: MutableGraph>(&self, graph: &mut G) {
         use GraphCommand::*;
         match self {
             AddVertex(v) => { graph.add_vertex(*v); }
             RemoveVertex(v) =>  { graph.remove_vertex(*v); }
             AddEdge(v1, v2) => { graph.add_edge(Edge(*v1, *v2)); }
             RemoveEdge(v1, v2) => { graph.remove_edge(Edge(*v1, *v2)); }
+            SetVertexAttribute(v, key, value, _) => { graph.set_vertex_attribute(*v, key.clone(), value.clone()); }
+            RemoveVertexAttribute(v, key, _) => { graph.remove_vertex_attribute(*v, key); }
+            SetEdgeAttribute(v1, v2, key, value, _) => { graph.set_edge_attribute(Edge(*v1, *v2), key.clone(), value.clone()); }
+            RemoveEdgeAttribute(v1, v2, key, _) => { graph.remove_edge_attribute(Edge(*v1, *v2), key); }
         }
     }
 
@@ -1,8 +1,10 @@
 use crate::graph::graph::*;
 use crate::graph::directed_graph::DirectedGraph;
 use crate::graph::command::GraphCommand;
+use crate::util::b_tree_bag::BTreeBag;
 
 /// Diff between 2 graphs
+#[derive(Debug, PartialEq)]
 pub struct StructureDiff {
     extra_vertices: Vec<VertexId>,   // in graph 1, not in graph 2
     missing_vertices: Vec<VertexId>, // missing in graph 2
@@ -10,12 +12,91 @@ pub struct StructureDiff {
     missing_edges: Vec<Edge>,        // missing in graph 2
 }
 
-// Compute the diff between 2 graphs, from the point of view of the first one
-pub fn diff(_g1: DirectedGraph, _g2: DirectedGraph) -> StructureDiff {
-    unimplemented!()
+/// Compute the diff between 2 graphs, from the point of view of the first one.
+///
+/// Edges are compared with their multiplicity: if graph 1 contains an edge three times, and
+/// graph 2 contains it once, the edge is reported twice as an extra edge.
+///
+/// All vectors of the resulting [`StructureDiff`] are sorted.
+///
+/// # Examples
+///
+/// ```
+/// use histo_graph_core::graph::directed_graph::DirectedGraph;
+/// use histo_graph_core::graph::graph::{VertexId, Edge};
+/// use histo_graph_core::graph::command::GraphCommand;
+/// use histo_graph_core::graph::diff::diff;
+///
+/// let mut g1 = DirectedGraph::new();
+/// g1.add_edge(Edge(VertexId(1), VertexId(2)));
+///
+/// let mut g2 = DirectedGraph::new();
+/// g2.add_edge(Edge(VertexId(2), VertexId(3)));
+///
+/// let d = diff(&g1, &g2);
+/// assert_eq!(d.extra_vertices(), &[VertexId(1)]);
+/// assert_eq!(d.missing_vertices(), &[VertexId(3)]);
+///
+/// GraphCommand::apply_commands(d.as_commands(), &mut g1);
+/// assert_eq!(g1, g2);
+/// ```
+///
+/// [`StructureDiff`]: ./struct.StructureDiff.html
+pub fn diff(g1: &DirectedGraph, g2: &DirectedGraph) -> StructureDiff {
+    let mut extra_vertices: Vec<VertexId> = g1
+        .vertices()
+        .filter(|&&v| !g2.contains_vertex(v))
+        .cloned()
+        .collect();
+    extra_vertices.sort();
+
+    let mut missing_vertices: Vec<VertexId> = g2
+        .vertices()
+        .filter(|&&v| !g1.contains_vertex(v))
+        .cloned()
+        .collect();
+    missing_vertices.sort();
+
+    let edges_1: BTreeBag<Edge> = g1.edges().cloned().collect();
+    let edges_2: BTreeBag<Edge> = g2.edges().cloned().collect();
+
+    StructureDiff {
+        extra_vertices,
+        missing_vertices,
+        extra_edges: edges_1.difference(&edges_2).cloned().collect(),
+        missing_edges: edges_2.difference(&edges_1).cloned().collect(),
+    }
 }
 
 impl StructureDiff {
+    /// The vertices that are in graph 1, but not in graph 2.
+    pub fn extra_vertices(&self) -> &[VertexId] {
+        &self.extra_vertices
+    }
+
+    /// The vertices that are in graph 2, but not in graph 1.
+    pub fn missing_vertices(&self) -> &[VertexId] {
+        &self.missing_vertices
+    }
+
+    /// The edges that are in graph 1, but not in graph 2.
+    pub fn extra_edges(&self) -> &[Edge] {
+        &self.extra_edges
+    }
+
+    /// The edges that are in graph 2, but not in graph 1.
+    pub fn missing_edges(&self) -> &[Edge] {
+        &self.missing_edges
+    }
+
+    /// Returns true if both graphs are equal.
+    pub fn is_empty(&self) -> bool {
+        self.extra_vertices.is_empty()
+            && self.missing_vertices.is_empty()
+            && self.extra_edges.is_empty()
+            && self.missing_edges.is_empty()
+    }
+
     /// Reverse the diff : provides the point of view of the second graph
     pub fn reverse(&self) -> StructureDiff {
         // Note : inefficient implementation which is using cloning.
@@ -47,3 +128,79 @@ impl StructureDiff {
         res
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::diff;
+    use crate::graph::command::GraphCommand;
+    use crate::graph::directed_graph::DirectedGraph;
+    use crate::graph::graph::{Edge, VertexId};
+
+    use rand::{thread_rng, Rng};
+
+    fn random_graph<R: Rng>(rng: &mut R) -> DirectedGraph {
+        // few distinct vertex ids, so that there are many duplicate edges and loops
+        let mut graph = DirectedGraph::new();
+        for _ in 0..5 {
+            graph.add_vertex(VertexId(rng.gen_range(0u64, 6u64)));
+        }
+        for _ in 0..15 {
+            graph.add_edge(Edge(VertexId(rng.gen_range(0u64, 6u64)), VertexId(rng.gen_range(0u64, 6u64))));
+        }
+        graph
+    }
+
+    #[test]
+    fn test_diff_equal() {
+        let mut g1 = DirectedGraph::new();
+        g1.add_edge(Edge(VertexId(0), VertexId(1)));
+
+        let mut g2 = DirectedGraph::new();
+        g2.add_edge(Edge(VertexId(0), VertexId(1)));
+
+        assert!(diff(&g1, &g2).is_empty());
+    }
+
+    #[test]
+    fn test_diff_multiplicity() {
+        let mut g1 = DirectedGraph::new();
+        g1.add_edge(Edge(VertexId(0), VertexId(1)));
+        g1.add_edge(Edge(VertexId(0), VertexId(1)));
+        g1.add_edge(Edge(VertexId(0), VertexId(1)));
+
+        let mut g2 = DirectedGraph::new();
+        g2.add_edge(Edge(VertexId(0), VertexId(1)));
+
+        let d = diff(&g1, &g2);
+        assert_eq!(d.extra_vertices(), &[]);
+        assert_eq!(d.missing_vertices(), &[]);
+        assert_eq!(d.extra_edges(), &[Edge(VertexId(0), VertexId(1)), Edge(VertexId(0), VertexId(1))]);
+        assert_eq!(d.missing_edges(), &[]);
+    }
+
+    #[test]
+    fn test_reverse() {
+        let mut g1 = DirectedGraph::new();
+        g1.add_edge(Edge(VertexId(0), VertexId(1)));
+
+        let mut g2 = DirectedGraph::new();
+        g2.add_edge(Edge(VertexId(1), VertexId(2)));
+
+        assert_eq!(diff(&g1, &g2).reverse(), diff(&g2, &g1));
+    }
+
+    #[test]
+    fn test_apply_diff_many() {
+        let mut rng = thread_rng();
+
+        for _ in 0..100 {
+            let mut g1 = random_graph(&mut rng);
+            let g2 = random_graph(&mut rng);
+
+            let d = diff(&g1, &g2);
+            GraphCommand::apply_commands(d.as_commands(), &mut g1);
+
+            assert_eq!(g1, g2);
+        }
+    }
+}
@@ -7,7 +7,7 @@ use std::collections::btree_map::BTreeMap;
 
 /// A directed graph structure that doesn't contain any information concerning the vertex or the
 /// edge attributes
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct DirectedGraph {
     // Each edge is indexed for by of both its vertices => 1 edge appears twice in the map
     edge_map: HashMap<VertexId, BTreeBag<Edge>>,
@@ -367,8 +367,10 @@ impl DirectedGraph {
         if let Some(found_v1) = self.edge_map.get_mut(&v1) {
             found |= found_v1.remove(&edge);
         }
-        if let Some(found_v2) = self.edge_map.get_mut(&v2) {
-            found |= found_v2.remove(&edge);
+        if v1 != v2 {
+            if let Some(found_v2) = self.edge_map.get_mut(&v2) {
+                found |= found_v2.remove(&edge);
+            }
         }
         found
     }
@@ -443,4 +445,16 @@ mod test {
 
         assert_ne!(graph_1, graph_2);
     }
+
+    #[test]
+    fn test_remove_one_of_two_loops() {
+        let mut graph = DirectedGraph::new();
+        graph.add_edge(Edge(VertexId(0), VertexId(0)));
+        graph.add_edge(Edge(VertexId(0), VertexId(0)));
+
+        assert!(graph.remove_edge(Edge(VertexId(0), VertexId(0))));
+
+        assert_eq!(graph.edge_count(), 1);
+        assert!(graph.contains_edge(Edge(VertexId(0), VertexId(0))));
+    }
 }
\ No newline at end of file
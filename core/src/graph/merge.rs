@@ -0,0 +1,518 @@
+//! Three-way merge of graphs.
+//!
+//! Two versions of a graph, `ours` and `theirs`, that have been derived from a common `base`
+//! version, are combined by computing the [`StructureDiff`]s from the base to each of them, and
+//! applying the changes of both sides to the base.
+//!
+//! [`StructureDiff`]: ../diff/struct.StructureDiff.html
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::graph::graph::*;
+use crate::graph::attributes::{Attributes, AttributeValue};
+use crate::graph::attributed_graph::AttributedGraph;
+use crate::graph::diff::{diff, StructureDiff};
+
+/// One of the two versions of a graph that are merged.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Side {
+    Ours,
+    Theirs,
+}
+
+/// A change of one side that contradicts a change of the other side.
+#[derive(PartialEq, Clone, Debug)]
+pub enum MergeConflict {
+
+    /// One side removed the vertex, while the other side added edges to it, or changed its
+    /// attributes. The merged graph keeps the vertex.
+    RemovedVertex { vertex: VertexId, removed_by: Side },
+
+    /// One side removed the edge, while the other side changed its attributes. The merged graph
+    /// keeps the edge.
+    RemovedEdge { edge: Edge, removed_by: Side },
+
+    /// One side added copies of the edge, while the other side removed copies of it. The numbers
+    /// are how often each side contains the edge. The merged graph holds the number of our side.
+    EdgeCount { edge: Edge, ours: usize, theirs: usize },
+
+    /// Both sides changed an attribute of the vertex to different values. `None` means that the
+    /// attribute has been removed. The merged graph holds the value of our side.
+    VertexAttribute { vertex: VertexId, key: String, ours: Option<AttributeValue>, theirs: Option<AttributeValue> },
+
+    /// Both sides changed an attribute of the edge to different values. `None` means that the
+    /// attribute has been removed. The merged graph holds the value of our side.
+    EdgeAttribute { edge: Edge, key: String, ours: Option<AttributeValue>, theirs: Option<AttributeValue> },
+}
+
+/// The result of a merge: the merged graph, and the conflicts that were found. If there are
+/// conflicts, the merged graph holds a preliminary resolution of them, as described at
+/// [`MergeConflict`].
+///
+/// [`MergeConflict`]: ./enum.MergeConflict.html
+#[derive(Debug)]
+pub struct MergeResult {
+    pub graph: AttributedGraph,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+impl MergeResult {
+
+    /// Returns true if the merge did not find any conflicts.
+    pub fn is_clean(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+}
+
+impl fmt::Display for Side {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Side::Ours => write!(f, "ours"),
+            Side::Theirs => write!(f, "theirs"),
+        }
+    }
+}
+
+fn fmt_value(value: &Option<AttributeValue>) -> String {
+    match value {
+        Some(value) => format!("'{}'", value),
+        None => "<removed>".to_string(),
+    }
+}
+
+impl fmt::Display for MergeConflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use MergeConflict::*;
+        match self {
+            RemovedVertex { vertex: VertexId(v), removed_by } =>
+                write!(f, "vertex {} was removed by {}, but changed by the other side", v, removed_by),
+            RemovedEdge { edge: Edge(VertexId(v1), VertexId(v2)), removed_by } =>
+                write!(f, "edge {} -> {} was removed by {}, but changed by the other side", v1, v2, removed_by),
+            EdgeCount { edge: Edge(VertexId(v1), VertexId(v2)), ours, theirs } =>
+                write!(f, "edge {} -> {}: ours {} time(s), theirs {} time(s)", v1, v2, ours, theirs),
+            VertexAttribute { vertex: VertexId(v), key, ours, theirs } =>
+                write!(f, "attribute '{}' of vertex {}: ours {}, theirs {}", key, v, fmt_value(ours), fmt_value(theirs)),
+            EdgeAttribute { edge: Edge(VertexId(v1), VertexId(v2)), key, ours, theirs } =>
+                write!(f, "attribute '{}' of edge {} -> {}: ours {}, theirs {}", key, v1, v2, fmt_value(ours), fmt_value(theirs)),
+        }
+    }
+}
+
+/// Combines two changes of the number of the same edge: if both sides added copies, or both sides
+/// removed copies, the larger change is applied, as the smaller one is taken to be part of it.
+/// Returns `None` if one side added copies, while the other side removed copies.
+fn combine(delta_ours: i64, delta_theirs: i64) -> Option<i64> {
+    if delta_ours >= 0 && delta_theirs >= 0 {
+        Some(delta_ours.max(delta_theirs))
+    } else if delta_ours <= 0 && delta_theirs <= 0 {
+        Some(delta_ours.min(delta_theirs))
+    } else {
+        None
+    }
+}
+
+/// Returns how often each edge is contained in `graph`.
+fn edge_counts(graph: &AttributedGraph) -> BTreeMap<Edge, i64> {
+    let mut counts = BTreeMap::new();
+    for &edge in graph.edges() {
+        *counts.entry(edge).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Returns the change of the number of each edge, from the point of view of graph 1.
+fn edge_deltas(d: &StructureDiff) -> BTreeMap<Edge, i64> {
+    let mut deltas = BTreeMap::new();
+    for &edge in d.extra_edges() {
+        *deltas.entry(edge).or_insert(0) += 1;
+    }
+    for &edge in d.missing_edges() {
+        *deltas.entry(edge).or_insert(0) -= 1;
+    }
+    deltas
+}
+
+/// A key of an attribute that has been changed differently on both sides, with the value of our
+/// side and the value of their side.
+type AttributeConflict = (String, Option<AttributeValue>, Option<AttributeValue>);
+
+/// Three-way merge of the attributes of a single vertex or edge. A side that does not contain
+/// the vertex or edge is considered to not have changed its attributes.
+/// Returns the merged attributes, and the keys that have been changed differently on both sides.
+fn merge_attributes(base: Option<&Attributes>, ours: Option<&Attributes>, theirs: Option<&Attributes>)
+    -> (Attributes, Vec<AttributeConflict>)
+{
+    let no_attributes = Attributes::new();
+    let base = base.unwrap_or(&no_attributes);
+    let ours = ours.unwrap_or(base);
+    let theirs = theirs.unwrap_or(base);
+
+    let keys: BTreeSet<&String> = base.keys().chain(ours.keys()).chain(theirs.keys()).collect();
+
+    let mut merged = Attributes::new();
+    let mut conflicts = Vec::new();
+    for key in keys {
+        let (b, o, t) = (base.get(key), ours.get(key), theirs.get(key));
+        let value = if o == t || t == b {
+            o
+        } else if o == b {
+            t
+        } else {
+            conflicts.push((key.clone(), o.cloned(), t.cloned()));
+            o
+        };
+        if let Some(value) = value {
+            merged.insert(key.clone(), value.clone());
+        }
+    }
+    (merged, conflicts)
+}
+
+/// Returns true if `side` changed the attributes of `vertex_id`, added edges to it, or changed the
+/// attributes of one of its edges.
+fn changed_vertex(base: &AttributedGraph, side: &AttributedGraph, side_diff: &StructureDiff, vertex_id: VertexId) -> bool {
+    let incident = |&Edge(v1, v2): &Edge| v1 == vertex_id || v2 == vertex_id;
+
+    side.vertex_attributes(vertex_id) != base.vertex_attributes(vertex_id)
+        || side_diff.extra_edges().iter().any(incident)
+        || side.edges().filter(|e| incident(e)).any(|&e| changed_edge(base, side, e))
+}
+
+/// Returns true if `side` changed the attributes of `edge`.
+fn changed_edge(base: &AttributedGraph, side: &AttributedGraph, edge: Edge) -> bool {
+    side.edge_attributes(edge).is_some() && side.edge_attributes(edge) != base.edge_attributes(edge)
+}
+
+/// Merges the graphs `ours` and `theirs`, which have both been derived from `base`.
+///
+/// Changes that only one side made are applied. Changes that both sides made in the same way
+/// are applied once. If both sides added copies of an edge, or both removed copies of it, the
+/// larger change is applied. Contradicting changes, including one side adding copies of an edge
+/// that the other side removed copies of, are reported as [`MergeConflict`]s.
+///
+/// # Examples
+///
+/// ```
+/// use histo_graph_core::graph::attributed_graph::AttributedGraph;
+/// use histo_graph_core::graph::graph::{VertexId, Edge};
+/// use histo_graph_core::graph::merge::merge;
+///
+/// let mut base = AttributedGraph::new();
+/// base.add_vertex(VertexId(1));
+///
+/// let mut ours = base.clone();
+/// ours.add_edge(Edge(VertexId(1), VertexId(2)));
+///
+/// let mut theirs = base.clone();
+/// theirs.add_vertex(VertexId(3));
+///
+/// let result = merge(&base, &ours, &theirs);
+/// assert!(result.is_clean());
+/// assert!(result.graph.graph().contains_edge(Edge(VertexId(1), VertexId(2))));
+/// assert!(result.graph.graph().contains_vertex(VertexId(3)));
+/// ```
+///
+/// [`MergeConflict`]: ./enum.MergeConflict.html
+pub fn merge(base: &AttributedGraph, ours: &AttributedGraph, theirs: &AttributedGraph) -> MergeResult {
+    // the diffs are computed from the point of view of the sides, so that the "extra" elements are
+    // the added ones, and the "missing" elements are the removed ones
+    let diff_ours = diff(ours.graph(), base.graph());
+    let diff_theirs = diff(theirs.graph(), base.graph());
+
+    let mut conflicts: Vec<MergeConflict> = Vec::new();
+
+    // vertices
+    let mut added_vertices: BTreeSet<VertexId> = BTreeSet::new();
+    added_vertices.extend(diff_ours.extra_vertices());
+    added_vertices.extend(diff_theirs.extra_vertices());
+
+    let mut removed_vertices: BTreeSet<VertexId> = BTreeSet::new();
+    for (removed, other, other_diff, removed_by) in [
+        (diff_ours.missing_vertices(), theirs, &diff_theirs, Side::Ours),
+        (diff_theirs.missing_vertices(), ours, &diff_ours, Side::Theirs),
+    ] {
+        for &vertex_id in removed {
+            if other.graph().contains_vertex(vertex_id) && changed_vertex(base, other, other_diff, vertex_id) {
+                conflicts.push(MergeConflict::RemovedVertex { vertex: vertex_id, removed_by });
+            } else {
+                removed_vertices.insert(vertex_id);
+            }
+        }
+    }
+    // a vertex that only one side removed and the other side changed is kept
+    for conflict in conflicts.iter() {
+        if let MergeConflict::RemovedVertex { vertex, .. } = conflict {
+            removed_vertices.remove(vertex);
+        }
+    }
+
+    // edges
+    let deltas_ours = edge_deltas(&diff_ours);
+    let deltas_theirs = edge_deltas(&diff_theirs);
+    let edges: BTreeSet<Edge> = deltas_ours.keys().chain(deltas_theirs.keys()).cloned().collect();
+    let base_counts = edge_counts(base);
+
+    let mut edge_deltas: BTreeMap<Edge, i64> = BTreeMap::new();
+    for edge in edges {
+        let delta_ours = deltas_ours.get(&edge).cloned().unwrap_or(0);
+        let delta_theirs = deltas_theirs.get(&edge).cloned().unwrap_or(0);
+        let base_count = base_counts.get(&edge).cloned().unwrap_or(0);
+
+        let mut delta = match combine(delta_ours, delta_theirs) {
+            Some(delta) => delta,
+            None => {
+                conflicts.push(MergeConflict::EdgeCount {
+                    edge,
+                    ours: (base_count + delta_ours) as usize,
+                    theirs: (base_count + delta_theirs) as usize,
+                });
+                delta_ours
+            }
+        };
+
+        if base_count + delta == 0 && base_count > 0 {
+            // the edge is removed: check that the other side did not change its attributes
+            let keep = if delta_ours < 0 && changed_edge(base, theirs, edge) {
+                conflicts.push(MergeConflict::RemovedEdge { edge, removed_by: Side::Ours });
+                Some(delta_theirs)
+            } else if delta_theirs < 0 && changed_edge(base, ours, edge) {
+                conflicts.push(MergeConflict::RemovedEdge { edge, removed_by: Side::Theirs });
+                Some(delta_ours)
+            } else {
+                None
+            };
+            if let Some(side_delta) = keep {
+                delta = side_delta;
+            }
+        }
+        edge_deltas.insert(edge, delta);
+    }
+
+    // apply the structural changes to a copy of the base
+    let mut graph = base.clone();
+    for &vertex_id in added_vertices.iter() {
+        graph.add_vertex(vertex_id);
+    }
+    for (&edge, &delta) in edge_deltas.iter() {
+        for _ in 0..delta {
+            graph.add_edge(edge);
+        }
+        for _ in delta..0 {
+            graph.remove_edge(edge);
+        }
+    }
+    for &vertex_id in removed_vertices.iter() {
+        graph.remove_vertex(vertex_id);
+    }
+
+    // merge the attributes of all vertices and edges of the merged graph
+    let vertices: Vec<VertexId> = graph.vertices().cloned().collect();
+    for vertex_id in vertices {
+        let (attributes, attribute_conflicts) = merge_attributes(
+            base.vertex_attributes(vertex_id),
+            ours.vertex_attributes(vertex_id),
+            theirs.vertex_attributes(vertex_id),
+        );
+        graph.add_vertex_with_attributes(vertex_id, attributes);
+        conflicts.extend(attribute_conflicts
+            .into_iter()
+            .map(|(key, ours, theirs)| MergeConflict::VertexAttribute { vertex: vertex_id, key, ours, theirs }));
+    }
+
+    let mut edges: Vec<Edge> = graph.edges().cloned().collect();
+    edges.sort();
+    edges.dedup();
+    for edge in edges {
+        let (attributes, attribute_conflicts) = merge_attributes(
+            base.edge_attributes(edge),
+            ours.edge_attributes(edge),
+            theirs.edge_attributes(edge),
+        );
+        let removed_keys: Vec<String> = graph
+            .edge_attributes(edge)
+            .map(|current| current.keys().filter(|key| !attributes.contains_key(*key)).cloned().collect())
+            .unwrap_or_default();
+        for key in removed_keys {
+            graph.remove_edge_attribute(edge, &key);
+        }
+        for (key, value) in attributes {
+            graph.set_edge_attribute(edge, key, value);
+        }
+        conflicts.extend(attribute_conflicts
+            .into_iter()
+            .map(|(key, ours, theirs)| MergeConflict::EdgeAttribute { edge, key, ours, theirs }));
+    }
+
+    MergeResult {
+        graph,
+        conflicts,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn e(v1: u64, v2: u64) -> Edge {
+        Edge(VertexId(v1), VertexId(v2))
+    }
+
+    fn edge_count(graph: &AttributedGraph, edge: Edge) -> usize {
+        graph.edges().filter(|&&e2| e2 == edge).count()
+    }
+
+    #[test]
+    fn test_merge_independent_changes() {
+        let mut base = AttributedGraph::new();
+        base.add_edge(e(1, 2));
+        base.add_edge(e(2, 3));
+
+        let mut ours = base.clone();
+        ours.remove_edge(e(1, 2));
+        ours.set_vertex_attribute(VertexId(1), "name".to_string(), "one".into());
+
+        let mut theirs = base.clone();
+        theirs.remove_vertex(VertexId(3));
+        theirs.add_edge(e(4, 1));
+
+        let result = merge(&base, &ours, &theirs);
+        assert!(result.is_clean());
+
+        let mut expected = AttributedGraph::new();
+        expected.add_vertex(VertexId(2));
+        expected.add_edge(e(4, 1));
+        expected.set_vertex_attribute(VertexId(1), "name".to_string(), "one".into());
+        assert_eq!(result.graph, expected);
+    }
+
+    #[test]
+    fn test_merge_same_change_is_applied_once() {
+        let mut base = AttributedGraph::new();
+        base.add_edge(e(1, 2));
+
+        let mut ours = base.clone();
+        ours.add_edge(e(2, 3));
+        ours.remove_edge(e(1, 2));
+
+        let theirs = ours.clone();
+
+        let result = merge(&base, &ours, &theirs);
+        assert!(result.is_clean());
+        assert_eq!(result.graph, ours);
+    }
+
+    #[test]
+    fn test_merge_edge_counts_take_larger_change() {
+        let mut base = AttributedGraph::new();
+        base.add_edge(e(1, 2));
+        base.add_edge(e(2, 3));
+        base.add_edge(e(2, 3));
+        base.add_edge(e(2, 3));
+
+        let mut ours = base.clone();
+        ours.add_edge(e(1, 2));
+        ours.remove_edge(e(2, 3));
+
+        let mut theirs = base.clone();
+        theirs.add_edge(e(1, 2));
+        theirs.add_edge(e(1, 2));
+        theirs.remove_edge(e(2, 3));
+        theirs.remove_edge(e(2, 3));
+
+        let result = merge(&base, &ours, &theirs);
+        assert!(result.is_clean());
+        assert_eq!(edge_count(&result.graph, e(1, 2)), 3);
+        assert_eq!(edge_count(&result.graph, e(2, 3)), 1);
+    }
+
+    #[test]
+    fn test_merge_edge_count_conflict() {
+        let mut base = AttributedGraph::new();
+        base.add_edge(e(1, 2));
+        base.add_edge(e(1, 2));
+
+        let mut ours = base.clone();
+        ours.add_edge(e(1, 2));
+
+        let mut theirs = base.clone();
+        theirs.remove_edge(e(1, 2));
+
+        let result = merge(&base, &ours, &theirs);
+        assert_eq!(result.conflicts, vec![MergeConflict::EdgeCount { edge: e(1, 2), ours: 3, theirs: 1 }]);
+        assert_eq!(result.graph, ours);
+    }
+
+    #[test]
+    fn test_merge_attribute_conflict() {
+        let mut base = AttributedGraph::new();
+        base.set_vertex_attribute(VertexId(1), "color".to_string(), "red".into());
+
+        let mut ours = base.clone();
+        ours.set_vertex_attribute(VertexId(1), "color".to_string(), "blue".into());
+
+        let mut theirs = base.clone();
+        theirs.remove_vertex_attribute(VertexId(1), "color");
+
+        let result = merge(&base, &ours, &theirs);
+        assert_eq!(result.conflicts, vec![MergeConflict::VertexAttribute {
+            vertex: VertexId(1),
+            key: "color".to_string(),
+            ours: Some("blue".into()),
+            theirs: None,
+        }]);
+        assert_eq!(result.graph, ours);
+    }
+
+    #[test]
+    fn test_merge_edge_attributes() {
+        let mut base = AttributedGraph::new();
+        base.set_edge_attribute(e(1, 2), "label".to_string(), "knows".into());
+        base.set_edge_attribute(e(1, 2), "weight".to_string(), 1i64.into());
+
+        let mut ours = base.clone();
+        ours.remove_edge_attribute(e(1, 2), "weight");
+
+        let mut theirs = base.clone();
+        theirs.set_edge_attribute(e(1, 2), "label".to_string(), "likes".into());
+
+        let result = merge(&base, &ours, &theirs);
+        assert!(result.is_clean());
+
+        let mut expected = AttributedGraph::new();
+        expected.set_edge_attribute(e(1, 2), "label".to_string(), "likes".into());
+        assert_eq!(result.graph, expected);
+    }
+
+    #[test]
+    fn test_merge_removed_vertex_conflict() {
+        let mut base = AttributedGraph::new();
+        base.add_vertex(VertexId(1));
+
+        let mut ours = base.clone();
+        ours.remove_vertex(VertexId(1));
+
+        let mut theirs = base.clone();
+        theirs.add_edge(e(1, 2));
+
+        let result = merge(&base, &ours, &theirs);
+        assert_eq!(result.conflicts, vec![MergeConflict::RemovedVertex { vertex: VertexId(1), removed_by: Side::Ours }]);
+        assert_eq!(result.graph, theirs);
+    }
+
+    #[test]
+    fn test_merge_removed_edge_conflict() {
+        let mut base = AttributedGraph::new();
+        base.add_edge(e(1, 2));
+
+        let mut ours = base.clone();
+        ours.set_edge_attribute(e(1, 2), "label".to_string(), "knows".into());
+
+        let mut theirs = base.clone();
+        theirs.remove_edge(e(1, 2));
+
+        let result = merge(&base, &ours, &theirs);
+        assert_eq!(result.conflicts, vec![MergeConflict::RemovedEdge { edge: e(1, 2), removed_by: Side::Theirs }]);
+        assert_eq!(result.graph, ours);
+    }
+}
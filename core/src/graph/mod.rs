@@ -1,4 +1,8 @@
+pub mod attributed_graph;
+pub mod attributes;
 pub mod command;
 pub mod diff;
 pub mod directed_graph;
 pub mod graph;
+pub mod merge;
+pub mod mutable_graph;
@@ -0,0 +1,63 @@
+use crate::graph::graph::{VertexId, Edge};
+use crate::graph::attributes::AttributeValue;
+use crate::graph::directed_graph::DirectedGraph;
+
+/// A graph that can be manipulated by [`GraphCommand`]s.
+///
+/// [`GraphCommand`]: ../command/enum.GraphCommand.html
+pub trait MutableGraph {
+
+    /// Adds a vertex to the graph. Returns true if the graph already contained `vertex_id`.
+    fn add_vertex(&mut self, vertex_id: VertexId) -> bool;
+
+    /// Removes a vertex and all its edges from the graph.
+    /// Returns true if the graph contained `vertex_id` before the removal.
+    fn remove_vertex(&mut self, vertex_id: VertexId) -> bool;
+
+    /// Adds an edge to the graph.
+    fn add_edge(&mut self, edge: Edge);
+
+    /// Removes an edge from the graph.
+    /// Returns true if the graph contained the `edge` before the removal.
+    fn remove_edge(&mut self, edge: Edge) -> bool;
+
+    /// Sets the attribute `key` of the vertex `vertex_id` to `value`.
+    fn set_vertex_attribute(&mut self, vertex_id: VertexId, key: String, value: AttributeValue);
+
+    /// Removes the attribute `key` of the vertex `vertex_id`.
+    fn remove_vertex_attribute(&mut self, vertex_id: VertexId, key: &str);
+
+    /// Sets the attribute `key` of `edge` to `value`.
+    fn set_edge_attribute(&mut self, edge: Edge, key: String, value: AttributeValue);
+
+    /// Removes the attribute `key` of `edge`.
+    fn remove_edge_attribute(&mut self, edge: Edge, key: &str);
+}
+
+/// A `DirectedGraph` does not hold any attributes, so setting or removing attributes has no
+/// effect on it.
+impl MutableGraph for DirectedGraph {
+    fn add_vertex(&mut self, vertex_id: VertexId) -> bool {
+        DirectedGraph::add_vertex(self, vertex_id)
+    }
+
+    fn remove_vertex(&mut self, vertex_id: VertexId) -> bool {
+        DirectedGraph::remove_vertex(self, vertex_id)
+    }
+
+    fn add_edge(&mut self, edge: Edge) {
+        DirectedGraph::add_edge(self, edge)
+    }
+
+    fn remove_edge(&mut self, edge: Edge) -> bool {
+        DirectedGraph::remove_edge(self, edge)
+    }
+
+    fn set_vertex_attribute(&mut self, _vertex_id: VertexId, _key: String, _value: AttributeValue) {}
+
+    fn remove_vertex_attribute(&mut self, _vertex_id: VertexId, _key: &str) {}
+
+    fn set_edge_attribute(&mut self, _edge: Edge, _key: String, _value: AttributeValue) {}
+
+    fn remove_edge_attribute(&mut self, _edge: Edge, _key: &str) {}
+}
@@ -25,7 +25,7 @@ use std::collections::btree_map::BTreeMap;
 ///     println!("{}", i);
 /// }
 /// ```
-#[derive(Debug, Hash, PartialEq)]
+#[derive(Debug, Clone, Hash, PartialEq)]
 pub struct BTreeBag<T>
     where T: Ord {
     inner: BTreeMap<T, usize>
@@ -165,6 +165,68 @@ impl<T> BTreeBag<T>
     pub fn len(&self) -> usize {
         self.iter().count()
     }
+
+    /// Returns the number of times `t` is contained in the BTreeBag.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::util::b_tree_bag::BTreeBag;
+    ///
+    /// let mut bag: BTreeBag<u32> = BTreeBag::new();
+    /// bag.insert(2);
+    /// bag.insert(2);
+    ///
+    /// assert_eq!(bag.count(&1), 0);
+    /// assert_eq!(bag.count(&2), 2);
+    /// ```
+    pub fn count(&self, t: &T) -> usize {
+        self.inner.get(t).cloned().unwrap_or(0)
+    }
+
+    /// An iterator that visits the elements that are in `self`, but not in `other`, in sorted
+    /// order. An element that is contained `n` times in `self` and `m` times in `other` is visited
+    /// `n - m` times if `n > m`, and not at all otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use histo_graph_core::util::b_tree_bag::BTreeBag;
+    ///
+    /// let mut bag_1: BTreeBag<u32> = BTreeBag::new();
+    /// bag_1.insert(1);
+    /// bag_1.insert(2);
+    /// bag_1.insert(2);
+    /// bag_1.insert(2);
+    ///
+    /// let mut bag_2: BTreeBag<u32> = BTreeBag::new();
+    /// bag_2.insert(2);
+    /// bag_2.insert(3);
+    ///
+    /// let vec: Vec<&u32> = bag_1.difference(&bag_2).collect();
+    /// assert_eq!(vec, vec![&1, &2, &2]);
+    /// ```
+    pub fn difference<'a>(&'a self, other: &'a BTreeBag<T>) -> impl Iterator<Item=&'a T> {
+        self.inner.iter().flat_map(move |(t, &count)| {
+            let i: DuplicationIter<'a, T> = DuplicationIter {
+                element: t,
+                total_count: count.saturating_sub(other.count(t)),
+                current_count: 0,
+            };
+            i
+        })
+    }
+}
+
+impl<T> std::iter::FromIterator<T> for BTreeBag<T>
+    where T: Ord {
+    fn from_iter<I: IntoIterator<Item=T>>(iter: I) -> BTreeBag<T> {
+        let mut bag = BTreeBag::new();
+        for t in iter {
+            bag.insert(t);
+        }
+        bag
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,78 @@
+//! Measures the throughput of loading and saving a large graph with a [`DirectoryStore`], depending
+//! on the number of objects that are read or written at the same time, which is set with a
+//! [`LimitedStore`].
+//!
+//! The graph has 100 000 edges, or the number of edges in the environment variable
+//! `GRAPH_IO_EDGES`, between a tenth as many vertices. Saving the graph, when all of its objects
+//! exist already, checks the existence of every object. Loading the graph through a new
+//! [`CachedStore`] reads every vertex once, instead of once per edge.
+//!
+//! [`DirectoryStore`]: ../histo_graph_file/store/directory/struct.DirectoryStore.html
+//! [`LimitedStore`]: ../histo_graph_file/store/limited/struct.LimitedStore.html
+//! [`CachedStore`]: ../histo_graph_file/store/cached/struct.CachedStore.html
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use histo_graph_core::graph::{
+    graph::{VertexId, Edge},
+    attributed_graph::AttributedGraph,
+};
+use histo_graph_file::{
+    file_storage::{save_graph_as, load_graph},
+    store::{DirectoryStore, LimitedStore, CachedStore, DEFAULT_CACHE_CAPACITY},
+};
+use std::{env, fs};
+use tokio::runtime::Runtime;
+
+const DEFAULT_EDGES: u64 = 100_000;
+
+const MAX_CONCURRENCIES: [usize; 4] = [1, 8, 64, 512];
+
+const BASE_PATH: &str = "../target/bench/graph_io/";
+
+fn edge_count() -> u64 {
+    env::var("GRAPH_IO_EDGES").ok().and_then(|edges| edges.parse().ok()).unwrap_or(DEFAULT_EDGES)
+}
+
+/// Returns a graph with `edges` edges. Each vertex has ten outgoing edges.
+fn large_graph(edges: u64) -> AttributedGraph {
+    let vertices = edges / 10;
+    let mut graph = AttributedGraph::new();
+    for k in 1..=10 {
+        for i in 0..vertices {
+            graph.add_edge(Edge(VertexId(i), VertexId((i + k) % vertices)));
+        }
+    }
+    graph
+}
+
+fn bench_graph_io(c: &mut Criterion) {
+    let mut rt = Runtime::new().unwrap();
+    let edges = edge_count();
+    let graph = large_graph(edges);
+
+    let _ = fs::remove_dir_all(BASE_PATH);
+    rt.block_on(save_graph_as(&DirectoryStore::new(BASE_PATH), "main".to_string(), &graph)).unwrap();
+
+    let mut group = c.benchmark_group("graph_io");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(edges));
+
+    for &max_concurrency in MAX_CONCURRENCIES.iter() {
+        let store = LimitedStore::new(DirectoryStore::new(BASE_PATH), max_concurrency);
+        group.bench_with_input(BenchmarkId::new("load_graph", max_concurrency), &store, |b, store| b.iter(|| {
+            rt.block_on(load_graph(store, "main".to_string())).unwrap()
+        }));
+        group.bench_with_input(BenchmarkId::new("load_graph, cached", max_concurrency), &max_concurrency, |b, &max_concurrency| b.iter(|| {
+            let store = CachedStore::new(LimitedStore::new(DirectoryStore::new(BASE_PATH), max_concurrency), DEFAULT_CACHE_CAPACITY);
+            rt.block_on(load_graph(&store, "main".to_string())).unwrap()
+        }));
+        group.bench_with_input(BenchmarkId::new("save_graph, all objects exist", max_concurrency), &store, |b, store| b.iter(|| {
+            rt.block_on(save_graph_as(store, "copy".to_string(), &graph)).unwrap()
+        }));
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_graph_io);
+criterion_main!(benches);
@@ -0,0 +1,75 @@
+//! Measures how long it takes to save a version of a large graph, with a [`DirectoryStore`].
+//!
+//! Saving a graph to an empty store writes every object. Saving a new version of a graph, with a
+//! single added vertex, only writes the objects that do not exist yet, and checks the existence of
+//! all others. Then, saving a graph mostly serializes and hashes its objects, and checks for their
+//! files, which is faster with BLAKE3, see [`HashAlgorithm`].
+//!
+//! [`DirectoryStore`]: ../histo_graph_file/store/directory/struct.DirectoryStore.html
+//! [`HashAlgorithm`]: ../histo_graph_file/store/config/enum.HashAlgorithm.html
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use histo_graph_core::graph::{
+    graph::{VertexId, Edge},
+    attributed_graph::AttributedGraph,
+};
+use histo_graph_file::{
+    file_storage::save_graph_as,
+    store::{DirectoryStore, HashAlgorithm, ObjectStore},
+};
+use std::fs;
+use tokio::runtime::Runtime;
+
+const VERTICES: u64 = 10_000;
+
+const BASE_PATH: &str = "../target/bench/save_graph/";
+
+const BLAKE3_PATH: &str = "../target/bench/save_graph_blake3/";
+
+/// Returns a graph with `VERTICES` vertices, connected in a chain.
+fn large_graph() -> AttributedGraph {
+    let mut graph = AttributedGraph::new();
+    for i in 1..VERTICES {
+        graph.add_edge(Edge(VertexId(i), VertexId(i + 1)));
+    }
+    graph
+}
+
+fn save<S>(rt: &mut Runtime, store: &S, graph: &AttributedGraph)
+    where S: ObjectStore
+{
+    rt.block_on(save_graph_as(store, "main".to_string(), graph)).unwrap();
+}
+
+fn bench_save_graph(c: &mut Criterion) {
+    let mut rt = Runtime::new().unwrap();
+    let graph = large_graph();
+    let mut changed = graph.clone();
+    changed.add_vertex(VertexId(0));
+
+    let mut group = c.benchmark_group("save_graph");
+    group.sample_size(10);
+
+    group.bench_function("all objects new", |b| b.iter_batched(
+        || {
+            let _ = fs::remove_dir_all(BASE_PATH);
+            DirectoryStore::new(BASE_PATH)
+        },
+        |store| save(&mut rt, &store, &graph),
+        BatchSize::PerIteration,
+    ));
+
+    let store = DirectoryStore::new(BASE_PATH);
+    save(&mut rt, &store, &graph);
+    group.bench_function("one vertex added", |b| b.iter(|| save(&mut rt, &store, &changed)));
+
+    let _ = fs::remove_dir_all(BLAKE3_PATH);
+    let store = DirectoryStore::new(BLAKE3_PATH).with_hash_algorithm(HashAlgorithm::Blake3);
+    save(&mut rt, &store, &graph);
+    group.bench_function("one vertex added, blake3", |b| b.iter(|| save(&mut rt, &store, &changed)));
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_save_graph);
+criterion_main!(benches);
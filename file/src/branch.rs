@@ -0,0 +1,158 @@
+//! Implements the management of branches: named references to the latest commit of a version of
+//! a graph, and the `HEAD`, which holds the name of the branch that is currently worked on.
+
+use crate::{
+    error::{Result, Error::{InvalidRefName, BranchExists, CurrentBranch}},
+    file_storage::read_ref,
+    store::ObjectStore,
+};
+
+/// The name of the branch that `HEAD` refers to, if `HEAD` has never been set.
+pub const DEFAULT_BRANCH: &str = "current";
+
+/// Checks that `name` can be used as the name of a branch, i.e. that it is a plain file name.
+/// Every name that is passed in is checked, not only the names of new branches, so that no name
+/// addresses a file outside of the references, like `../HEAD`.
+fn check_branch_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.starts_with('.') || name.contains('/') || name.contains('\\') {
+        Err(InvalidRefName { name: name.to_string() })
+    } else {
+        Ok(())
+    }
+}
+
+/// Returns the name of the current branch.
+pub async fn read_head<S>(store: &S) -> Result<String>
+    where S: ObjectStore + ?Sized
+{
+    Ok(store.read_head().await?.unwrap_or_else(|| DEFAULT_BRANCH.to_string()))
+}
+
+/// Sets the current branch to `name`, which must be an existing branch.
+pub async fn switch_branch<S>(store: &S, name: &str) -> Result<()>
+    where S: ObjectStore + ?Sized
+{
+    check_branch_name(name)?;
+    let _guard = store.lock().await?;
+    read_ref(store, name).await?;
+    store.write_head(name).await
+}
+
+/// Returns the names of all branches, in sorted order.
+pub async fn list_branches<S>(store: &S) -> Result<Vec<String>>
+    where S: ObjectStore + ?Sized
+{
+    store.list_refs().await
+}
+
+/// Creates the branch `name`, which refers to the same commit as the existing branch `from`.
+/// Fails if a branch called `name` already exists.
+pub async fn create_branch<S>(store: &S, name: &str, from: &str) -> Result<()>
+    where S: ObjectStore + ?Sized
+{
+    check_branch_name(name)?;
+    check_branch_name(from)?;
+    let _guard = store.lock().await?;
+    let hash = read_ref(store, from).await?;
+    if store.read_ref(name).await?.is_some() {
+        return Err(BranchExists { name: name.to_string() });
+    }
+    store.compare_and_swap_ref(name, None, hash).await
+}
+
+/// Renames the branch `from` to `to`. If `from` is the current branch, `to` becomes the current
+/// branch. Fails if a branch called `to` already exists.
+pub async fn rename_branch<S>(store: &S, from: &str, to: &str) -> Result<()>
+    where S: ObjectStore + ?Sized
+{
+    check_branch_name(from)?;
+    check_branch_name(to)?;
+    let _guard = store.lock().await?;
+    let hash = read_ref(store, from).await?;
+    if store.read_ref(to).await?.is_some() {
+        return Err(BranchExists { name: to.to_string() });
+    }
+    store.compare_and_swap_ref(to, None, hash).await?;
+    store.remove_ref(from).await?;
+
+    if read_head(store).await? == from {
+        store.write_head(to).await?;
+    }
+    Ok(())
+}
+
+/// Deletes the branch `name`. The commits of the branch are not deleted. The current branch
+/// cannot be deleted.
+pub async fn delete_branch<S>(store: &S, name: &str) -> Result<()>
+    where S: ObjectStore + ?Sized
+{
+    check_branch_name(name)?;
+    let _guard = store.lock().await?;
+    if read_head(store).await? == name {
+        return Err(CurrentBranch { name: name.to_string() });
+    }
+    store.remove_ref(name).await
+}
+
+#[cfg(test)]
+mod test {
+    use tokio::runtime::Runtime;
+    use tokio::fs;
+    use histo_graph_core::graph::{
+        graph::VertexId,
+        attributed_graph::AttributedGraph,
+    };
+
+    use super::*;
+    use crate::{
+        file_storage::{save_graph_as, load_graph},
+        store::DirectoryStore,
+    };
+
+    #[test]
+    fn test_branches() -> Result<()> {
+        let mut rt = Runtime::new()?;
+        rt.block_on(async {
+            // use a separate store, so that the list of branches is not affected by other tests
+            let store = DirectoryStore::new("../target/test/branch_store/");
+            let _ = fs::remove_dir_all(store.base_path()).await;
+
+            assert_eq!(read_head(&store).await?, DEFAULT_BRANCH);
+
+            let mut graph = AttributedGraph::new();
+            graph.add_vertex(VertexId(1));
+            save_graph_as(&store, DEFAULT_BRANCH.to_string(), &graph).await?;
+
+            create_branch(&store, "feature", DEFAULT_BRANCH).await?;
+            assert!(matches!(create_branch(&store, "feature", DEFAULT_BRANCH).await, Err(BranchExists { name }) if name == "feature"));
+            assert_eq!(list_branches(&store).await?, vec![DEFAULT_BRANCH.to_string(), "feature".to_string()]);
+
+            switch_branch(&store, "feature").await?;
+            assert_eq!(read_head(&store).await?, "feature");
+            assert!(matches!(delete_branch(&store, "feature").await, Err(CurrentBranch { name }) if name == "feature"));
+
+            graph.add_vertex(VertexId(2));
+            save_graph_as(&store, read_head(&store).await?, &graph).await?;
+            assert_eq!(load_graph(&store, DEFAULT_BRANCH.to_string()).await?.graph().vertex_count(), 1);
+
+            rename_branch(&store, "feature", "other").await?;
+            assert_eq!(read_head(&store).await?, "other");
+            assert_eq!(load_graph(&store, "other".to_string()).await?, graph);
+
+            switch_branch(&store, DEFAULT_BRANCH).await?;
+            delete_branch(&store, "other").await?;
+            assert_eq!(list_branches(&store).await?, vec![DEFAULT_BRANCH.to_string()]);
+
+            // no name addresses a file outside of the references
+            assert!(matches!(delete_branch(&store, "../HEAD").await, Err(InvalidRefName { .. })));
+            assert!(matches!(rename_branch(&store, "../config", "x").await, Err(InvalidRefName { .. })));
+            assert!(matches!(create_branch(&store, "x", "../HEAD").await, Err(InvalidRefName { .. })));
+            for name in ["", "..", "../HEAD", "/etc/passwd", "a/../../HEAD"].iter() {
+                assert!(matches!(store.read_ref(name).await, Err(InvalidRefName { .. })), "{}", name);
+                assert!(matches!(store.remove_ref(name).await, Err(InvalidRefName { .. })), "{}", name);
+            }
+            assert!(store.base_path().join("HEAD").exists());
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,191 @@
+//! Implements the comparison of two stored versions of a graph.
+//!
+//! The versions are compared by their trees of vertices and edges, without loading them: chunks
+//! that are shared by both versions are skipped, so the comparison of two versions, that differ by
+//! a few vertices, reads a few objects, however large the graph is.
+
+use std::collections::BTreeMap;
+use histo_graph_core::graph::{
+    graph::{VertexId, Edge},
+    attributes::Attributes,
+};
+
+use crate::{
+    Hash,
+    error::Result,
+    file_storage::{read_object, read_ref, read_all_objects, read_all_edges},
+    object::{StoredVertex, HashEdge, GraphHash, Commit, from_stored_attributes},
+    store::ObjectStore,
+    tree::diff_trees,
+};
+
+/// The difference between two versions of a graph.
+///
+/// A vertex whose attributes differ is both removed, with its attributes in the first version,
+/// and added, with its attributes in the second version. The same holds for edges. A graph can
+/// contain equal edges, so an edge that one version contains more often than the other is listed
+/// once per additional copy. All vectors are sorted.
+#[derive(Debug, Default, PartialEq)]
+pub struct GraphDiff {
+
+    /// The vertices that are in the second version, but not in the first one.
+    pub added_vertices: Vec<(VertexId, Attributes)>,
+
+    /// The vertices that are in the first version, but not in the second one.
+    pub removed_vertices: Vec<(VertexId, Attributes)>,
+
+    /// The edges that are in the second version, but not in the first one.
+    pub added_edges: Vec<(Edge, Attributes)>,
+
+    /// The edges that are in the first version, but not in the second one.
+    pub removed_edges: Vec<(Edge, Attributes)>,
+}
+
+impl GraphDiff {
+
+    /// Returns true if both versions are equal.
+    pub fn is_empty(&self) -> bool {
+        self.added_vertices.is_empty()
+            && self.removed_vertices.is_empty()
+            && self.added_edges.is_empty()
+            && self.removed_edges.is_empty()
+    }
+}
+
+async fn read_vertices<S>(store: &S, hashes: Vec<Hash>) -> Result<BTreeMap<VertexId, Attributes>>
+    where S: ObjectStore + ?Sized
+{
+    let vertices: Vec<StoredVertex> = read_all_objects(store, hashes).await?;
+    Ok(vertices
+        .into_iter()
+        .map(|StoredVertex { id, attributes }| (VertexId(id), from_stored_attributes(attributes)))
+        .collect())
+}
+
+/// Compares the graphs with the [`Hash`]es `graph_hash_1` and `graph_hash_2`.
+///
+/// [`Hash`]: ../struct.Hash.html
+async fn diff_graphs<S>(store: &S, graph_hash_1: Hash, graph_hash_2: Hash) -> Result<GraphDiff>
+    where S: ObjectStore + ?Sized
+{
+    if graph_hash_1 == graph_hash_2 {
+        return Ok(GraphDiff::default());
+    }
+    let graph_1: GraphHash = read_object(store, graph_hash_1).await?;
+    let graph_2: GraphHash = read_object(store, graph_hash_2).await?;
+
+    let (removed, added) = diff_trees::<S, StoredVertex>(store, graph_1.vertex_tree_hash, graph_2.vertex_tree_hash).await?;
+    let removed_vertices = read_vertices(store, removed).await?;
+    let added_vertices = read_vertices(store, added).await?;
+
+    // an edge refers to the hashes of its vertices, so it is stored anew when the attributes of one
+    // of its vertices change, although the edge itself does not change
+    let (removed, added) = diff_trees::<S, HashEdge>(store, graph_1.edge_tree_hash, graph_2.edge_tree_hash).await?;
+    let mut removed_edges: Vec<(Edge, Attributes)> = read_all_edges(store, removed).await?;
+    let mut added_edges: Vec<(Edge, Attributes)> = Vec::new();
+    for edge in read_all_edges(store, added).await? {
+        match removed_edges.iter().position(|removed| *removed == edge) {
+            Some(index) => { removed_edges.remove(index); }
+            None => added_edges.push(edge),
+        }
+    }
+    removed_edges.sort_by_key(|(edge, _)| *edge);
+    added_edges.sort_by_key(|(edge, _)| *edge);
+
+    Ok(GraphDiff {
+        added_vertices: added_vertices.into_iter().collect(),
+        removed_vertices: removed_vertices.into_iter().collect(),
+        added_edges,
+        removed_edges,
+    })
+}
+
+/// Compares the graphs of the commits with the [`Hash`]es `commit_hash_1` and `commit_hash_2`,
+/// from the point of view of the first one: vertices and edges that are only in the second graph
+/// are added.
+///
+/// [`Hash`]: ../struct.Hash.html
+pub async fn diff_commits<S>(store: &S, commit_hash_1: Hash, commit_hash_2: Hash) -> Result<GraphDiff>
+    where S: ObjectStore + ?Sized
+{
+    let commit_1: Commit = read_object(store, commit_hash_1).await?;
+    let commit_2: Commit = read_object(store, commit_hash_2).await?;
+    diff_graphs(store, commit_1.graph_hash, commit_2.graph_hash).await
+}
+
+/// Compares the latest versions of the graphs `name_1` and `name_2`, e.g. of two branches.
+pub async fn diff_branches<S>(store: &S, name_1: &str, name_2: &str) -> Result<GraphDiff>
+    where S: ObjectStore + ?Sized
+{
+    let commit_hash_1 = read_ref(store, name_1).await?;
+    let commit_hash_2 = read_ref(store, name_2).await?;
+    diff_commits(store, commit_hash_1, commit_hash_2).await
+}
+
+#[cfg(test)]
+mod test {
+    use tokio::runtime::Runtime;
+    use histo_graph_core::graph::attributed_graph::AttributedGraph;
+
+    use super::*;
+    use crate::{
+        file_storage::{save_graph_as, load_graph},
+        object::ObjectType,
+        store::MemoryStore,
+    };
+
+    #[test]
+    fn test_diff_commits() -> Result<()> {
+        let mut rt = Runtime::new()?;
+        rt.block_on(async {
+            let store = MemoryStore::new();
+            let name = "graph_diff".to_string();
+
+            let mut graph = AttributedGraph::new();
+            for id in 0..1000 {
+                graph.add_edge(Edge(VertexId(id), VertexId(id + 1)));
+            }
+            let hash_1 = save_graph_as(&store, name.clone(), &graph).await?;
+
+            graph.remove_vertex(VertexId(500));
+            graph.add_edge(Edge(VertexId(2000), VertexId(1)));
+            graph.set_vertex_attribute(VertexId(7), "name".to_string(), "seven".into());
+            let hash_2 = save_graph_as(&store, name.clone(), &graph).await?;
+
+            // an object that both versions share is not read
+            let vertex_hashes = store.list_objects(StoredVertex::storage_name()).await?;
+            for hash in vertex_hashes {
+                let vertex: StoredVertex = read_object(&store, hash).await?;
+                if vertex.id == 300 {
+                    store.remove_object(StoredVertex::storage_name(), hash).await?;
+                }
+            }
+            assert!(load_graph(&store, name).await.is_err());
+
+            let diff = diff_commits(&store, hash_1, hash_2).await?;
+            let mut seven = Attributes::new();
+            seven.insert("name".to_string(), "seven".into());
+            assert_eq!(diff.added_vertices, vec![(VertexId(7), seven), (VertexId(2000), Attributes::new())]);
+            assert_eq!(diff.removed_vertices, vec![(VertexId(7), Attributes::new()), (VertexId(500), Attributes::new())]);
+            assert_eq!(diff.added_edges, vec![(Edge(VertexId(2000), VertexId(1)), Attributes::new())]);
+            assert_eq!(diff.removed_edges, vec![
+                (Edge(VertexId(499), VertexId(500)), Attributes::new()),
+                (Edge(VertexId(500), VertexId(501)), Attributes::new()),
+            ]);
+
+            assert!(diff_commits(&store, hash_2, hash_2).await?.is_empty());
+            let reverse = diff_commits(&store, hash_2, hash_1).await?;
+            assert_eq!(reverse.added_vertices, diff.removed_vertices);
+            assert_eq!(reverse.removed_edges, diff.added_edges);
+
+            // a second copy of an edge is added, and removed again
+            graph.add_edge(Edge(VertexId(1), VertexId(2)));
+            let hash_3 = save_graph_as(&store, "graph_diff_copy".to_string(), &graph).await?;
+            let copy = vec![(Edge(VertexId(1), VertexId(2)), Attributes::new())];
+            let diff = diff_commits(&store, hash_2, hash_3).await?;
+            assert_eq!((diff.added_edges, diff.removed_edges), (copy.clone(), Vec::new()));
+            assert_eq!(diff_commits(&store, hash_3, hash_2).await?.removed_edges, copy);
+            Ok(())
+        })
+    }
+}
@@ -1,11 +1,140 @@
+//! Defines the [`Error`] of the functions of this crate.
+//!
+//! [`Error`]: ./enum.Error.html
+
+use std::{fmt, path::PathBuf};
+
 #[derive(Debug)]
 pub enum Error {
     IoError(std::io::Error),
-    BinCodeError(bincode::Error)
+    BinCodeError(bincode::Error),
+    DatabaseError(sled::Error),
+
+    /// There is no store under the directory `path`. A store is created with `create_store`.
+    NotInitialized { path: PathBuf },
+
+    /// The directory `path` holds a store with the configuration `existing`, which differs from the
+    /// one that the store was to be created with.
+    ConfigMismatch { path: PathBuf, existing: StoreConfig },
+
+    /// The directory `path` is not empty, but holds nothing that is recognised as a store, so no
+    /// store is opened, created or migrated in it.
+    UnknownStore { path: PathBuf },
+
+    /// `name` cannot be the name of a reference, e.g. of a branch, because it is empty, or would
+    /// address a file outside of the directory of the references.
+    InvalidRefName { name: String },
+
+    /// There is no reference, e.g. no branch, called `name`, and, if `name` is looked up as an
+    /// abbreviated hash as well, no commit whose hash starts with `name`.
+    RefNotFound { name: String },
+
+    /// A branch called `name` already exists, so no other branch can be created or renamed to it.
+    BranchExists { name: String },
+
+    /// The branch `name` is the current branch, which cannot be deleted.
+    CurrentBranch { name: String },
+
+    /// There is no object of type `object_type` with the [`Hash`] `hash`.
+    ///
+    /// [`Hash`]: ../struct.Hash.html
+    ObjectNotFound { object_type: String, hash: Hash },
+
+    /// The object of type `object_type` with the [`Hash`] `hash` is corrupt: the `Hash` of its
+    /// content differs, or its content cannot be decompressed or decoded.
+    ///
+    /// [`Hash`]: ../struct.Hash.html
+    CorruptObject { object_type: String, hash: Hash },
+
+    /// `input` is not the hex-representation of a [`Hash`].
+    ///
+    /// [`Hash`]: ../struct.Hash.html
+    InvalidHash { input: String },
+
+    /// The abbreviated [`Hash`] `prefix` is the beginning of more than one `Hash`, the
+    /// `candidates`.
+    ///
+    /// [`Hash`]: ../struct.Hash.html
+    AmbiguousHash { prefix: String, candidates: Vec<Hash> },
+
+    /// `revision` has no version that is not newer than `timestamp`, in seconds since the UNIX
+    /// epoch, UTC.
+    VersionNotFound { revision: String, timestamp: i64 },
+
+    /// The reference `name` has been moved by someone else: it was expected to refer to the commit
+    /// `expected`, but refers to the commit `actual`. `None` means that the reference does not
+    /// exist.
+    ConcurrentModification { name: String, expected: Option<Hash>, actual: Option<Hash> },
+
+    /// The store has been written in the format `found`, but only the format `supported` can be
+    /// read and written. An older store can be upgraded with `migrate_store`.
+    VersionMismatch { found: u32, supported: u32 },
 }
 
 use Error::*;
-use crate::error::Error::IoError;
+use crate::{Hash, store::StoreConfig};
+
+/// Describes the commit that a reference refers to, if any.
+fn describe_commit(hash: &Option<Hash>) -> String {
+    match hash {
+        Some(hash) => format!("commit {}", hash),
+        None => "no commit".to_string(),
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IoError(e) => write!(f, "I/O error: {}", e),
+            BinCodeError(e) => write!(f, "cannot serialize object: {}", e),
+            DatabaseError(e) => write!(f, "database error: {}", e),
+            NotInitialized { path } => write!(f, "there is no store in {}", path.display()),
+            ConfigMismatch { path, existing } => write!(
+                f,
+                "{} holds a store with a different configuration:\n{}",
+                path.display(), existing,
+            ),
+            UnknownStore { path } => write!(f, "{} is not empty, but holds no store", path.display()),
+            InvalidRefName { name } => write!(f, "'{}' is not a valid reference name", name),
+            RefNotFound { name } => write!(f, "reference '{}' not found", name),
+            BranchExists { name } => write!(f, "branch '{}' already exists", name),
+            CurrentBranch { name } => write!(f, "cannot delete the current branch '{}'", name),
+            ObjectNotFound { object_type, hash } => write!(f, "object {}/{} not found", object_type, hash),
+            CorruptObject { object_type, hash } => write!(f, "object {}/{} is corrupt", object_type, hash),
+            InvalidHash { input } => write!(f, "'{}' is not a hash", input),
+            AmbiguousHash { prefix, candidates } => {
+                let candidates: Vec<String> = candidates.iter().map(Hash::to_string).collect();
+                write!(f, "'{}' is the beginning of several hashes: {}", prefix, candidates.join(", "))
+            }
+            VersionNotFound { revision, timestamp } => write!(
+                f,
+                "'{}' has no version as of {} seconds since the UNIX epoch",
+                revision, timestamp,
+            ),
+            ConcurrentModification { name, expected, actual } => write!(
+                f,
+                "reference '{}' has been modified concurrently: expected {}, found {}",
+                name, describe_commit(expected), describe_commit(actual),
+            ),
+            VersionMismatch { found, supported } => write!(
+                f,
+                "the store has the format {}, but only the format {} is supported",
+                found, supported,
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            IoError(e) => Some(e),
+            BinCodeError(e) => Some(e),
+            DatabaseError(e) => Some(e),
+            _ => None,
+        }
+    }
+}
 
 impl From<std::io::Error> for Error {
     fn from(e: std::io::Error) -> Error {
@@ -19,4 +148,38 @@ impl From<bincode::Error> for Error {
     }
 }
 
+impl From<sled::Error> for Error {
+    fn from(e: sled::Error) -> Error {
+        DatabaseError(e)
+    }
+}
+
 pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let hash = Hash::sha256(b"27");
+        let missing = ObjectNotFound { object_type: "vertex".to_string(), hash };
+        assert_eq!(missing.to_string(), format!("object vertex/{} not found", hash));
+
+        let moved = ConcurrentModification { name: "main".to_string(), expected: None, actual: Some(hash) };
+        assert_eq!(
+            moved.to_string(),
+            format!("reference 'main' has been modified concurrently: expected no commit, found commit {}", hash),
+        );
+
+        assert_eq!(BranchExists { name: "main".to_string() }.to_string(), "branch 'main' already exists");
+        let mismatch = ConfigMismatch { path: PathBuf::from("store"), existing: StoreConfig::default() };
+        assert!(mismatch.to_string().starts_with("store holds a store with a different configuration:\n"));
+        assert_eq!(UnknownStore { path: PathBuf::from("store") }.to_string(), "store is not empty, but holds no store");
+
+        let io: Error = std::io::Error::new(std::io::ErrorKind::PermissionDenied, "read-only").into();
+        assert_eq!(io.to_string(), "I/O error: read-only");
+        assert!(std::error::Error::source(&io).is_some());
+        assert!(std::error::Error::source(&RefNotFound { name: "main".to_string() }).is_none());
+    }
+}
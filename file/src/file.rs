@@ -1,25 +1,25 @@
 //! This module defines a struct [`File`] that holds all the data that is needed to store an object.
-//! It also provides functions to create a `File` from a reference to a type that can be stored,
-//! by implementations of `TryInto` for these types.
+//! A `File` is created from a reference to a type that can be stored by [`File::serialize`], and
+//! the stored types are read from a `File` by implementations of `TryInto` for these types.
 //! [`File`]: ./struct.File.html
+//! [`File::serialize`]: ./struct.File.html#method.serialize
 
-use std::{
-    path::{Path, PathBuf},
-    convert::TryFrom,
-};
+use std::convert::TryFrom;
 
 use crate::{
     Hash,
+    store::HashAlgorithm,
     object::{
         ObjectType,
-        NamedObjectType,
+        StoredVertex,
         HashEdge,
         HashVec,
+        HashChunk,
         GraphHash,
+        Commit,
     },
 };
 
-use histo_graph_core::graph::graph::{VertexId, Edge};
 use serde::Deserialize;
 
 /// Holds the data that is needed to store an object.
@@ -46,118 +46,23 @@ impl<OT> File<OT>
         }
     }
 
-    /// Returns the directory in which to store objects of type `OT`, given a `base_path`.
-    pub(crate) fn create_dir<P>(base_path: P) -> PathBuf
-        where P: AsRef<Path>
-    {
-        let path_buf: PathBuf = base_path.as_ref().into();
-        path_buf.join(OT::storage_name())
-    }
-
-    /// Returns the path of the file to be stored, given a `base_path`.
-    pub(crate) fn create_path<P>(&self, base_path: P) -> PathBuf
-        where P: AsRef<Path>
-    {
-        File::<OT>::create_path_from_hash(base_path, self.hash)
-    }
-
-    /// Returns the path of a file with the given `hash`, under the `base_path`.
-    pub(crate) fn create_path_from_hash<P>(base_path: P, hash: Hash) -> PathBuf
-        where P: AsRef<Path>
-    {
-        let path_buf: PathBuf = base_path.as_ref().into();
-        path_buf.join(OT::storage_name()).join(hash.to_string())
-    }
-}
-
-impl<NOT> File<NOT>
-    where NOT: ObjectType,
-          NOT: NamedObjectType
-{
-    /// Returns the path of the file, which is stored under the provided name.
-    pub(crate) fn create_named_path<P, S>(base_path: P, name: S) -> PathBuf
-        where P: AsRef<Path>,
-              S: AsRef<str>
-    {
-        let path_buf: PathBuf = base_path.as_ref().into();
-        path_buf.join(NOT::storage_name()).join(name.as_ref())
-    }
-}
-
-impl TryFrom<&VertexId> for File<VertexId> {
-    type Error = bincode::Error;
-
-    fn try_from(vertex_id: &VertexId) -> std::result::Result<File<VertexId>, bincode::Error> {
-        let content: Vec<u8> = bincode::serialize(&vertex_id.0)?;
-        let hash: Hash = (&content).into();
-
-        Ok(File {
-            content,
-            hash,
-            _pot: std::marker::PhantomData,
-        })
-    }
-}
-
-impl TryFrom<&Edge> for File<HashEdge> {
-    type Error = bincode::Error;
-
-    fn try_from(edge: &Edge) -> std::result::Result<File<HashEdge>, bincode::Error> {
-        let content_from: Vec<u8> = bincode::serialize(&(edge.0).0)?;
-        let hash_from: Hash = (&content_from).into();
-
-        let content_to: Vec<u8> = bincode::serialize(&(edge.1).0)?;
-        let hash_to: Hash = (&content_to).into();
-
-        let hash_edge = HashEdge { from: hash_from, to: hash_to };
-
-        let content: Vec<u8> = bincode::serialize(&hash_edge)?;
-        let hash: Hash = (&content).into();
-
-        Ok(File {
-            content,
-            hash,
-            _pot: std::marker::PhantomData,
-        })
-    }
-}
-
-impl<OT> TryFrom<&HashVec<OT>> for File<HashVec<OT>> {
-    type Error = bincode::Error;
-
-    fn try_from(hash_vec: &HashVec<OT>) -> std::result::Result<File<HashVec<OT>>, bincode::Error> {
-        let content: Vec<u8> = bincode::serialize(hash_vec)?;
-        let hash: Hash = (&content).into();
-
-        Ok(File {
-            content,
-            hash,
-            _pot: std::marker::PhantomData,
-        })
-    }
-}
-
-impl TryFrom<&GraphHash> for File<GraphHash> {
-    type Error = bincode::Error;
-
-    fn try_from(graph_hash: &GraphHash) -> std::result::Result<File<GraphHash>, bincode::Error> {
-        let content: Vec<u8> = bincode::serialize(graph_hash)?;
-        let hash: Hash = (&content).into();
+    /// Serializes `object`, and computes the [`Hash`] of its content with `hash_algorithm`, the
+    /// algorithm of the store that the object is written to.
+    ///
+    /// [`Hash`]: ../struct.Hash.html
+    pub(crate) fn serialize(object: &OT, hash_algorithm: HashAlgorithm) -> std::result::Result<File<OT>, bincode::Error> {
+        let content: Vec<u8> = bincode::serialize(object)?;
+        let hash: Hash = hash_algorithm.hash(&content);
 
-        Ok(File {
-            content,
-            hash,
-            _pot: std::marker::PhantomData,
-        })
+        Ok(File::new(content, hash))
     }
 }
 
-impl TryFrom<&File<VertexId>> for VertexId {
+impl TryFrom<&File<StoredVertex>> for StoredVertex {
     type Error = bincode::Error;
 
-    fn try_from(file: &File<VertexId>) -> std::result::Result<VertexId, bincode::Error> {
-        let id: u64 = bincode::deserialize(file.content.as_ref())?;
-        Ok(VertexId(id))
+    fn try_from(file: &File<StoredVertex>) -> std::result::Result<StoredVertex, bincode::Error> {
+        bincode::deserialize::<StoredVertex>(file.content.as_ref())
     }
 }
 
@@ -180,6 +85,17 @@ impl<'de, OT> TryFrom<&'de File<HashVec<OT>>> for HashVec<OT>
     }
 }
 
+impl<'de, OT> TryFrom<&'de File<HashChunk<OT>>> for HashChunk<OT>
+    where HashChunk<OT>: ObjectType,
+          HashChunk<OT>: Deserialize<'de>
+{
+    type Error = bincode::Error;
+
+    fn try_from(file: &'de File<HashChunk<OT>>) -> Result<HashChunk<OT>, bincode::Error> {
+        bincode::deserialize::<HashChunk<OT>>(file.content.as_ref())
+    }
+}
+
 impl TryFrom<&File<GraphHash>> for GraphHash {
     type Error = bincode::Error;
 
@@ -188,18 +104,63 @@ impl TryFrom<&File<GraphHash>> for GraphHash {
     }
 }
 
+impl TryFrom<&File<Commit>> for Commit {
+    type Error = bincode::Error;
+
+    fn try_from(file: &File<Commit>) -> Result<Commit, bincode::Error> {
+        bincode::deserialize::<Commit>(file.content.as_ref())
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::convert::TryInto;
-    use histo_graph_core::graph::graph::VertexId;
+    use histo_graph_core::graph::{
+        graph::VertexId,
+        attributes::Attributes,
+    };
     use crate::file::File;
+    use crate::object::{StoredVertex, from_stored_attributes};
+    use crate::store::HashAlgorithm;
+    use crate::Hash;
 
     #[test]
-    fn test_vertex_id_to_file() -> Result<(), bincode::Error> {
-        let vertex_id = VertexId(27u64);
-        let file: File<VertexId> = (&vertex_id).try_into()?;
-        let result: VertexId = (&file).try_into()?;
+    fn test_vertex_to_file() -> Result<(), bincode::Error> {
+        let mut attributes = Attributes::new();
+        attributes.insert("name".to_string(), "pepi".into());
+
+        let vertex = StoredVertex::new(VertexId(27u64), &attributes);
+        let file = File::serialize(&vertex, HashAlgorithm::Sha256)?;
+        let result: StoredVertex = (&file).try_into()?;
 
-        Ok(assert_eq!(vertex_id, result))
+        assert_eq!(result.id, 27u64);
+        assert_eq!(from_stored_attributes(result.attributes), attributes);
+        Ok(())
+    }
+
+    #[test]
+    fn test_changed_attribute_changes_hash() -> Result<(), bincode::Error> {
+        let mut attributes = Attributes::new();
+        attributes.insert("name".to_string(), "pepi".into());
+        let file_1 = File::serialize(&StoredVertex::new(VertexId(27u64), &attributes), HashAlgorithm::Sha256)?;
+
+        attributes.insert("name".to_string(), "franz".into());
+        let file_2 = File::serialize(&StoredVertex::new(VertexId(27u64), &attributes), HashAlgorithm::Sha256)?;
+
+        assert_ne!(file_1.hash.to_string(), file_2.hash.to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn test_hash_algorithms() -> Result<(), bincode::Error> {
+        let vertex = StoredVertex::new(VertexId(27u64), &Attributes::new());
+        let sha256 = File::serialize(&vertex, HashAlgorithm::Sha256)?;
+        let blake3 = File::serialize(&vertex, HashAlgorithm::Blake3)?;
+
+        assert_eq!(sha256.content, blake3.content);
+        assert_eq!(sha256.hash, Hash::sha256(&sha256.content));
+        assert_ne!(sha256.hash, blake3.hash);
+        assert_eq!(blake3.hash, HashAlgorithm::Blake3.hash(&blake3.content));
+        Ok(())
     }
 }
\ No newline at end of file
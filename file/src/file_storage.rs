@@ -1,309 +1,525 @@
-//! Implements the functions that write and read a graph to the file system.
+//! Implements the functions that write and read a graph to an [`ObjectStore`].
+//!
+//! [`ObjectStore`]: ../store/trait.ObjectStore.html
 
 use histo_graph_core::graph::{
     graph::{VertexId, Edge},
-    directed_graph::DirectedGraph,
+    attributes::Attributes,
+    attributed_graph::AttributedGraph,
 };
 
-use crate::error::Result;
+use crate::error::{Result, Error, Error::{RefNotFound, CorruptObject, AmbiguousHash}};
 
 use std::{
-    io,
-    path::{Path, PathBuf},
+    collections::HashMap,
+    time::{SystemTime, UNIX_EPOCH},
 };
 use std::convert::TryInto;
-use tokio::fs;
-use futures;
+use futures::{
+    stream::{self, Stream, StreamExt, TryStreamExt},
+};
 
 use crate::{
     Hash,
+    MIN_PREFIX_LENGTH,
     object::{
         ObjectType,
-        NamedObjectType,
-        HashVec,
+        StoredVertex,
         HashEdge,
         GraphHash,
+        Commit,
+        to_stored_attributes,
+        from_stored_attributes,
     },
     file::File,
+    store::{ObjectStore, HashAlgorithm},
+    tree::{write_tree, read_tree},
 };
 
-/// Takes an interator over objects of type `OT` and returns a vector of `File<OT>`.
-fn to_file_vec<I, T, OT>(i: I) -> Result<Vec<File<OT>>>
-    where I: IntoIterator<Item=T>,
-          T: TryInto<File<OT>, Error=bincode::Error>,
+/// Takes an interator over objects of type `OT` and returns an iterator over `File<OT>`, whose
+/// [`Hash`]es are computed with `hash_algorithm`. The objects are serialized when the files are
+/// taken from the iterator.
+///
+/// [`Hash`]: ../struct.Hash.html
+pub(crate) fn to_files<'a, I, OT>(i: I, hash_algorithm: HashAlgorithm) -> impl Iterator<Item=Result<File<OT>>> + 'a
+    where I: IntoIterator<Item=&'a OT>,
+          I::IntoIter: 'a,
           OT: ObjectType
 {
     i
         .into_iter()
-        .map(TryInto::<File<OT>>::try_into)
+        .map(move |object| File::serialize(object, hash_algorithm))
         .map(|r| r.map_err(Into::into))
-        .collect()
 }
 
-async fn write_file<P, OT>(base_path: P, file: File<OT>) -> std::result::Result<Hash, io::Error>
+/// Writes `file`, unless the object already exists. Objects are identified by the [`Hash`] of
+/// their content, so an object that exists never has to be written again. Checking for the object
+/// is much cheaper than writing it, and most objects of a graph exist already, when a new version
+/// of it is saved.
+///
+/// [`Hash`]: ../struct.Hash.html
+async fn write_file<S, OT>(store: &S, file: File<OT>) -> Result<Hash>
     where OT: ObjectType,
-          P: AsRef<Path>
+          S: ObjectStore + ?Sized
 {
-    let path: PathBuf = file.create_path(base_path);
-    fs::write(path, file.content).await?;
+    if !store.contains_object(OT::storage_name(), file.hash).await? {
+        store.put_object(OT::storage_name(), file.hash, file.content).await?;
+    }
     Ok(file.hash)
 }
 
-async fn write_named_file<P, S, NOT>(base_path: P, name: S, file: File<NOT>) -> std::result::Result<(), io::Error>
-    where NOT: ObjectType,
-          NOT: NamedObjectType,
-          P: AsRef<Path>,
-          S: AsRef<str>
+/// Writes all `files` that do not exist yet. At most [`max_concurrency`] files are written at the
+/// same time, and the next files are only taken from the iterator, when the ones before have been
+/// written. Returns their [`Hash`]es, in the order of the `files`.
+///
+/// [`max_concurrency`]: ../store/trait.ObjectStore.html#method.max_concurrency
+/// [`Hash`]: ../struct.Hash.html
+pub(crate) async fn write_all_files<S, OT, I>(store: &S, files: I) -> Result<Vec<Hash>>
+    where OT: ObjectType,
+          S: ObjectStore + ?Sized,
+          I: IntoIterator<Item=Result<File<OT>>>
 {
-    let path: PathBuf = File::<NOT>::create_named_path(base_path, name);
-    fs::write(path, file.content).await?;
-    Ok(())
+    stream::iter(files)
+        .map(|file| async move { write_file(store, file?).await })
+        .buffered(store.max_concurrency())
+        .try_collect()
+        .await
 }
 
-async fn write_all_files<P, OT>(base_path: P, files: Vec<File<OT>>) -> Result<HashVec<OT>>
-    where OT: ObjectType,
-          P: AsRef<Path>,
-          P: Clone
+pub(crate) async fn write_object<S, OT>(store: &S, object: &OT) -> Result<Hash>
+    where S: ObjectStore + ?Sized,
+          OT: ObjectType
 {
-    let base_path: PathBuf = base_path.as_ref().into();
+    let file = File::serialize(object, store.hash_algorithm())?;
+    write_file(store, file).await
+}
 
-    let futs = files
+/// Writes the vertices of `graph`, together with their attributes.
+///
+/// Returns the [`Hash`] of the root of the tree of the vertices, and the `Hash` of each vertex.
+///
+/// [`Hash`]: ../struct.Hash.html
+async fn write_graph_vertices<S>(store: &S, graph: &AttributedGraph) -> Result<(Hash, HashMap<VertexId, Hash>)>
+    where S: ObjectStore + ?Sized
+{
+    let no_attributes = Attributes::new();
+    let hash_algorithm = store.hash_algorithm();
+    let vertex_ids: Vec<VertexId> = graph.vertices().cloned().collect();
+    let files = vertex_ids
+        .iter()
+        .cloned()
+        .map(|vertex_id| {
+            let vertex = StoredVertex::new(vertex_id, graph.vertex_attributes(vertex_id).unwrap_or(&no_attributes));
+            Ok(File::serialize(&vertex, hash_algorithm)?)
+        });
+
+    let hashes = write_all_files(store, files).await?;
+    let vertex_hashes: HashMap<VertexId, Hash> = vertex_ids
         .into_iter()
-        .map(|file| write_file(base_path.clone(), file));
-
-    let vec = futures::future::try_join_all(futs).await?;
+        .zip(hashes.iter().cloned())
+        .collect();
 
-    Ok(HashVec::<OT>::new(vec))
+    let hash = write_tree::<S, StoredVertex>(store, hashes).await?;
+    Ok((hash, vertex_hashes))
 }
 
-async fn create_dir_and_write_all_files<P, OT>(base_path: P, files: Vec<File<OT>>) -> Result<HashVec<OT>>
-    where OT: ObjectType,
-          P: AsRef<Path>,
-          P: Clone
+/// Writes the edges of `graph`, together with their attributes, given the `Hash` of each vertex.
+async fn write_graph_edges<S>(store: &S, graph: &AttributedGraph, vertex_hashes: &HashMap<VertexId, Hash>) -> Result<Hash>
+    where S: ObjectStore + ?Sized
 {
-    fs::create_dir_all(File::<OT>::create_dir(base_path.clone())).await?;
-    write_all_files(base_path, files).await
+    let no_attributes = Attributes::new();
+    let hash_algorithm = store.hash_algorithm();
+    let files = graph
+        .edges()
+        .cloned()
+        .map(|edge| {
+            let hash_edge = HashEdge {
+                from: vertex_hashes[&edge.0],
+                to: vertex_hashes[&edge.1],
+                attributes: to_stored_attributes(graph.edge_attributes(edge).unwrap_or(&no_attributes)),
+            };
+            Ok(File::serialize(&hash_edge, hash_algorithm)?)
+        });
+
+    let hashes = write_all_files(store, files).await?;
+    write_tree::<S, HashEdge>(store, hashes).await
 }
 
-async fn write_object<'a, P, T, OT>(base_path: P, object: &'a T) -> Result<Hash>
-    where P: AsRef<Path>,
-          OT: ObjectType,
-          &'a T: TryInto<File<OT>, Error=bincode::Error>
+async fn write_graph<S>(store: &S, graph: &AttributedGraph) -> Result<GraphHash>
+    where S: ObjectStore + ?Sized
 {
-    let file = TryInto::<File<OT>>::try_into(object)?;
-    Ok(write_file(base_path, file).await?)
+    let (vertex_tree_hash, vertex_hashes) = write_graph_vertices(store, graph).await?;
+    Ok(GraphHash {
+        vertex_tree_hash,
+        edge_tree_hash: write_graph_edges(store, graph, &vertex_hashes).await?
+    })
 }
 
-async fn create_dir_and_write_object<'a, P, T, OT>(base_path: P, object: &'a T) -> Result<Hash>
-    where P: AsRef<Path>,
-          P: Clone,
-          OT: ObjectType,
-          &'a T: TryInto<File<OT>, Error=bincode::Error>
+/// Who saves a version of a graph, and why. It is recorded in the [`Commit`] of the version,
+/// together with the time of the save.
+///
+/// [`Commit`]: ../object/struct.Commit.html
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommitInfo {
+
+    /// The author of the version, e.g. `Jane Doe <jane@example.com>`.
+    pub author: String,
+
+    /// A free-text description of the changes.
+    pub message: String,
+
+    /// The time of the version, in seconds since the UNIX epoch, UTC. `None` means the time when
+    /// the version is saved.
+    pub timestamp: Option<i64>,
+}
+
+impl CommitInfo {
+
+    /// Creates a `CommitInfo` with the `author` and the `message`, that is saved at the time of
+    /// the save.
+    pub fn new<A, M>(author: A, message: M) -> CommitInfo
+        where A: Into<String>,
+              M: Into<String>
+    {
+        CommitInfo {
+            author: author.into(),
+            message: message.into(),
+            timestamp: None,
+        }
+    }
+}
+
+/// Returns the current time, in seconds since the UNIX epoch, UTC.
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Writes `graph` and a [`Commit`] of it, with the provided `parents`, and the author, message and
+/// time of `info`. Returns the [`Hash`] of the commit.
+///
+/// [`Commit`]: ../object/struct.Commit.html
+/// [`Hash`]: ../struct.Hash.html
+pub(crate) async fn write_commit<S>(store: &S, graph: &AttributedGraph, parents: Vec<Hash>, info: &CommitInfo) -> Result<Hash>
+    where S: ObjectStore + ?Sized
 {
-    fs::create_dir_all(File::<OT>::create_dir(base_path.clone())).await?;
-    write_object(base_path, object).await
+    let graph_hash = write_graph(store, graph).await?;
+    let graph_hash = write_object(store, &graph_hash).await?;
+
+    let commit = Commit {
+        parents,
+        graph_hash,
+        timestamp: info.timestamp.unwrap_or_else(now),
+        author: info.author.clone(),
+        message: info.message.clone(),
+    };
+
+    write_object(store, &commit).await
 }
 
-/// Writes the vertices of `graph`.
-/// Creates the necessary directories.
-async fn write_graph_vertices<P>(base_path: P, graph: &DirectedGraph) -> Result<Hash>
-    where P: AsRef<Path>,
-          P: Clone
+/// Saves `graph` as a new version of the graph `name`, without an author or a message, see
+/// [`save_graph_as_with_info`].
+///
+/// [`save_graph_as_with_info`]: ./fn.save_graph_as_with_info.html
+pub async fn save_graph_as<S>(store: &S, name: String, graph: &AttributedGraph) -> Result<Hash>
+    where S: ObjectStore + ?Sized
 {
-    let files = to_file_vec(graph.vertices())?;
-    let hash_vec = create_dir_and_write_all_files(base_path.clone(), files).await?;
-    create_dir_and_write_object(base_path, &hash_vec).await
+    save_graph_as_with_info(store, name, graph, &CommitInfo::default()).await
 }
 
-/// Writes the edges of `graph`.
-/// Creates the necessary directories
-async fn write_graph_edges<P>(base_path: P, graph: &DirectedGraph) -> Result<Hash>
-    where P: AsRef<Path>,
-          P: Clone
+/// Saves `graph` as a new version of the graph `name`, by the author and with the message of
+/// `info`.
+///
+/// A new [`Commit`] is written, whose parent is the commit that `name` referred to before (if
+/// any), and `name` is updated to refer to the new commit. Returns the [`Hash`] of the new commit.
+///
+/// [`Commit`]: ../object/struct.Commit.html
+/// [`Hash`]: ../struct.Hash.html
+pub async fn save_graph_as_with_info<S>(store: &S, name: String, graph: &AttributedGraph, info: &CommitInfo) -> Result<Hash>
+    where S: ObjectStore + ?Sized
 {
-    let files = to_file_vec(graph.edges())?;
-    let hash_vec = create_dir_and_write_all_files(base_path.clone(), files).await?;
-    create_dir_and_write_object(base_path, &hash_vec).await
+    let _guard = store.lock().await?;
+
+    let parent = store.read_ref(&name).await?;
+    let commit_hash = write_commit(store, graph, parent.into_iter().collect(), info).await?;
+    store.compare_and_swap_ref(&name, parent, commit_hash).await?;
+    Ok(commit_hash)
 }
 
-async fn write_graph<P>(base_path: P, graph: &DirectedGraph) -> Result<GraphHash>
-    where P: AsRef<Path>,
-          P: Clone
+/// Saves `graph` as a new version of the graph `name`, that is based on the version with the
+/// commit `parent`, without an author or a message, see [`save_graph_with_parent_and_info`].
+///
+/// [`save_graph_with_parent_and_info`]: ./fn.save_graph_with_parent_and_info.html
+pub async fn save_graph_with_parent<S>(store: &S, name: String, parent: Option<Hash>, graph: &AttributedGraph) -> Result<Hash>
+    where S: ObjectStore + ?Sized
 {
-    Ok(GraphHash {
-        vertex_vec_hash: write_graph_vertices(base_path.clone(), graph).await?,
-        edge_vec_hash: write_graph_edges(base_path, graph).await?
-    })
+    save_graph_with_parent_and_info(store, name, parent, graph, &CommitInfo::default()).await
 }
-pub async fn save_graph_as<P>(base_path: P, name: String, graph: &DirectedGraph) -> Result<()>
-    where P: AsRef<Path>,
-          P: Clone
+
+/// Saves `graph` as a new version of the graph `name`, that is based on the version with the
+/// commit `parent`, as returned by [`load_graph_with_commit`], by the author and with the message
+/// of `info`. `None` means that `graph` is the first version.
+///
+/// Fails with [`ConcurrentModification`] if `name` does not refer to `parent` anymore, because
+/// another version has been saved in the meantime. In this case, `name` is not changed, and the
+/// objects that have already been written are left to the garbage collection.
+/// Returns the [`Hash`] of the new commit.
+///
+/// [`load_graph_with_commit`]: ./fn.load_graph_with_commit.html
+/// [`ConcurrentModification`]: ../error/enum.Error.html#variant.ConcurrentModification
+/// [`Hash`]: ../struct.Hash.html
+pub async fn save_graph_with_parent_and_info<S>(store: &S, name: String, parent: Option<Hash>, graph: &AttributedGraph, info: &CommitInfo) -> Result<Hash>
+    where S: ObjectStore + ?Sized
 {
-    let graph_hash = write_graph(base_path.clone(), graph).await?;
-    let file = TryInto::<File<GraphHash>>::try_into(&graph_hash)?;
-    fs::create_dir_all(File::<GraphHash>::create_dir(base_path.clone())).await?;
-    Ok(write_named_file(base_path, name, file).await?)
+    let _guard = store.lock().await?;
+
+    let commit_hash = write_commit(store, graph, parent.into_iter().collect(), info).await?;
+    store.compare_and_swap_ref(&name, parent, commit_hash).await?;
+    Ok(commit_hash)
 }
 
-async fn read_file<P, OT>(base_path: P, hash: Hash) -> Result<File<OT>>
+async fn read_file<S, OT>(store: &S, hash: Hash) -> Result<File<OT>>
     where OT: ObjectType,
-          P: AsRef<Path>
+          S: ObjectStore + ?Sized
 {
-    let path: PathBuf = File::<OT>::create_path_from_hash(base_path, hash);
-    Ok(File::<OT>::new(fs::read(path).await?, hash))
+    let content = store.get_object(OT::storage_name(), hash).await?;
+    if store.hash_algorithm().hash(&content) != hash {
+        return Err(corrupt::<OT>(hash));
+    }
+    Ok(File::<OT>::new(content, hash))
 }
 
-async fn read_named_file<P, S, NOT>(base_path: P, name: S) -> Result<File<NOT>>
-    where NOT: ObjectType,
-          NOT: NamedObjectType,
-          P: AsRef<Path>,
-          S: AsRef<str>
+fn corrupt<OT>(hash: Hash) -> Error
+    where OT: ObjectType
 {
-    let path: PathBuf = File::<NOT>::create_named_path(base_path, name);
-    let content = fs::read(path).await?;
-    let hash: Hash = (&content).into();
-    Ok(File::<NOT>::new(content, hash))
+    CorruptObject { object_type: OT::storage_name().to_string(), hash }
 }
 
-async fn read_object<P, OT>(base_path: P, hash: Hash) -> Result<OT>
+/// Reads the [`Hash`] of the commit that the reference `name` refers to.
+/// Fails with [`RefNotFound`], if there is no such reference.
+///
+/// [`Hash`]: ../struct.Hash.html
+/// [`RefNotFound`]: ../error/enum.Error.html#variant.RefNotFound
+pub(crate) async fn read_ref<S>(store: &S, name: &str) -> Result<Hash>
+    where S: ObjectStore + ?Sized
+{
+    match store.read_ref(name).await? {
+        Some(hash) => Ok(hash),
+        None => Err(RefNotFound { name: name.to_string() }),
+    }
+}
+
+/// Returns the [`Hash`] of the commit that `revision` names: the commit that the reference
+/// `revision` refers to, if there is such a reference, or else the commit whose `Hash` starts with
+/// `revision`, e.g. `4d1591`, which must have at least [`MIN_PREFIX_LENGTH`] characters. Fails with
+/// [`RefNotFound`], if there is no such commit, and with [`AmbiguousHash`], if there are several.
+///
+/// [`Hash`]: ../struct.Hash.html
+/// [`MIN_PREFIX_LENGTH`]: ../constant.MIN_PREFIX_LENGTH.html
+/// [`RefNotFound`]: ../error/enum.Error.html#variant.RefNotFound
+/// [`AmbiguousHash`]: ../error/enum.Error.html#variant.AmbiguousHash
+pub async fn resolve_commit<S>(store: &S, revision: &str) -> Result<Hash>
+    where S: ObjectStore + ?Sized
+{
+    if let Some(hash) = store.read_ref(revision).await? {
+        return Ok(hash);
+    }
+    let not_found = || RefNotFound { name: revision.to_string() };
+    if revision.len() < MIN_PREFIX_LENGTH || !revision.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(not_found());
+    }
+
+    let mut candidates: Vec<Hash> = store
+        .list_objects(Commit::storage_name())
+        .await?
+        .into_iter()
+        .filter(|hash| hash.starts_with(revision))
+        .collect();
+    candidates.sort();
+    match candidates.len() {
+        0 => Err(not_found()),
+        1 => Ok(candidates[0]),
+        _ => Err(AmbiguousHash { prefix: revision.to_string(), candidates }),
+    }
+}
+
+pub(crate) async fn read_object<S, OT>(store: &S, hash: Hash) -> Result<OT>
     where OT: ObjectType,
           for<'a> &'a File<OT>: TryInto<OT, Error=bincode::Error> /* this is a "higher ranked trait bound" https://doc.rust-lang.org/nomicon/hrtb.html */,
-          P: AsRef<Path>
+          S: ObjectStore + ?Sized
 {
-    let file:File<OT> = read_file(base_path, hash).await?;
-    Ok((&file).try_into()?)
+    let cache = store.object_cache();
+    if let Some(object) = cache.and_then(|cache| cache.get::<OT>(hash)) {
+        return Ok(object);
+    }
+    let file:File<OT> = read_file(store, hash).await?;
+    let object: OT = (&file).try_into().map_err(|_| corrupt::<OT>(hash))?;
+    if let Some(cache) = cache {
+        cache.insert(hash, object.clone());
+    }
+    Ok(object)
 }
 
-async fn read_named_object<P, S, NOT>(base_path: P, name: S) -> Result<NOT>
-    where NOT: ObjectType,
-          NOT: NamedObjectType,
-          for<'a> &'a File<NOT>: TryInto<NOT, Error=bincode::Error> /* this is a "higher ranked trait bound" https://doc.rust-lang.org/nomicon/hrtb.html */,
-          P: AsRef<Path>,
-          S: AsRef<str>
+
+async fn read_edge<S>(store: &S, hash: Hash) -> Result<(Edge, Attributes)>
+    where S: ObjectStore + ?Sized
 {
-    let file: File<NOT> = read_named_file(base_path, name).await?;
-    Ok((&file).try_into()?)
+    let HashEdge { from, to, attributes } = read_object::<S, HashEdge>(store, hash).await?;
+    let edge = Edge(
+        VertexId(read_object::<S, StoredVertex>(store, from).await?.id),
+        VertexId(read_object::<S, StoredVertex>(store, to).await?.id)
+    );
+    Ok((edge, from_stored_attributes(attributes)))
 }
 
-async fn read_edge<P>(base_path: P, hash: Hash) -> Result<Edge>
-    where P: AsRef<Path>,
-          P: Clone
+/// Reads the objects with the [`Hash`]es `hashes`, in their order. At most [`max_concurrency`]
+/// objects are read at the same time, and only when the returned stream is polled.
+///
+/// [`Hash`]: ../struct.Hash.html
+/// [`max_concurrency`]: ../store/trait.ObjectStore.html#method.max_concurrency
+pub(crate) fn read_objects<'s, S, OT>(store: &'s S, hashes: Vec<Hash>) -> impl Stream<Item=Result<OT>> + 's
+    where S: ObjectStore + ?Sized,
+          OT: ObjectType + 's,
+          for<'a> &'a File<OT>: TryInto<OT, Error=bincode::Error>
 {
-    let HashEdge { from, to } = read_object::<P, HashEdge>(base_path.clone(), hash).await?;
-    Ok(Edge(
-        read_object::<P, VertexId>(base_path.clone(), from).await?,
-        read_object::<P, VertexId>(base_path, to).await?
-    ))
+    stream::iter(hashes)
+        .map(move |hash| read_object::<S, OT>(store, hash))
+        .buffered(store.max_concurrency())
 }
 
-async fn read_all_objects<P, OT>(base_path: P, hashes: Vec<Hash>) -> Result<Vec<OT>>
-    where P: AsRef<Path>,
-          P: Clone,
+pub(crate) async fn read_all_objects<S, OT>(store: &S, hashes: Vec<Hash>) -> Result<Vec<OT>>
+    where S: ObjectStore + ?Sized,
           OT: ObjectType,
           for<'a> &'a File<OT>: TryInto<OT, Error=bincode::Error>
 {
-    let futs = hashes
-        .into_iter()
-        .map(move |hash| read_object::<P, OT>(base_path.clone(), hash));
-
-    futures::future::try_join_all(futs).await
+    read_objects(store, hashes).try_collect().await
 }
 
-async fn read_all_edges<P>(base_path: P, hashes: Vec<Hash>) -> Result<Vec<Edge>>
-    where P: AsRef<Path>,
-          P: Clone
+/// Reads the edges with the [`Hash`]es `hashes`, like [`read_objects`].
+///
+/// [`Hash`]: ../struct.Hash.html
+/// [`read_objects`]: ./fn.read_objects.html
+pub(crate) fn read_edges<S>(store: &S, hashes: Vec<Hash>) -> impl Stream<Item=Result<(Edge, Attributes)>> + '_
+    where S: ObjectStore + ?Sized
 {
-    let futs = hashes
-        .into_iter()
-        .map(move |hash| read_edge::<P>(base_path.clone(), hash));
+    stream::iter(hashes)
+        .map(move |hash| read_edge::<S>(store, hash))
+        .buffered(store.max_concurrency())
+}
 
-    futures::future::try_join_all(futs).await
+pub(crate) async fn read_all_edges<S>(store: &S, hashes: Vec<Hash>) -> Result<Vec<(Edge, Attributes)>>
+    where S: ObjectStore + ?Sized
+{
+    read_edges(store, hashes).try_collect().await
 }
 
-/// Reads the vertices of a graph.
+/// Reads the vertices of a graph, together with their attributes.
 ///
 /// Note that this function consumes the graph, and gives it back in the returned Future, with
 /// the vertices added.
-async fn read_graph_vertices<P>(base_path: P, vertex_vec_hash: Hash, mut graph: DirectedGraph) -> Result<DirectedGraph>
-    where P: AsRef<Path>,
-          P: Clone
+async fn read_graph_vertices<S>(store: &S, vertex_tree_hash: Hash, mut graph: AttributedGraph) -> Result<AttributedGraph>
+    where S: ObjectStore + ?Sized
 {
-    let hash_vec: HashVec<VertexId> = read_object(base_path.clone(), vertex_vec_hash).await?;
-    let vertices = read_all_objects(base_path, hash_vec.0).await?;
+    let hashes = read_tree::<S, StoredVertex>(store, vertex_tree_hash).await?;
+    let mut vertices = read_objects::<S, StoredVertex>(store, hashes);
 
-    for v in vertices {
-        graph.add_vertex(v);
+    while let Some(StoredVertex { id, attributes }) = vertices.try_next().await? {
+        graph.add_vertex_with_attributes(VertexId(id), from_stored_attributes(attributes));
     }
 
     Ok(graph)
 }
 
-/// Reads the edges of a graph.
+/// Reads the edges of a graph, together with their attributes.
 ///
 /// Note that this function consumes the graph, and gives it back in the returned Future, with
 /// the edges added.
-async fn read_graph_edges<P>(base_path: P, edge_vec_hash: Hash, mut graph: DirectedGraph) -> Result<DirectedGraph>
-    where P: AsRef<Path>,
-          P: Clone
+async fn read_graph_edges<S>(store: &S, edge_tree_hash: Hash, mut graph: AttributedGraph) -> Result<AttributedGraph>
+    where S: ObjectStore + ?Sized
 {
-    let hash_vec: HashVec<HashEdge> = read_object(base_path.clone(), edge_vec_hash).await?;
-    let edges = read_all_edges(base_path, hash_vec.0).await?;
+    let hashes = read_tree::<S, HashEdge>(store, edge_tree_hash).await?;
+    let mut edges = read_edges(store, hashes);
 
-    for e in edges {
-        graph.add_edge(e);
+    while let Some((e, attributes)) = edges.try_next().await? {
+        graph.add_edge_with_attributes(e, attributes);
     }
 
     Ok(graph)
 }
 
-async fn read_graph<P>(base_path: P, graph_hash: &GraphHash) -> Result<DirectedGraph>
-    where P: AsRef<Path>,
-          P: Clone
+async fn read_graph<S>(store: &S, graph_hash: &GraphHash) -> Result<AttributedGraph>
+    where S: ObjectStore + ?Sized
 {
-    let &GraphHash { vertex_vec_hash, edge_vec_hash } = graph_hash;
+    let &GraphHash { vertex_tree_hash, edge_tree_hash } = graph_hash;
 
-    let graph = read_graph_vertices(base_path.clone(), vertex_vec_hash, DirectedGraph::new()).await?;
-    read_graph_edges(base_path, edge_vec_hash, graph).await
+    let graph = read_graph_vertices(store, vertex_tree_hash, AttributedGraph::new()).await?;
+    read_graph_edges(store, edge_tree_hash, graph).await
+}
+
+/// Reads the graph of the [`Commit`] with the [`Hash`] `commit_hash`.
+///
+/// [`Commit`]: ../object/struct.Commit.html
+/// [`Hash`]: ../struct.Hash.html
+pub async fn read_commit_graph<S>(store: &S, commit_hash: Hash) -> Result<AttributedGraph>
+    where S: ObjectStore + ?Sized
+{
+    let commit: Commit = read_object(store, commit_hash).await?;
+    let graph_hash: GraphHash = read_object(store, commit.graph_hash).await?;
+    read_graph(store, &graph_hash).await
 }
 
-pub async fn load_graph<P>(base_path: P, name: String) -> Result<DirectedGraph>
-    where P: AsRef<Path>,
-          P: Clone
+/// Loads the latest version of the graph `name`.
+pub async fn load_graph<S>(store: &S, name: String) -> Result<AttributedGraph>
+    where S: ObjectStore + ?Sized
 {
-    let graph_hash = read_named_object::<P, String, GraphHash>(base_path.clone(), name).await?;
-    read_graph(base_path, &graph_hash).await
+    let commit_hash = read_ref(store, &name).await?;
+    read_commit_graph(store, commit_hash).await
+}
+
+/// Loads the latest version of the graph `name`, together with the [`Hash`] of its commit, which
+/// is to be passed to [`save_graph_with_parent`] when saving a modification of the graph.
+///
+/// [`Hash`]: ../struct.Hash.html
+/// [`save_graph_with_parent`]: ./fn.save_graph_with_parent.html
+pub async fn load_graph_with_commit<S>(store: &S, name: String) -> Result<(AttributedGraph, Hash)>
+    where S: ObjectStore + ?Sized
+{
+    let commit_hash = read_ref(store, &name).await?;
+    let graph = read_commit_graph(store, commit_hash).await?;
+    Ok((graph, commit_hash))
 }
 
 #[cfg(test)]
 mod test {
     use histo_graph_core::graph::graph::{VertexId, Edge};
-    use std::path::{PathBuf, Path};
     use crate::{
-        error::Result,
-        file::File,
+        error::{Result, Error::{ConcurrentModification, ConfigMismatch, RefNotFound, ObjectNotFound, CorruptObject, NotInitialized, AmbiguousHash}},
+        store::{DirectoryStore, MemoryStore, StoreConfig, Backend, Compression, create_store, open_store, counting::CountingStore},
     };
 
-    use tokio::runtime::Runtime;
+    use std::path::Path;
+    use tokio::{fs, runtime::Runtime};
 
     use super::*;
     use crate::object::HashEdge;
-    use histo_graph_core::graph::directed_graph::DirectedGraph;
+    use histo_graph_core::graph::attributed_graph::AttributedGraph;
 
     #[test]
     fn test_write_read_vertex() -> Result<()> {
         let mut rt = Runtime::new()?;
         rt.block_on(async {
-            let base_path: PathBuf = Path::new("../target/test/store/").into();
+            let store = DirectoryStore::new("../target/test/store/");
 
-            let vertex = VertexId(27);
+            let vertex = StoredVertex::new(VertexId(27), &Attributes::new());
 
-            fs::create_dir_all(File::<VertexId>::create_dir(base_path.clone())).await?;
+            let hash = write_object(&store, &vertex).await?;
 
-            let hash = write_object(base_path.clone(), &vertex).await?;
+            let result = read_object::<DirectoryStore, StoredVertex>(&store, hash).await?;
 
-            let result = read_object::<PathBuf, VertexId>(base_path, hash).await?;
-
-            Ok(assert_eq!(vertex, result))
+            assert_eq!(vertex.id, result.id);
+            Ok(())
         })
     }
 
@@ -311,21 +527,22 @@ mod test {
     fn test_write_read_edge() -> Result<()> {
         let mut rt = Runtime::new()?;
         rt.block_on(async {
-            let base_path: PathBuf = Path::new("../target/test/store/").into();
+            let store = DirectoryStore::new("../target/test/store/");
             let edge = Edge(VertexId(3), VertexId(4));
 
-            fs::create_dir_all(File::<VertexId>::create_dir(base_path.clone())).await?;
-            fs::create_dir_all(File::<HashEdge>::create_dir(base_path.clone())).await?;
-
-            let f1 = write_object(base_path.clone(), &edge.0);
-            let f2 = write_object(base_path.clone(), &edge.1);
-            let f3 = write_object(base_path.clone(), &edge);
+            let (from, to) = futures::future::try_join(
+                write_object(&store, &StoredVertex::new(edge.0, &Attributes::new())),
+                write_object(&store, &StoredVertex::new(edge.1, &Attributes::new())),
+            ).await?;
 
-            let (_, _, hash) = futures::future::try_join3(f1, f2, f3).await?;
+            let mut attributes = Attributes::new();
+            attributes.insert("label".to_string(), "knows".into());
+            let hash_edge = HashEdge { from, to, attributes: to_stored_attributes(&attributes) };
+            let hash = write_object(&store, &hash_edge).await?;
 
-            let result = read_edge(base_path, hash).await?;
+            let result = read_edge(&store, hash).await?;
 
-            Ok(assert_eq!(edge, result))
+            Ok(assert_eq!((edge, attributes), result))
         })
     }
 
@@ -333,18 +550,19 @@ mod test {
     fn test_write_read_graph_vertices() -> Result<()> {
         let mut rt = Runtime::new()?;
         rt.block_on(async {
-            let base_path: PathBuf = Path::new("../target/test/store/").into();
+            let store = DirectoryStore::new("../target/test/store/");
 
             let graph = {
-                let mut graph = DirectedGraph::new();
+                let mut graph = AttributedGraph::new();
                 graph.add_vertex(VertexId(14));
                 graph.add_vertex(VertexId(17));
+                graph.set_vertex_attribute(VertexId(17), "weight".to_string(), 1.5.into());
                 graph
             };
 
-            let hash = write_graph_vertices(base_path.clone(), &graph).await?;
+            let (hash, _) = write_graph_vertices(&store, &graph).await?;
 
-            let result = read_graph_vertices(base_path, hash, DirectedGraph::new()).await?;
+            let result = read_graph_vertices(&store, hash, AttributedGraph::new()).await?;
 
             Ok(assert_eq!(graph, result))
         })
@@ -354,17 +572,17 @@ mod test {
     fn test_write_read_graph() -> Result<()> {
         let mut rt = Runtime::new()?;
         rt.block_on(async {
-            let base_path: PathBuf = Path::new("../target/test/store/").into();
+            let store = DirectoryStore::new("../target/test/store/");
 
             let graph = {
-                let mut graph = DirectedGraph::new();
+                let mut graph = AttributedGraph::new();
                 graph.add_vertex(VertexId(14));
                 graph.add_edge(Edge(VertexId(14), VertexId(15)));
                 graph
             };
 
-            let hash = write_graph(base_path.clone(), &graph).await?;
-            let result = read_graph(base_path, &hash).await?;
+            let hash = write_graph(&store, &graph).await?;
+            let result = read_graph(&store, &hash).await?;
 
             Ok(assert_eq!(graph, result))
         })
@@ -374,21 +592,238 @@ mod test {
     fn test_save_as_and_load_graph() -> Result<()> {
         let mut rt = Runtime::new()?;
         rt.block_on(async {
-            let base_path: PathBuf = Path::new("../target/test/store/").into();
+            let store = DirectoryStore::new("../target/test/store/");
             let name = "graph_pepi".to_string();
 
             let graph = {
-                let mut graph = DirectedGraph::new();
+                let mut graph = AttributedGraph::new();
                 graph.add_vertex(VertexId(19));
                 graph.add_edge(Edge(VertexId(12), VertexId(19)));
                 graph
             };
 
-            save_graph_as(base_path.clone(), name.clone(), &graph).await?;
-            let result = load_graph(base_path, name).await?;
+            save_graph_as(&store, name.clone(), &graph).await?;
+            let result = load_graph(&store, name).await?;
 
             Ok(assert_eq!(graph, result))
         })
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_save_as_and_load_graph_in_memory() -> Result<()> {
+        let mut rt = Runtime::new()?;
+        rt.block_on(async {
+            let store = MemoryStore::new();
+            let name = "graph_pepi".to_string();
+
+            let mut graph = AttributedGraph::new();
+            graph.add_edge(Edge(VertexId(12), VertexId(19)));
+            graph.set_edge_attribute(Edge(VertexId(12), VertexId(19)), "label".to_string(), "knows".into());
+            // equal edges have the same hash, and are all kept
+            graph.add_edge(Edge(VertexId(12), VertexId(19)));
+
+            save_graph_as(&store, name.clone(), &graph).await?;
+            let loaded = load_graph(&store, name).await?;
+            assert_eq!(loaded.edges().count(), 2);
+            assert_eq!(loaded, graph);
+            assert_eq!(store.list_objects(Commit::storage_name()).await?.len(), 1);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_store_configs_give_same_results() -> Result<()> {
+        let mut rt = Runtime::new()?;
+        rt.block_on(async {
+            let base_path = "../target/test/store_configs/";
+            let _ = fs::remove_dir_all(base_path).await;
+            let name = "graph_pepi".to_string();
+
+            let mut graph = AttributedGraph::new();
+            graph.add_edge(Edge(VertexId(12), VertexId(19)));
+            graph.set_vertex_attribute(VertexId(19), "name".to_string(), "nineteen".into());
+
+            let mut graph_hashes: Vec<Hash> = Vec::new();
+            let configs = [
+                (StoreConfig { backend: Backend::Directory, compression: Compression::None, ..StoreConfig::default() }, "directory"),
+                (StoreConfig { backend: Backend::Database, compression: Compression::None, ..StoreConfig::default() }, "database"),
+                (StoreConfig { backend: Backend::Directory, compression: Compression::Deflate, ..StoreConfig::default() }, "compressed"),
+                (StoreConfig { hash_algorithm: HashAlgorithm::Blake3, ..StoreConfig::default() }, "blake3"),
+            ];
+            for (config, path) in configs.iter() {
+                let path = Path::new(base_path).join(path);
+                let store = create_store(&path, config.clone())?;
+                let commit_hash = save_graph_as(&*store, name.clone(), &graph).await?;
+                assert_eq!(load_graph(&*store, name.clone()).await?, graph);
+                graph_hashes.push(read_object::<_, Commit>(&*store, commit_hash).await?.graph_hash);
+            }
+
+            assert_eq!(graph_hashes[0], graph_hashes[1]);
+            assert_eq!(graph_hashes[0], graph_hashes[2]);
+            assert_ne!(graph_hashes[0], graph_hashes[3]);
+            assert_eq!(open_store(Path::new(base_path).join("blake3"))?.hash_algorithm(), HashAlgorithm::Blake3);
+
+            // the configuration that a store has been created with is recorded
+            assert!(matches!(create_store(Path::new(base_path).join("database"), StoreConfig::default()), Err(ConfigMismatch { .. })));
+            assert!(create_store(Path::new(base_path).join("compressed"), StoreConfig::default()).is_err());
+            assert!(create_store(Path::new(base_path).join("blake3"), StoreConfig::default()).is_err());
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_save_skips_existing_objects() -> Result<()> {
+        let mut rt = Runtime::new()?;
+        rt.block_on(async {
+            let store = CountingStore::default();
+            let name = "graph_skip".to_string();
+
+            let mut graph = AttributedGraph::new();
+            graph.add_vertex(VertexId(1));
+            save_graph_as(&store, name.clone(), &graph).await?;
+            assert_eq!(store.puts(StoredVertex::storage_name()), 1);
+
+            // only the added vertex is written, not the one that exists already
+            graph.add_vertex(VertexId(2));
+            save_graph_as(&store, name.clone(), &graph).await?;
+            assert_eq!(store.puts(StoredVertex::storage_name()), 2);
+            assert_eq!(store.list_objects(StoredVertex::storage_name()).await?.len(), 2);
+
+            // saving the same graph again writes a commit, but no vertices
+            save_graph_as(&store, name, &graph).await?;
+            assert_eq!(store.puts(StoredVertex::storage_name()), 2);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_save_as_creates_history() -> Result<()> {
+        let mut rt = Runtime::new()?;
+        rt.block_on(async {
+            let store = DirectoryStore::new("../target/test/store/");
+            let name = "graph_history".to_string();
+
+            let mut graph = AttributedGraph::new();
+            graph.add_vertex(VertexId(1));
+            graph.set_vertex_attribute(VertexId(1), "name".to_string(), "one".into());
+            let first_hash = save_graph_as(&store, name.clone(), &graph).await?;
+
+            graph.add_edge(Edge(VertexId(1), VertexId(2)));
+            graph.set_vertex_attribute(VertexId(1), "name".to_string(), "uno".into());
+            graph.set_edge_attribute(Edge(VertexId(1), VertexId(2)), "weight".to_string(), 0.5.into());
+            let second_hash = save_graph_as(&store, name.clone(), &graph).await?;
+
+            let second: Commit = read_object(&store, second_hash).await?;
+            assert_eq!(second.parents.len(), 1);
+            assert_eq!(second.parents[0].to_string(), first_hash.to_string());
+
+            let first_graph = read_commit_graph(&store, first_hash).await?;
+            assert_eq!(first_graph.graph().edge_count(), 0);
+            assert_eq!(first_graph.vertex_attribute(VertexId(1), "name"), Some(&"one".into()));
+
+            let result = load_graph(&store, name).await?;
+            assert_eq!(graph, result);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_save_with_parent_detects_concurrent_modification() -> Result<()> {
+        let mut rt = Runtime::new()?;
+        rt.block_on(async {
+            let store = DirectoryStore::new("../target/test/store/");
+            let name = "graph_concurrent".to_string();
+
+            save_graph_as(&store, name.clone(), &AttributedGraph::new()).await?;
+
+            // two writers load the same version
+            let (mut graph_1, parent_1) = load_graph_with_commit(&store, name.clone()).await?;
+            let (mut graph_2, parent_2) = load_graph_with_commit(&store, name.clone()).await?;
+
+            graph_1.add_vertex(VertexId(1));
+            let hash_1 = save_graph_with_parent(&store, name.clone(), Some(parent_1), &graph_1).await?;
+
+            graph_2.add_vertex(VertexId(2));
+            match save_graph_with_parent(&store, name.clone(), Some(parent_2), &graph_2).await {
+                Err(ConcurrentModification { expected, actual, .. }) => {
+                    assert_eq!(expected, Some(parent_2));
+                    assert_eq!(actual, Some(hash_1));
+                }
+                result => panic!("unexpected result {:?}", result),
+            }
+
+            assert_eq!(load_graph(&store, name).await?, graph_1);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_errors_name_what_is_missing() -> Result<()> {
+        let mut rt = Runtime::new()?;
+        rt.block_on(async {
+            let store = MemoryStore::new();
+            let mut graph = AttributedGraph::new();
+            graph.add_edge(Edge(VertexId(1), VertexId(2)));
+            save_graph_as(&store, "main".to_string(), &graph).await?;
+
+            match load_graph(&store, "other".to_string()).await {
+                Err(RefNotFound { name }) => assert_eq!(name, "other"),
+                result => panic!("unexpected result {:?}", result),
+            }
+
+            let vertex_hash: Hash = write_object(&store, &StoredVertex::new(VertexId(1), &Attributes::new())).await?;
+            store.remove_object(StoredVertex::storage_name(), vertex_hash).await?;
+            match load_graph(&store, "main".to_string()).await {
+                Err(ObjectNotFound { object_type, hash }) => assert_eq!((object_type.as_str(), hash), ("vertex", vertex_hash)),
+                result => panic!("unexpected result {:?}", result),
+            }
+
+            store.put_object(StoredVertex::storage_name(), vertex_hash, vec![1, 2, 3]).await?;
+            match load_graph(&store, "main".to_string()).await {
+                Err(CorruptObject { object_type, hash }) => assert_eq!((object_type.as_str(), hash), ("vertex", vertex_hash)),
+                result => panic!("unexpected result {:?}", result),
+            }
+
+            match open_store("../target/test/no_store/") {
+                Err(NotInitialized { path }) => assert_eq!(path, Path::new("../target/test/no_store/")),
+                result => panic!("unexpected result {:?}", result.map(|_| ())),
+            }
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_resolve_commit() -> Result<()> {
+        let mut rt = Runtime::new()?;
+        rt.block_on(async {
+            let store = MemoryStore::new();
+            let mut graph = AttributedGraph::new();
+            graph.add_vertex(VertexId(1));
+            let first_hash = save_graph_as(&store, "main".to_string(), &graph).await?;
+            graph.add_vertex(VertexId(2));
+            let second_hash = save_graph_as(&store, "main".to_string(), &graph).await?;
+
+            let first = first_hash.to_string();
+            assert_eq!(resolve_commit(&store, "main").await?, second_hash);
+            assert_eq!(resolve_commit(&store, &first).await?, first_hash);
+            assert_eq!(resolve_commit(&store, &first[..6].to_ascii_uppercase()).await?, first_hash);
+            assert_eq!(read_commit_graph(&store, resolve_commit(&store, &first[..6]).await?).await?.vertices().count(), 1);
+            assert!(matches!(resolve_commit(&store, &first[..3]).await, Err(RefNotFound { .. })));
+            assert!(matches!(resolve_commit(&store, "other").await, Err(RefNotFound { .. })));
+
+            let hash_1: Hash = format!("abcd0{}", &first[5..]).parse()?;
+            let hash_2: Hash = format!("abcd1{}", &first[5..]).parse()?;
+            store.put_object(Commit::storage_name(), hash_1, Vec::new()).await?;
+            store.put_object(Commit::storage_name(), hash_2, Vec::new()).await?;
+            match resolve_commit(&store, "abcd").await {
+                Err(AmbiguousHash { prefix, candidates }) => {
+                    assert_eq!(prefix, "abcd");
+                    assert_eq!(candidates, vec![hash_1, hash_2]);
+                }
+                result => panic!("unexpected result {:?}", result),
+            }
+            assert_eq!(resolve_commit(&store, "abcd1").await?, hash_2);
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,335 @@
+//! Implements the verification of the integrity of the stored objects.
+//!
+//! Every stored object is named by the [`Hash`] of its content. The verification re-hashes the
+//! content of every stored object, and follows all named references through the history of
+//! commits down to the vertices, to find objects that are corrupt, missing, or not reachable.
+//!
+//! [`Hash`]: ../struct.Hash.html
+
+use std::{
+    collections::HashSet,
+    convert::TryInto,
+    fmt,
+};
+
+use crate::{
+    Hash,
+    branch::list_branches,
+    error::{Result, Error::{ObjectNotFound, CorruptObject}},
+    file::File,
+    object::{ObjectType, StoredVertex, HashEdge, HashVec, HashChunk, GraphHash, Commit},
+    store::{ObjectStore, ObjectId},
+};
+
+/// A problem that has been found by the verification.
+#[derive(Debug, PartialEq)]
+pub enum Problem {
+
+    /// The content of the object does not match its hash, cannot be decompressed, or cannot be
+    /// read as an object of its type.
+    Corrupt(ObjectId),
+
+    /// The named reference cannot be read.
+    CorruptRef(String),
+
+    /// The object is referred to by another object, or by a named reference, but does not exist.
+    Missing { object: ObjectId, referenced_by: String },
+
+    /// The object is not reachable from any named reference.
+    Dangling(ObjectId),
+
+    /// Interrupted writes have left the given number of bytes behind.
+    Leftovers(u64),
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Problem::Corrupt(object) => write!(f, "corrupt: {}", object),
+            Problem::CorruptRef(name) => write!(f, "corrupt: refs/{}", name),
+            Problem::Missing { object, referenced_by } => write!(f, "missing: {} (referenced by {})", object, referenced_by),
+            Problem::Dangling(object) => write!(f, "dangling: {}", object),
+            Problem::Leftovers(bytes) => write!(f, "leftovers of interrupted writes: {} bytes", bytes),
+        }
+    }
+}
+
+/// The result of a verification.
+#[derive(Debug, Default)]
+pub struct FsckReport {
+
+    /// The number of stored objects that have been checked.
+    pub checked: usize,
+
+    /// The problems that have been found.
+    pub problems: Vec<Problem>,
+}
+
+impl FsckReport {
+
+    /// Returns true if no problems have been found.
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Holds the state of a verification.
+struct Checker<'s, S: ?Sized> {
+    store: &'s S,
+
+    /// All stored objects.
+    stored: Vec<ObjectId>,
+
+    /// The objects that have been reached from a named reference.
+    reachable: HashSet<ObjectId>,
+
+    /// The objects that have been found to be corrupt.
+    corrupt: HashSet<ObjectId>,
+
+    problems: Vec<Problem>,
+}
+
+impl<'s, S> Checker<'s, S>
+    where S: ObjectStore + ?Sized
+{
+
+    /// Re-hashes the content of all stored objects of type `OT`.
+    async fn check_hashes<OT>(&mut self) -> Result<()>
+        where OT: ObjectType
+    {
+        for hash in self.store.list_objects(OT::storage_name()).await? {
+            let id = ObjectId { object_type: OT::storage_name(), hash };
+            let content_hash: Option<Hash> = match self.store.get_object(id.object_type, hash).await {
+                Ok(content) => Some(self.store.hash_algorithm().hash(&content)),
+                // the content cannot be decompressed
+                Err(CorruptObject { .. }) => None,
+                Err(e) => return Err(e),
+            };
+            if content_hash != Some(hash) {
+                self.corrupt.insert(id);
+                self.problems.push(Problem::Corrupt(id));
+            }
+            self.stored.push(id);
+        }
+
+        Ok(())
+    }
+
+    /// Marks the object of type `OT` with the [`Hash`] `hash` as reachable, and reads it.
+    /// Returns `None` if the object has been reached before, or if it is missing or corrupt.
+    ///
+    /// [`Hash`]: ../struct.Hash.html
+    async fn visit<OT>(&mut self, hash: Hash, referenced_by: String) -> Result<Option<OT>>
+        where OT: ObjectType,
+              for<'a> &'a File<OT>: TryInto<OT, Error=bincode::Error>
+    {
+        let id = ObjectId { object_type: OT::storage_name(), hash };
+        if !self.reachable.insert(id) || self.corrupt.contains(&id) {
+            return Ok(None);
+        }
+
+        let content = match self.store.get_object(id.object_type, hash).await {
+            Ok(content) => content,
+            Err(ObjectNotFound { .. }) => {
+                self.problems.push(Problem::Missing { object: id, referenced_by });
+                return Ok(None);
+            }
+            Err(e) => return Err(e),
+        };
+
+        match (&File::<OT>::new(content, hash)).try_into() {
+            Ok(object) => Ok(Some(object)),
+            Err(_) => {
+                self.corrupt.insert(id);
+                self.problems.push(Problem::Corrupt(id));
+                Ok(None)
+            }
+        }
+    }
+
+    /// Follows the commit with the [`Hash`] `commit_hash`, its graph, and all its ancestors.
+    ///
+    /// [`Hash`]: ../struct.Hash.html
+    async fn check_history(&mut self, commit_hash: Hash, referenced_by: String) -> Result<()> {
+        let mut commits: Vec<(Hash, String)> = vec![(commit_hash, referenced_by)];
+
+        while let Some((commit_hash, referenced_by)) = commits.pop() {
+            if let Some(commit) = self.visit::<Commit>(commit_hash, referenced_by).await? {
+                let commit_id = ObjectId { object_type: Commit::storage_name(), hash: commit_hash }.to_string();
+                self.check_graph(commit.graph_hash, commit_id.clone()).await?;
+                commits.extend(commit.parents.into_iter().map(|parent| (parent, commit_id.clone())));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Follows the tree with the root chunk `root_hash` down to its chunks of level 0. Returns the
+    /// [`Hash`]es of the objects of type `OT` in the chunks that have been reached for the first
+    /// time, together with the chunk that refers to them.
+    ///
+    /// [`Hash`]: ../struct.Hash.html
+    async fn check_tree<OT>(&mut self, root_hash: Hash, referenced_by: String) -> Result<Vec<(Hash, String)>>
+        where HashChunk<OT>: ObjectType,
+              for<'a> &'a File<HashChunk<OT>>: TryInto<HashChunk<OT>, Error=bincode::Error>
+    {
+        let mut chunks: Vec<(Hash, String)> = vec![(root_hash, referenced_by)];
+        let mut objects: Vec<(Hash, String)> = Vec::new();
+
+        while let Some((chunk_hash, referenced_by)) = chunks.pop() {
+            if let Some(chunk) = self.visit::<HashChunk<OT>>(chunk_hash, referenced_by).await? {
+                let chunk_id = ObjectId { object_type: HashChunk::<OT>::storage_name(), hash: chunk_hash }.to_string();
+                let hashes = chunk.hashes.into_iter().map(|hash| (hash, chunk_id.clone()));
+                if chunk.level == 0 {
+                    objects.extend(hashes);
+                } else {
+                    chunks.extend(hashes);
+                }
+            }
+        }
+
+        Ok(objects)
+    }
+
+    /// Follows the graph with the [`Hash`] `graph_hash` down to its vertices.
+    ///
+    /// [`Hash`]: ../struct.Hash.html
+    async fn check_graph(&mut self, graph_hash: Hash, referenced_by: String) -> Result<()> {
+        let graph: GraphHash = match self.visit(graph_hash, referenced_by).await? {
+            Some(graph) => graph,
+            None => return Ok(()),
+        };
+        let graph_id = ObjectId { object_type: GraphHash::storage_name(), hash: graph_hash }.to_string();
+
+        for (hash, chunk_id) in self.check_tree::<StoredVertex>(graph.vertex_tree_hash, graph_id.clone()).await? {
+            self.visit::<StoredVertex>(hash, chunk_id).await?;
+        }
+
+        for (hash, chunk_id) in self.check_tree::<HashEdge>(graph.edge_tree_hash, graph_id).await? {
+            if let Some(edge) = self.visit::<HashEdge>(hash, chunk_id).await? {
+                let edge_id = ObjectId { object_type: HashEdge::storage_name(), hash }.to_string();
+                for &vertex_hash in [edge.from, edge.to].iter() {
+                    self.visit::<StoredVertex>(vertex_hash, edge_id.clone()).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Verifies the integrity of `store`.
+///
+/// The content of every stored object is re-hashed and compared to its hash. All named references
+/// are followed through the history of commits, the graphs, the trees of vertices and edges,
+/// down to the edges and vertices, to find objects that are missing or cannot be read. Finally,
+/// all stored objects that have not been reached are reported as dangling.
+pub async fn fsck<S>(store: &S) -> Result<FsckReport>
+    where S: ObjectStore + ?Sized
+{
+    let mut checker = Checker {
+        store,
+        stored: Vec::new(),
+        reachable: HashSet::new(),
+        corrupt: HashSet::new(),
+        problems: Vec::new(),
+    };
+
+    checker.check_hashes::<Commit>().await?;
+    checker.check_hashes::<GraphHash>().await?;
+    checker.check_hashes::<HashVec<StoredVertex>>().await?;
+    checker.check_hashes::<HashVec<HashEdge>>().await?;
+    checker.check_hashes::<HashChunk<StoredVertex>>().await?;
+    checker.check_hashes::<HashChunk<HashEdge>>().await?;
+    checker.check_hashes::<HashEdge>().await?;
+    checker.check_hashes::<StoredVertex>().await?;
+
+    for name in list_branches(store).await? {
+        match store.read_ref(&name).await {
+            Ok(Some(commit_hash)) => checker.check_history(commit_hash, format!("refs/{}", name)).await?,
+            _ => checker.problems.push(Problem::CorruptRef(name)),
+        }
+    }
+
+    let Checker { stored, reachable, corrupt, mut problems, .. } = checker;
+    let checked = stored.len();
+    problems.extend(stored
+        .into_iter()
+        .filter(|id| !reachable.contains(id) && !corrupt.contains(id))
+        .map(Problem::Dangling));
+
+    let leftovers = store.remove_leftovers(true).await?;
+    if leftovers > 0 {
+        problems.push(Problem::Leftovers(leftovers));
+    }
+
+    Ok(FsckReport {
+        checked,
+        problems,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use tokio::{fs, runtime::Runtime};
+    use histo_graph_core::graph::{
+        graph::{VertexId, Edge},
+        attributed_graph::AttributedGraph,
+    };
+
+    use super::*;
+    use crate::{
+        branch::{create_branch, delete_branch},
+        file_storage::save_graph_as,
+        store::DirectoryStore,
+    };
+
+    #[test]
+    fn test_fsck() -> Result<()> {
+        let mut rt = Runtime::new()?;
+        rt.block_on(async {
+            // use a separate store, so that it can be damaged
+            let store = DirectoryStore::new("../target/test/fsck_store/");
+            let _ = fs::remove_dir_all(store.base_path()).await;
+
+            let mut graph = AttributedGraph::new();
+            graph.add_edge(Edge(VertexId(1), VertexId(2)));
+            save_graph_as(&store, "main".to_string(), &graph).await?;
+
+            let report = fsck(&store).await?;
+            assert!(report.is_ok());
+            assert_eq!(report.checked, 7);
+
+            create_branch(&store, "feature", "main").await?;
+            graph.add_vertex(VertexId(3));
+            let commit_hash = save_graph_as(&store, "feature".to_string(), &graph).await?;
+            delete_branch(&store, "feature").await?;
+
+            // the commit, the graph, the vector of vertices and the new vertex are dangling
+            let report = fsck(&store).await?;
+            assert_eq!(report.problems.len(), 4);
+            let commit = ObjectId { object_type: Commit::storage_name(), hash: commit_hash };
+            assert!(report.problems.contains(&Problem::Dangling(commit)));
+
+            // a damaged vertex is corrupt, a removed edge is missing
+            let vertex_hash = Hash::sha256(&bincode::serialize(&StoredVertex::new(VertexId(1), &Default::default()))?);
+            let vertex = ObjectId { object_type: StoredVertex::storage_name(), hash: vertex_hash };
+            fs::write(store.object_path(vertex.object_type, vertex.hash), b"damaged").await?;
+
+            let edge_hash = store.list_objects(HashEdge::storage_name()).await?[0];
+            let edge = ObjectId { object_type: HashEdge::storage_name(), hash: edge_hash };
+            store.remove_object(edge.object_type, edge.hash).await?;
+
+            store.remove_object(commit.object_type, commit.hash).await?;
+
+            let report = fsck(&store).await?;
+            assert!(report.problems.contains(&Problem::Corrupt(vertex)));
+            assert!(report.problems.iter().any(|problem| match problem {
+                Problem::Missing { object, .. } => object == &edge,
+                _ => false,
+            }));
+            assert_eq!(report.problems.len(), 5);
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,226 @@
+//! Implements the garbage collection of stored objects.
+//!
+//! Objects that cannot be reached from any named reference, e.g. the objects of a deleted branch,
+//! are never read again. The garbage collection is a mark-and-sweep: first all objects that are
+//! reachable from the named references are marked, then all objects that are not marked are
+//! removed.
+
+use std::{
+    collections::HashSet,
+    convert::TryInto,
+};
+
+use crate::{
+    Hash,
+    branch::list_branches,
+    error::Result,
+    file::File,
+    file_storage::{read_object, read_ref},
+    object::{ObjectType, StoredVertex, HashEdge, HashVec, HashChunk, GraphHash, Commit},
+    store::{ObjectStore, ObjectId},
+};
+
+/// The result of a garbage collection.
+#[derive(Debug, Default)]
+pub struct GcReport {
+
+    /// The unreachable objects.
+    pub unreachable: Vec<ObjectId>,
+
+    /// The total size of the unreachable objects, and of the leftovers of interrupted writes, in
+    /// bytes.
+    pub bytes: u64,
+}
+
+/// Marks the object of type `OT` with the [`Hash`] `hash` as reachable.
+/// Returns false if the object has already been marked before.
+///
+/// [`Hash`]: ../struct.Hash.html
+fn mark<OT>(marked: &mut HashSet<ObjectId>, hash: Hash) -> bool
+    where OT: ObjectType
+{
+    marked.insert(ObjectId { object_type: OT::storage_name(), hash })
+}
+
+/// Marks the chunks of the tree with the root chunk `root_hash`. Chunks that have been marked
+/// before are skipped, together with the chunks below them, as they are shared with a graph that
+/// has been marked before. Returns the [`Hash`]es of the objects of type `OT` in the chunks that
+/// have been marked.
+///
+/// [`Hash`]: ../struct.Hash.html
+async fn mark_tree<S, OT>(marked: &mut HashSet<ObjectId>, store: &S, root_hash: Hash) -> Result<Vec<Hash>>
+    where S: ObjectStore + ?Sized,
+          HashChunk<OT>: ObjectType,
+          for<'a> &'a File<HashChunk<OT>>: TryInto<HashChunk<OT>, Error=bincode::Error>
+{
+    let mut chunks: Vec<Hash> = vec![root_hash];
+    let mut objects: Vec<Hash> = Vec::new();
+
+    while let Some(chunk_hash) = chunks.pop() {
+        if mark::<HashChunk<OT>>(marked, chunk_hash) {
+            let chunk: HashChunk<OT> = read_object(store, chunk_hash).await?;
+            if chunk.level == 0 {
+                objects.extend(chunk.hashes);
+            } else {
+                chunks.extend(chunk.hashes);
+            }
+        }
+    }
+
+    Ok(objects)
+}
+
+/// Marks the graph with the [`Hash`] `graph_hash` and all the objects it consists of.
+///
+/// [`Hash`]: ../struct.Hash.html
+async fn mark_graph<S>(marked: &mut HashSet<ObjectId>, store: &S, graph_hash: Hash) -> Result<()>
+    where S: ObjectStore + ?Sized
+{
+    if !mark::<GraphHash>(marked, graph_hash) {
+        return Ok(());
+    }
+    let GraphHash { vertex_tree_hash, edge_tree_hash } = read_object(store, graph_hash).await?;
+
+    for hash in mark_tree::<S, StoredVertex>(marked, store, vertex_tree_hash).await? {
+        mark::<StoredVertex>(marked, hash);
+    }
+
+    for hash in mark_tree::<S, HashEdge>(marked, store, edge_tree_hash).await? {
+        if mark::<HashEdge>(marked, hash) {
+            let HashEdge { from, to, .. } = read_object(store, hash).await?;
+            mark::<StoredVertex>(marked, from);
+            mark::<StoredVertex>(marked, to);
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns all objects that are reachable from a named reference, through the history of commits.
+async fn mark_reachable<S>(store: &S) -> Result<HashSet<ObjectId>>
+    where S: ObjectStore + ?Sized
+{
+    let mut marked: HashSet<ObjectId> = HashSet::new();
+
+    let mut commits: Vec<Hash> = Vec::new();
+    for name in list_branches(store).await? {
+        commits.push(read_ref(store, &name).await?);
+    }
+
+    while let Some(commit_hash) = commits.pop() {
+        if mark::<Commit>(&mut marked, commit_hash) {
+            let commit: Commit = read_object(store, commit_hash).await?;
+            mark_graph(&mut marked, store, commit.graph_hash).await?;
+            commits.extend(commit.parents);
+        }
+    }
+
+    Ok(marked)
+}
+
+/// Collects the objects of type `OT` that are not `marked`, and adds them to the `report`.
+async fn sweep<S, OT>(marked: &HashSet<ObjectId>, store: &S, report: &mut GcReport) -> Result<()>
+    where S: ObjectStore + ?Sized,
+          OT: ObjectType
+{
+    for hash in store.list_objects(OT::storage_name()).await? {
+        let id = ObjectId { object_type: OT::storage_name(), hash };
+        if !marked.contains(&id) {
+            report.bytes += store.object_size(id.object_type, hash).await?;
+            report.unreachable.push(id);
+        }
+    }
+
+    Ok(())
+}
+
+/// Removes all objects that are not reachable from any named reference, and returns what has been
+/// removed. If `dry_run` is true, nothing is removed, and the returned report tells what would be
+/// removed. The leftovers of interrupted writes are removed as well.
+///
+/// The store is locked while the garbage collection is running, so that objects that are being
+/// written, and are not yet referred to by a named reference, are not removed.
+pub async fn collect_garbage<S>(store: &S, dry_run: bool) -> Result<GcReport>
+    where S: ObjectStore + ?Sized
+{
+    let _guard = store.lock().await?;
+
+    let marked = mark_reachable(store).await?;
+
+    let mut report = GcReport::default();
+    sweep::<S, Commit>(&marked, store, &mut report).await?;
+    sweep::<S, GraphHash>(&marked, store, &mut report).await?;
+    // the vectors of the graphs of the formats before 3 are left behind by the migration
+    sweep::<S, HashVec<StoredVertex>>(&marked, store, &mut report).await?;
+    sweep::<S, HashVec<HashEdge>>(&marked, store, &mut report).await?;
+    sweep::<S, HashChunk<StoredVertex>>(&marked, store, &mut report).await?;
+    sweep::<S, HashChunk<HashEdge>>(&marked, store, &mut report).await?;
+    sweep::<S, HashEdge>(&marked, store, &mut report).await?;
+    sweep::<S, StoredVertex>(&marked, store, &mut report).await?;
+    report.unreachable.sort();
+
+    if !dry_run {
+        store.remove_objects(&report.unreachable).await?;
+    }
+    report.bytes += store.remove_leftovers(dry_run).await?;
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod test {
+    use tokio::runtime::Runtime;
+    use histo_graph_core::graph::{
+        graph::{VertexId, Edge},
+        attributed_graph::AttributedGraph,
+    };
+
+    use super::*;
+    use crate::{
+        branch::{create_branch, delete_branch},
+        file_storage::{save_graph_as, load_graph},
+        store::{MemoryStore, CompressedStore, Compression},
+    };
+
+    #[test]
+    fn test_collect_garbage() -> Result<()> {
+        let mut rt = Runtime::new()?;
+        rt.block_on(async {
+            let inner = MemoryStore::new();
+            let store = CompressedStore::new(inner.clone(), Compression::Deflate);
+
+            let mut graph = AttributedGraph::new();
+            graph.add_edge(Edge(VertexId(1), VertexId(2)));
+            save_graph_as(&store, "main".to_string(), &graph).await?;
+            assert_eq!(collect_garbage(&store, false).await?.unreachable.len(), 0);
+
+            create_branch(&store, "feature", "main").await?;
+            let mut feature = graph.clone();
+            feature.add_edge(Edge(VertexId(2), VertexId(3)));
+            save_graph_as(&store, "feature".to_string(), &feature).await?;
+            delete_branch(&store, "feature").await?;
+
+            // the commit, the graph, both hash vectors, the new edge and the new vertex
+            let report = collect_garbage(&store, true).await?;
+            assert_eq!(report.unreachable.len(), 6);
+            // the bytes are the ones that the objects take in the store, after compression
+            let mut stored_bytes = 0;
+            for id in report.unreachable.iter() {
+                assert!(store.contains_object(id.object_type, id.hash).await?);
+                stored_bytes += inner.get_object(id.object_type, id.hash).await?.len() as u64;
+            }
+            assert!(report.bytes > 0);
+            assert_eq!(report.bytes, stored_bytes);
+
+            let report = collect_garbage(&store, false).await?;
+            assert_eq!(report.unreachable.len(), 6);
+            for id in report.unreachable.iter() {
+                assert!(!store.contains_object(id.object_type, id.hash).await?);
+            }
+
+            assert_eq!(load_graph(&store, "main".to_string()).await?, graph);
+            assert_eq!(collect_garbage(&store, true).await?.unreachable.len(), 0);
+            Ok(())
+        })
+    }
+}
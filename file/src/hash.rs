@@ -1,55 +1,68 @@
-//! This module defines the struct `Hash` that represents the SHA256 hash of a serialized object
+//! This module defines the struct `Hash` that represents the hash of a serialized object
 
+use std::{fmt, str::FromStr};
 use data_encoding::HEXLOWER;
 use ring::digest::{Context, SHA256};
 use serde::{Serialize, Deserialize};
 
-/// A struct that represents the SHA256 hash of a serialized object.
+use crate::error::{Result, Error, Error::InvalidHash};
+
+/// A struct that represents the hash of a serialized object, computed by the [`HashAlgorithm`] of
+/// the store that holds the object, SHA256 by default.
+///
+/// [`HashAlgorithm`]: ./store/config/enum.HashAlgorithm.html
 ///
 /// # Examples
 ///
 /// ```
 /// use histo_graph_core::graph::graph::VertexId;
-/// use histo_graph_file::Hash;
+/// use histo_graph_file::{Hash, store::HashAlgorithm};
 ///
 /// # fn main() -> std::result::Result<(), bincode::Error> {
 /// let id = 27u64;
 /// let serialized: Vec<u8> = bincode::serialize(&id)?;
-/// let hash: Hash = (&serialized).into();
+/// let hash: Hash = HashAlgorithm::Sha256.hash(&serialized);
 /// let str = hash.to_string();
 /// assert_eq!(str, "4d159113222bfeb85fbe717cc2393ee8a6a85b7ce5ac1791c4eade5e3dd6de41");
 /// # Ok(())
 /// # }
 /// ```
-#[derive(Clone, Copy, Serialize, Deserialize)]
+///
+/// A `Hash` is displayed as the hex-representation of its bytes, which is also used as a
+/// filename, and parsed from it:
+///
+/// ```
+/// use histo_graph_file::Hash;
+///
+/// let hash: Hash = "4d159113222bfeb85fbe717cc2393ee8a6a85b7ce5ac1791c4eade5e3dd6de41".parse().unwrap();
+/// assert_eq!(hash.to_string().parse::<Hash>().unwrap(), hash);
+/// assert!("4d1591".parse::<Hash>().is_err());
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct Hash([u8; 32]);
 
+/// The least number of characters of an abbreviated hash, e.g. of `4d15`.
+pub const MIN_PREFIX_LENGTH: usize = 4;
+
 impl Hash {
 
-    /// turns the hash into a `String`, so that it can be used a a filename.
-    pub fn to_string(&self) -> String {
-        HEXLOWER.encode(&self.0)
+    /// Returns true if the hex-representation of the hash starts with `prefix`, ignoring its case.
+    pub fn starts_with(&self, prefix: &str) -> bool {
+        self.to_string().starts_with(&prefix.to_ascii_lowercase())
     }
-}
 
-impl<T> From<T> for Hash
-    where T: AsRef<[u8]> {
-    /// transforms a serialized object into a `Hash`.
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// use histo_graph_core::graph::graph::VertexId;
-    /// use histo_graph_file::Hash;
+    /// creates a hash from the bytes that a hash algorithm has computed.
+    pub(crate) fn from_bytes(bytes: [u8; 32]) -> Hash {
+        Hash(bytes)
+    }
+
+    /// computes the SHA256 `Hash` of `content`. Other crates hash with the [`HashAlgorithm`] of
+    /// the store, which is SHA256 only by default.
     ///
-    /// # fn main() -> std::result::Result<(), bincode::Error> {
-    /// let id = 27u64;
-    /// let serialized: Vec<u8> = bincode::serialize(&id)?;
-    /// let hash: Hash = (&serialized).into();
-    /// # Ok(())
-    /// # }
-    /// ```
-    fn from(content: T) -> Hash {
+    /// [`HashAlgorithm`]: ./store/config/enum.HashAlgorithm.html
+    pub(crate) fn sha256<C>(content: C) -> Hash
+        where C: AsRef<[u8]>
+    {
         let mut context = Context::new(&SHA256);
         context.update(content.as_ref());
         let digest = context.finish();
@@ -58,4 +71,45 @@ impl<T> From<T> for Hash
 
         Hash(hash)
     }
+
+    /// returns the bytes of the hash.
+    pub(crate) fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// parses a `String` that has been returned by `to_string`, e.g. a filename. Returns `None` if
+    /// `s` is not such a `String`.
+    pub(crate) fn from_hex(s: &str) -> Option<Hash> {
+        let bytes = HEXLOWER.decode(s.as_bytes()).ok()?;
+        if bytes.len() != 32 {
+            return None;
+        }
+        let mut hash: [u8; 32] = [0u8; 32];
+        hash.copy_from_slice(&bytes);
+        Some(Hash(hash))
+    }
+}
+
+impl fmt::Display for Hash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&HEXLOWER.encode(&self.0))
+    }
+}
+
+impl fmt::Debug for Hash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Hash({})", self)
+    }
+}
+
+impl FromStr for Hash {
+    type Err = Error;
+
+    /// Parses the hex-representation of a hash, in upper or lower case. Fails with
+    /// [`InvalidHash`], if `s` is not the hex-representation of a whole hash.
+    ///
+    /// [`InvalidHash`]: ./error/enum.Error.html#variant.InvalidHash
+    fn from_str(s: &str) -> Result<Hash> {
+        Hash::from_hex(&s.to_ascii_lowercase()).ok_or_else(|| InvalidHash { input: s.to_string() })
+    }
 }
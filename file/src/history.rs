@@ -0,0 +1,283 @@
+//! Reads the history of a graph: the commits of its versions, with who saved them, when and why,
+//! and loads the graph as it has been at an earlier time.
+
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BinaryHeap, HashSet},
+};
+use futures::stream::{self, Stream};
+use histo_graph_core::graph::attributed_graph::AttributedGraph;
+
+use crate::{
+    Hash,
+    error::{Result, Error::VersionNotFound},
+    file_storage::{read_commit_graph, read_object, resolve_commit},
+    object::Commit,
+    store::ObjectStore,
+};
+
+/// A version of a graph in its history: the [`Hash`] of its commit, the commits it is based on,
+/// and the time, author and message that it has been saved with.
+///
+/// [`Hash`]: ../struct.Hash.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEntry {
+
+    /// The [`Hash`] of the commit.
+    ///
+    /// [`Hash`]: ../struct.Hash.html
+    pub hash: Hash,
+
+    /// The [`Hash`]es of the parent commits. The first commit of a history has no parents, a
+    /// merge commit has two.
+    ///
+    /// [`Hash`]: ../struct.Hash.html
+    pub parents: Vec<Hash>,
+
+    /// The time of the commit, in seconds since the UNIX epoch, UTC.
+    pub timestamp: i64,
+
+    /// The author of the commit, empty if none has been given.
+    pub author: String,
+
+    /// The message of the commit, empty if none has been given.
+    pub message: String,
+}
+
+/// Reads the commit with the [`Hash`] `hash`.
+///
+/// [`Hash`]: ../struct.Hash.html
+pub async fn read_log_entry<S>(store: &S, hash: Hash) -> Result<LogEntry>
+    where S: ObjectStore + ?Sized
+{
+    let Commit { parents, timestamp, author, message, .. } = read_object(store, hash).await?;
+    Ok(LogEntry { hash, parents, timestamp, author, message })
+}
+
+/// A commit that has been reached by [`history`], but not returned yet. The commits are returned
+/// by their timestamps, newest first, and commits with the same timestamp in the order in which
+/// they have been reached.
+///
+/// [`history`]: ./fn.history.html
+struct Pending {
+    entry: LogEntry,
+    order: Reverse<u64>,
+}
+
+impl Pending {
+    fn key(&self) -> (i64, Reverse<u64>) {
+        (self.entry.timestamp, self.order)
+    }
+}
+
+impl PartialEq for Pending {
+    fn eq(&self, other: &Pending) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for Pending {}
+
+impl PartialOrd for Pending {
+    fn partial_cmp(&self, other: &Pending) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Pending {
+    fn cmp(&self, other: &Pending) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+/// The commits that [`history`] has reached so far, and those of them that it has not returned
+/// yet.
+///
+/// [`history`]: ./fn.history.html
+#[derive(Default)]
+struct Walk {
+    reached: HashSet<Hash>,
+    pending: BinaryHeap<Pending>,
+}
+
+impl Walk {
+
+    /// Reads the commit with the [`Hash`] `hash`, unless it has been reached before.
+    ///
+    /// [`Hash`]: ../struct.Hash.html
+    async fn reach<S>(&mut self, store: &S, hash: Hash) -> Result<()>
+        where S: ObjectStore + ?Sized
+    {
+        if self.reached.insert(hash) {
+            let entry = read_log_entry(store, hash).await?;
+            let order = Reverse(self.reached.len() as u64);
+            self.pending.push(Pending { entry, order });
+        }
+        Ok(())
+    }
+}
+
+/// Returns the history of the commit with the [`Hash`] `commit_hash`, e.g. as returned by
+/// [`resolve_commit`] for a branch: the commit, and all commits that it is based on, including
+/// the commits of the branches that have been merged, each once. The commits are returned newest
+/// first, by their timestamps, and read only when the returned stream is polled, so that the
+/// latest versions of a long history are returned without reading all of it.
+///
+/// [`Hash`]: ../struct.Hash.html
+/// [`resolve_commit`]: ../file_storage/fn.resolve_commit.html
+pub fn history<S>(store: &S, commit_hash: Hash) -> impl Stream<Item=Result<LogEntry>> + '_
+    where S: ObjectStore + ?Sized
+{
+    stream::try_unfold((Walk::default(), Some(commit_hash)), move |(mut walk, start)| async move {
+        if let Some(hash) = start {
+            walk.reach(store, hash).await?;
+        }
+        match walk.pending.pop() {
+            Some(Pending { entry, .. }) => {
+                for &parent in entry.parents.iter() {
+                    walk.reach(store, parent).await?;
+                }
+                Ok(Some((entry, (walk, None))))
+            }
+            None => Ok(None),
+        }
+    })
+}
+
+/// Returns the [`Hash`] of the commit that `revision`, a branch or an abbreviated commit `Hash`
+/// as accepted by [`resolve_commit`], has referred to at the time `timestamp`, in seconds since
+/// the UNIX epoch, UTC: the latest of its commits, and of the commits before it, that is not newer
+/// than `timestamp`. Of the parents of a merge commit, only the first, the commit of the branch
+/// that has been merged into, is followed, because the commits of the merged branch have not been
+/// versions of `revision`. Fails with [`VersionNotFound`], if all versions are newer.
+///
+/// [`Hash`]: ../struct.Hash.html
+/// [`resolve_commit`]: ../file_storage/fn.resolve_commit.html
+/// [`VersionNotFound`]: ../error/enum.Error.html#variant.VersionNotFound
+pub async fn commit_as_of<S>(store: &S, revision: &str, timestamp: i64) -> Result<Hash>
+    where S: ObjectStore + ?Sized
+{
+    let mut next = Some(resolve_commit(store, revision).await?);
+    while let Some(hash) = next {
+        let entry = read_log_entry(store, hash).await?;
+        if entry.timestamp <= timestamp {
+            return Ok(hash);
+        }
+        next = entry.parents.first().cloned();
+    }
+    Err(VersionNotFound { revision: revision.to_string(), timestamp })
+}
+
+/// Loads the version `version` of a graph, a branch or an abbreviated commit [`Hash`] as accepted
+/// by [`resolve_commit`].
+///
+/// [`Hash`]: ../struct.Hash.html
+/// [`resolve_commit`]: ../file_storage/fn.resolve_commit.html
+pub async fn load_graph_at<S>(store: &S, version: &str) -> Result<AttributedGraph>
+    where S: ObjectStore + ?Sized
+{
+    let commit_hash = resolve_commit(store, version).await?;
+    read_commit_graph(store, commit_hash).await
+}
+
+/// Loads the version of the graph `revision` that has been the latest at the time `timestamp`, as
+/// found by [`commit_as_of`].
+///
+/// [`commit_as_of`]: ./fn.commit_as_of.html
+pub async fn load_graph_as_of<S>(store: &S, revision: &str, timestamp: i64) -> Result<AttributedGraph>
+    where S: ObjectStore + ?Sized
+{
+    let commit_hash = commit_as_of(store, revision, timestamp).await?;
+    read_commit_graph(store, commit_hash).await
+}
+
+#[cfg(test)]
+mod test {
+    use tokio::runtime::Runtime;
+    use futures::TryStreamExt;
+    use histo_graph_core::graph::graph::VertexId;
+
+    use super::*;
+    use crate::{
+        file_storage::{CommitInfo, save_graph_as_with_info},
+        merge::merge_branch_with_info,
+        store::MemoryStore,
+    };
+
+    /// Saves the versions 100 to 500 of the branch `main`, of which 300 has been saved on the
+    /// branch `feature`, and 500 merges it. Returns their commit hashes.
+    async fn save_history(store: &MemoryStore) -> Result<Vec<Hash>> {
+        let at = |timestamp| CommitInfo { timestamp: Some(timestamp), ..CommitInfo::default() };
+        let mut graph = AttributedGraph::new();
+        let mut hashes = vec![save_graph_as_with_info(store, "main".to_string(), &graph, &at(100)).await?];
+
+        graph.add_vertex(VertexId(1));
+        let info = CommitInfo { timestamp: Some(200), ..CommitInfo::new("Jane", "add vertex 1") };
+        hashes.push(save_graph_as_with_info(store, "main".to_string(), &graph, &info).await?);
+
+        let mut feature = graph.clone();
+        feature.add_vertex(VertexId(2));
+        store.write_ref("feature", hashes[1]).await?;
+        hashes.push(save_graph_as_with_info(store, "feature".to_string(), &feature, &at(300)).await?);
+
+        graph.add_vertex(VertexId(3));
+        hashes.push(save_graph_as_with_info(store, "main".to_string(), &graph, &at(400)).await?);
+
+        let info = CommitInfo { timestamp: Some(500), ..CommitInfo::new("Joe", "merge feature") };
+        merge_branch_with_info(store, "main", "feature", &info).await?;
+        hashes.push(resolve_commit(store, "main").await?);
+        Ok(hashes)
+    }
+
+    #[test]
+    fn test_history_is_newest_first() -> Result<()> {
+        let mut rt = Runtime::new()?;
+        rt.block_on(async {
+            let store = MemoryStore::new();
+            let hashes = save_history(&store).await?;
+
+            let log: Vec<LogEntry> = history(&store, hashes[4]).try_collect().await?;
+            let timestamps: Vec<i64> = log.iter().map(|entry| entry.timestamp).collect();
+            assert_eq!(timestamps, vec![500, 400, 300, 200, 100]);
+            assert_eq!(log[0].parents, vec![hashes[3], hashes[2]]);
+            assert_eq!((log[0].author.as_str(), log[0].message.as_str()), ("Joe", "merge feature"));
+            assert_eq!(log[3], LogEntry {
+                hash: hashes[1],
+                parents: vec![hashes[0]],
+                timestamp: 200,
+                author: "Jane".to_string(),
+                message: "add vertex 1".to_string(),
+            });
+            assert!(log[4].author.is_empty() && log[4].message.is_empty());
+
+            let feature: Vec<LogEntry> = history(&store, hashes[2]).try_collect().await?;
+            assert_eq!(feature.len(), 3);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_load_graph_as_of() -> Result<()> {
+        let mut rt = Runtime::new()?;
+        rt.block_on(async {
+            let store = MemoryStore::new();
+            let hashes = save_history(&store).await?;
+
+            assert_eq!(commit_as_of(&store, "main", 1000).await?, hashes[4]);
+            assert_eq!(commit_as_of(&store, "main", 400).await?, hashes[3]);
+            // the commit of the branch `feature` has not been a version of `main`
+            assert_eq!(commit_as_of(&store, "main", 399).await?, hashes[1]);
+            match commit_as_of(&store, "main", 99).await {
+                Err(VersionNotFound { revision, timestamp }) => assert_eq!((revision.as_str(), timestamp), ("main", 99)),
+                result => panic!("unexpected result {:?}", result),
+            }
+
+            let vertex_count = |graph: AttributedGraph| graph.vertices().count();
+            assert_eq!(vertex_count(load_graph_as_of(&store, "main", 250).await?), 1);
+            assert_eq!(vertex_count(load_graph_as_of(&store, "main", 500).await?), 3);
+            assert_eq!(vertex_count(load_graph_at(&store, "feature").await?), 2);
+            assert_eq!(vertex_count(load_graph_at(&store, &hashes[0].to_string()[..8]).await?), 0);
+            Ok(())
+        })
+    }
+}
@@ -1,8 +1,17 @@
 pub mod error;
 pub mod file_storage;
+pub mod branch;
+pub mod merge;
+pub mod gc;
+pub mod fsck;
+pub mod diff;
+pub mod history;
+pub mod store;
 
 mod hash;
-pub use hash::Hash;
+pub use hash::{Hash, MIN_PREFIX_LENGTH};
 
 mod object;
 mod file;
+mod tree;
+mod lock;
@@ -0,0 +1,58 @@
+//! Implements a lock that serializes the modifications of a [`DirectoryStore`] by concurrent
+//! processes, e.g. `refajo` and the http server.
+//!
+//! The lock is an exclusive advisory lock of a file. It is held as long as the [`FileLock`]
+//! exists, and released by the operating system, if the process terminates.
+//!
+//! [`DirectoryStore`]: ../store/directory/struct.DirectoryStore.html
+//! [`FileLock`]: ./struct.FileLock.html
+
+use std::{
+    fs::{File, OpenOptions},
+    path::Path,
+    time::Duration,
+};
+use fs2::FileExt;
+use tokio::{fs, time::delay_for};
+
+use crate::error::Result;
+
+/// The time to wait before trying again to acquire a lock that is held by someone else.
+const RETRY_DELAY: Duration = Duration::from_millis(10);
+
+/// An acquired lock of a file. The lock is released when this is dropped.
+pub(crate) struct FileLock {
+    file: File,
+}
+
+impl FileLock {
+
+    /// Acquires the lock of the file at `path`, waiting until it is released, if it is held by
+    /// someone else. Creates the file and its directory, if necessary.
+    pub(crate) async fn acquire<P>(path: P) -> Result<FileLock>
+        where P: AsRef<Path>
+    {
+        if let Some(dir) = path.as_ref().parent() {
+            fs::create_dir_all(dir).await?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)?;
+
+        loop {
+            match file.try_lock_exclusive() {
+                Ok(()) => return Ok(FileLock { file }),
+                Err(e) if e.raw_os_error() == fs2::lock_contended_error().raw_os_error() => delay_for(RETRY_DELAY).await,
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
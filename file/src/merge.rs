@@ -0,0 +1,208 @@
+//! Implements the merging of branches.
+//!
+//! The graph of one branch is merged into the graph of another branch with a three-way merge,
+//! using the graph of the latest common ancestor commit of both branches as the base.
+
+use std::collections::{HashSet, VecDeque};
+use histo_graph_core::graph::{
+    attributed_graph::AttributedGraph,
+    merge::{merge, MergeConflict},
+};
+
+use crate::{
+    Hash,
+    error::Result,
+    file_storage::{CommitInfo, read_commit_graph, read_object, read_ref, write_commit},
+    object::Commit,
+    store::ObjectStore,
+};
+
+/// The outcome of merging a branch into another branch.
+#[derive(Debug)]
+pub enum MergeOutcome {
+
+    /// The branch that was merged is already contained in the history of the other branch.
+    /// Nothing has been changed.
+    UpToDate,
+
+    /// The branch that was merged into had no commits of its own, since the branches diverged.
+    /// It has been moved forward to the commit with the contained [`Hash`].
+    ///
+    /// [`Hash`]: ../struct.Hash.html
+    FastForward(Hash),
+
+    /// The graphs of both branches have been merged without conflicts, and a merge commit with the
+    /// contained [`Hash`] has been written.
+    ///
+    /// [`Hash`]: ../struct.Hash.html
+    Merged(Hash),
+
+    /// The graphs of both branches could not be merged. Nothing has been changed.
+    Conflicts(Vec<MergeConflict>),
+}
+
+/// Returns the hashes of the commit `commit_hash` and of all its ancestors.
+async fn ancestors<S>(store: &S, commit_hash: Hash) -> Result<HashSet<Hash>>
+    where S: ObjectStore + ?Sized
+{
+    let mut visited: HashSet<Hash> = HashSet::new();
+    let mut queue: VecDeque<Hash> = VecDeque::new();
+    queue.push_back(commit_hash);
+
+    while let Some(hash) = queue.pop_front() {
+        if visited.insert(hash) {
+            let commit: Commit = read_object(store, hash).await?;
+            queue.extend(commit.parents);
+        }
+    }
+
+    Ok(visited)
+}
+
+/// Finds the merge base of the commits `a` and `b`: the common ancestor of both commits that is
+/// closest to `b`. A commit counts as an ancestor of itself.
+/// Returns `None`, if the histories of the commits are unrelated.
+pub(crate) async fn find_merge_base<S>(store: &S, a: Hash, b: Hash) -> Result<Option<Hash>>
+    where S: ObjectStore + ?Sized
+{
+    let ancestors_of_a = ancestors(store, a).await?;
+
+    let mut visited: HashSet<Hash> = HashSet::new();
+    let mut queue: VecDeque<Hash> = VecDeque::new();
+    queue.push_back(b);
+
+    while let Some(hash) = queue.pop_front() {
+        if ancestors_of_a.contains(&hash) {
+            return Ok(Some(hash));
+        }
+        if visited.insert(hash) {
+            let commit: Commit = read_object(store, hash).await?;
+            queue.extend(commit.parents);
+        }
+    }
+
+    Ok(None)
+}
+
+/// Merges the branch `from` into the branch `into`, like [`merge_branch_with_info`], with a merge
+/// commit without an author, whose message names the branches.
+///
+/// [`merge_branch_with_info`]: ./fn.merge_branch_with_info.html
+pub async fn merge_branch<S>(store: &S, into: &str, from: &str) -> Result<MergeOutcome>
+    where S: ObjectStore + ?Sized
+{
+    let info = CommitInfo::new("", format!("Merge branch '{}' into '{}'", from, into));
+    merge_branch_with_info(store, into, from, &info).await
+}
+
+/// Merges the branch `from` into the branch `into`.
+///
+/// If the merge succeeds, `into` is updated, either by moving it forward to the commit of `from`,
+/// or by writing a merge commit, whose parents are the commits of `into` and `from`. If the merge
+/// results in conflicts, no commit is written, and the conflicts are returned. A merge commit is
+/// written by the author and with the message of `info`.
+pub async fn merge_branch_with_info<S>(store: &S, into: &str, from: &str, info: &CommitInfo) -> Result<MergeOutcome>
+    where S: ObjectStore + ?Sized
+{
+    let _guard = store.lock().await?;
+
+    let ours = read_ref(store, into).await?;
+    let theirs = read_ref(store, from).await?;
+
+    let merge_base = find_merge_base(store, ours, theirs).await?;
+
+    if merge_base == Some(theirs) {
+        return Ok(MergeOutcome::UpToDate);
+    }
+    if merge_base == Some(ours) {
+        store.compare_and_swap_ref(into, Some(ours), theirs).await?;
+        return Ok(MergeOutcome::FastForward(theirs));
+    }
+
+    let base_graph = match merge_base {
+        Some(hash) => read_commit_graph(store, hash).await?,
+        None => AttributedGraph::new(),
+    };
+    let our_graph = read_commit_graph(store, ours).await?;
+    let their_graph = read_commit_graph(store, theirs).await?;
+
+    let result = merge(&base_graph, &our_graph, &their_graph);
+    if !result.is_clean() {
+        return Ok(MergeOutcome::Conflicts(result.conflicts));
+    }
+
+    let commit_hash = write_commit(store, &result.graph, vec![ours, theirs], info).await?;
+    store.compare_and_swap_ref(into, Some(ours), commit_hash).await?;
+    Ok(MergeOutcome::Merged(commit_hash))
+}
+
+#[cfg(test)]
+mod test {
+    use tokio::runtime::Runtime;
+    use histo_graph_core::graph::graph::{VertexId, Edge};
+
+    use super::*;
+    use crate::{
+        file_storage::{save_graph_as, load_graph},
+        store::MemoryStore,
+    };
+
+    #[test]
+    fn test_merge_branches() -> Result<()> {
+        let mut rt = Runtime::new()?;
+        rt.block_on(async {
+            let store = MemoryStore::new();
+            let (main, feature) = ("merge_main", "merge_feature");
+
+            let mut base = AttributedGraph::new();
+            base.add_edge(Edge(VertexId(1), VertexId(2)));
+            let base_hash = save_graph_as(&store, main.to_string(), &base).await?;
+            store.write_ref(feature, base_hash).await?;
+
+            let mut theirs = base.clone();
+            theirs.add_edge(Edge(VertexId(2), VertexId(3)));
+            let their_hash = save_graph_as(&store, feature.to_string(), &theirs).await?;
+
+            // main has no commits of its own, so it is moved forward
+            match merge_branch(&store, main, feature).await? {
+                MergeOutcome::FastForward(hash) => assert_eq!(hash, their_hash),
+                outcome => panic!("unexpected outcome {:?}", outcome),
+            }
+            assert!(matches!(merge_branch(&store, main, feature).await?, MergeOutcome::UpToDate));
+
+            let mut ours = theirs.clone();
+            ours.set_vertex_attribute(VertexId(1), "name".to_string(), "one".into());
+            let our_hash = save_graph_as(&store, main.to_string(), &ours).await?;
+
+            theirs.remove_edge(Edge(VertexId(1), VertexId(2)));
+            save_graph_as(&store, feature.to_string(), &theirs).await?;
+
+            let merged_hash = match merge_branch(&store, main, feature).await? {
+                MergeOutcome::Merged(hash) => hash,
+                outcome => panic!("unexpected outcome {:?}", outcome),
+            };
+            let commit: Commit = read_object(&store, merged_hash).await?;
+            assert_eq!(commit.parents[0], our_hash);
+
+            let mut expected = theirs.clone();
+            expected.set_vertex_attribute(VertexId(1), "name".to_string(), "one".into());
+            assert_eq!(load_graph(&store, main.to_string()).await?, expected);
+
+            // conflicting changes leave the branch untouched
+            store.write_ref("merge_conflict", merged_hash).await?;
+            let mut ours = expected.clone();
+            ours.set_vertex_attribute(VertexId(1), "name".to_string(), "uno".into());
+            save_graph_as(&store, main.to_string(), &ours).await?;
+            let mut theirs = expected.clone();
+            theirs.set_vertex_attribute(VertexId(1), "name".to_string(), "eins".into());
+            save_graph_as(&store, "merge_conflict".to_string(), &theirs).await?;
+
+            match merge_branch(&store, main, "merge_conflict").await? {
+                MergeOutcome::Conflicts(conflicts) => assert_eq!(conflicts.len(), 1),
+                outcome => panic!("unexpected outcome {:?}", outcome),
+            }
+            assert_eq!(load_graph(&store, main.to_string()).await?, ours);
+            Ok(())
+        })
+    }
+}
@@ -3,32 +3,109 @@
 //!
 //! [`ObjectType`]: ./trait.ObjectType.html
 
-use histo_graph_core::graph::graph::VertexId;
+use histo_graph_core::graph::{
+    graph::VertexId,
+    attributes::{Attributes, AttributeValue},
+};
 use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
 
 use crate::Hash;
 
-/// Respresents an edge by the [`Hash`]es of the vertices it is connected to.
+/// The value of an attribute, as it gets serialized and stored.
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) enum StoredValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+}
+
+impl From<&AttributeValue> for StoredValue {
+    fn from(value: &AttributeValue) -> StoredValue {
+        match value {
+            AttributeValue::Bool(b) => StoredValue::Bool(*b),
+            AttributeValue::Int(i) => StoredValue::Int(*i),
+            AttributeValue::Float(f) => StoredValue::Float(*f),
+            AttributeValue::Str(s) => StoredValue::Str(s.clone()),
+        }
+    }
+}
+
+impl From<StoredValue> for AttributeValue {
+    fn from(value: StoredValue) -> AttributeValue {
+        match value {
+            StoredValue::Bool(b) => AttributeValue::Bool(b),
+            StoredValue::Int(i) => AttributeValue::Int(i),
+            StoredValue::Float(f) => AttributeValue::Float(f),
+            StoredValue::Str(s) => AttributeValue::Str(s),
+        }
+    }
+}
+
+/// The attributes of a vertex or an edge, as they get serialized and stored. The keys are sorted,
+/// so that equal attributes always result in the same serialization.
+pub(crate) type StoredAttributes = BTreeMap<String, StoredValue>;
+
+pub(crate) fn to_stored_attributes(attributes: &Attributes) -> StoredAttributes {
+    attributes
+        .iter()
+        .map(|(key, value)| (key.clone(), value.into()))
+        .collect()
+}
+
+pub(crate) fn from_stored_attributes(attributes: StoredAttributes) -> Attributes {
+    attributes
+        .into_iter()
+        .map(|(key, value)| (key, value.into()))
+        .collect()
+}
+
+/// Represents a vertex with its attributes.
+/// This is the type that gets serialized and stored, when storing a vertex.
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct StoredVertex {
+    pub(crate) id: u64,
+    pub(crate) attributes: StoredAttributes,
+}
+
+impl StoredVertex {
+
+    /// Constructs a `StoredVertex` from a `VertexId` and its attributes.
+    pub(crate) fn new(vertex_id: VertexId, attributes: &Attributes) -> StoredVertex {
+        StoredVertex {
+            id: vertex_id.0,
+            attributes: to_stored_attributes(attributes),
+        }
+    }
+}
+
+/// Respresents an edge by the [`Hash`]es of the vertices it is connected to, and its attributes.
 /// This is the type that gets serialized and stored, when storing an edge.
 ///
 /// [`Hash`]: ../struct.Hash.html
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub(crate) struct HashEdge {
     pub(crate) from: Hash,
     pub(crate) to: Hash,
+    pub(crate) attributes: StoredAttributes,
 }
 
-/// A vector of [`Hash`]es. These are the `Hash`es of objects that are stored, like `VertexId`s
-/// or `HashEdge`s.
+/// A vector of [`Hash`]es. These are the `Hash`es of objects that are stored, like
+/// `StoredVertex`s or `HashEdge`s. Graphs of the formats before 3 refer to a `HashVec` of their
+/// vertices and of their edges, which are replaced by trees of [`HashChunk`]s by the migration.
 ///
 /// [`Hash`]: ../struct.Hash.html
-#[derive(Serialize, Deserialize)]
+/// [`HashChunk`]: ./struct.HashChunk.html
+#[derive(Clone, Serialize, Deserialize)]
 pub(crate) struct HashVec<OT>(pub(crate) Vec<Hash>, std::marker::PhantomData<OT>);
 
 impl<OT> HashVec<OT>
 where OT: ObjectType {
 
-    /// Constructs a `HashVec` where the [`Hash`]es are the ones of objects of type `OT`.
+    /// Constructs a `HashVec` where the [`Hash`]es are the ones of objects of type `OT`. This is
+    /// used by the migration of graphs of the format 0, that are converted to graphs of the
+    /// format 1.
     ///
     /// [`Hash`]: ../struct.Hash.html
     pub(crate) fn new(hashes: Vec<Hash>) -> HashVec<OT> {
@@ -36,37 +113,95 @@ where OT: ObjectType {
     }
 }
 
+/// A chunk of a tree of [`Hash`]es, that holds the `Hash`es of the objects of type `OT`, e.g. of
+/// the vertices of a graph.
+///
+/// The `Hash`es are sorted, and split into chunks after every `Hash` that fulfils a condition that
+/// only depends on the `Hash` itself. A chunk therefore ends at the same `Hash`, whatever the
+/// `Hash`es before it are, and adding or removing a `Hash` changes only the chunk that holds it.
+/// The `Hash`es of the chunks are split into chunks of the next level in the same way, up to the
+/// single chunk at the root of the tree.
+///
+/// [`Hash`]: ../struct.Hash.html
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct HashChunk<OT> {
+
+    /// The level of the chunk. A chunk of level 0 holds the `Hash`es of objects of type `OT`, a
+    /// chunk of a higher level holds the `Hash`es of the chunks of the level below.
+    pub(crate) level: u32,
+
+    /// The sorted `Hash`es.
+    pub(crate) hashes: Vec<Hash>,
+
+    _pot: std::marker::PhantomData<OT>,
+}
+
+impl<OT> HashChunk<OT>
+where OT: ObjectType {
+
+    /// Constructs a `HashChunk` of the given `level`.
+    pub(crate) fn new(level: u32, hashes: Vec<Hash>) -> HashChunk<OT> {
+        HashChunk { level, hashes, _pot: std::marker::PhantomData }
+    }
+}
+
 /// The top-object of a stored graph.
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub(crate) struct GraphHash {
 
-    /// The [`Hash`] of the [`HashVec`] of the vertices.
+    /// The [`Hash`] of the root [`HashChunk`] of the tree of the vertices.
     ///
     /// [`Hash`]: ../struct.Hash.html
-    /// [`HashVec`]: ./struct.HashVec.html
-    pub(crate) vertex_vec_hash: Hash,
+    /// [`HashChunk`]: ./struct.HashChunk.html
+    pub(crate) vertex_tree_hash: Hash,
 
-    /// The [`Hash`] of the [`HashVec`] of the edges.
+    /// The [`Hash`] of the root [`HashChunk`] of the tree of the edges.
     ///
     /// [`Hash`]: ../struct.Hash.html
-    /// [`HashVec`]: ./struct.HashVec.html
-    pub(crate) edge_vec_hash: Hash,
+    /// [`HashChunk`]: ./struct.HashChunk.html
+    pub(crate) edge_tree_hash: Hash,
 }
 
-/// Marks types as objects that can be stored.
-pub(crate) trait ObjectType {
+/// A version of a stored graph. Commits form the history of a graph: each commit refers to the
+/// commits it is based on.
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct Commit {
+
+    /// The [`Hash`]es of the parent commits. The first commit of a history has no parents.
+    ///
+    /// [`Hash`]: ../struct.Hash.html
+    pub(crate) parents: Vec<Hash>,
+
+    /// The [`Hash`] of the [`GraphHash`] of this version of the graph.
+    ///
+    /// [`Hash`]: ../struct.Hash.html
+    /// [`GraphHash`]: ./struct.GraphHash.html
+    pub(crate) graph_hash: Hash,
+
+    /// The time of the commit, in seconds since the UNIX epoch.
+    pub(crate) timestamp: i64,
+
+    /// The author of the commit.
+    pub(crate) author: String,
+
+    /// A description of the commit.
+    pub(crate) message: String,
+}
+
+/// Marks types as objects that can be stored. Objects are serialized into the content of a
+/// [`File`], and they are `Clone`, `Send` and `Sync`, so that the decoded objects can be shared by
+/// an [`ObjectCache`].
+///
+/// [`File`]: ../file/struct.File.html
+/// [`ObjectCache`]: ../store/cached/struct.ObjectCache.html
+pub(crate) trait ObjectType: Serialize + Clone + Send + Sync + 'static {
 
     /// The name that identifies the type of the object on the storage.
     fn storage_name() -> &'static str;
 }
 
-/// Marks types as objects that can be stored under a name (rather than storing them by their
-/// [`Hash`]).
-///
-/// [`Hash`]: ../struct.Hash.html
-pub(crate) trait NamedObjectType {}
 
-impl ObjectType for VertexId {
+impl ObjectType for StoredVertex {
     fn storage_name() -> &'static str {
         "vertex"
     }
@@ -78,7 +213,7 @@ impl ObjectType for HashEdge {
     }
 }
 
-impl ObjectType for HashVec<VertexId> {
+impl ObjectType for HashVec<StoredVertex> {
     fn storage_name() -> &'static str {
         "vertexvec"
     }
@@ -88,8 +223,18 @@ impl ObjectType for HashVec<HashEdge> {
     fn storage_name() -> &'static str { "edgevec" }
 }
 
+impl ObjectType for HashChunk<StoredVertex> {
+    fn storage_name() -> &'static str { "vertexchunk" }
+}
+
+impl ObjectType for HashChunk<HashEdge> {
+    fn storage_name() -> &'static str { "edgechunk" }
+}
+
 impl ObjectType for GraphHash {
     fn storage_name() -> &'static str { "graph" }
 }
 
-impl NamedObjectType for GraphHash {}
\ No newline at end of file
+impl ObjectType for Commit {
+    fn storage_name() -> &'static str { "commit" }
+}
\ No newline at end of file
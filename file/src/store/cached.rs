@@ -0,0 +1,346 @@
+//! Implements an [`ObjectStore`] that keeps the objects, that have been read from another
+//! `ObjectStore`, decoded in memory, so that they are not read and decoded again.
+//!
+//! [`ObjectStore`]: ../trait.ObjectStore.html
+
+use std::{
+    any::Any,
+    collections::{BTreeMap, HashMap},
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+
+use crate::{
+    Hash,
+    error::Result,
+    object::ObjectType,
+    store::{ObjectStore, ObjectId, StoreGuard, HashAlgorithm},
+};
+
+/// The number of objects that a [`CachedStore`] keeps by default.
+///
+/// [`CachedStore`]: ./struct.CachedStore.html
+pub const DEFAULT_CACHE_CAPACITY: usize = 65_536;
+
+/// The number of reads of an [`ObjectCache`] that found the object, and that did not.
+///
+/// [`ObjectCache`]: ./struct.ObjectCache.html
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl CacheStats {
+
+    /// The share of the reads that found the object, between 0 and 1.
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            reads => self.hits as f64 / reads as f64,
+        }
+    }
+}
+
+impl fmt::Display for CacheStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} hits, {} misses ({:.1}% hits)", self.hits, self.misses, 100.0 * self.hit_rate())
+    }
+}
+
+type CacheKey = (&'static str, Hash);
+
+struct Entries {
+    objects: HashMap<CacheKey, (Arc<dyn Any + Send + Sync>, u64)>,
+
+    /// The keys of the `objects`, by the time they have been used last.
+    used: BTreeMap<u64, CacheKey>,
+
+    time: u64,
+    stats: CacheStats,
+}
+
+impl Entries {
+    fn touch(&mut self, key: CacheKey) -> Option<Arc<dyn Any + Send + Sync>> {
+        self.time += 1;
+        let time = self.time;
+        let (object, used) = self.objects.get_mut(&key)?;
+        self.used.remove(used);
+        self.used.insert(time, key);
+        *used = time;
+        Some(object.clone())
+    }
+}
+
+/// Holds at most `capacity` decoded objects, and drops the object that has been used least
+/// recently, when another object is added.
+pub struct ObjectCache {
+    capacity: usize,
+    entries: Mutex<Entries>,
+}
+
+impl ObjectCache {
+
+    /// Creates a cache that holds at most `capacity` objects. A cache with a `capacity` of 0 holds
+    /// no objects, but counts the reads.
+    pub fn new(capacity: usize) -> ObjectCache {
+        ObjectCache {
+            capacity,
+            entries: Mutex::new(Entries {
+                objects: HashMap::new(),
+                used: BTreeMap::new(),
+                time: 0,
+                stats: CacheStats::default(),
+            }),
+        }
+    }
+
+    fn entries(&self) -> std::sync::MutexGuard<'_, Entries> {
+        // the entries are consistent after every statement, so a panic does not corrupt them
+        self.entries.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Returns the object of type `OT` with the [`Hash`] `hash`, if the cache holds it.
+    ///
+    /// [`Hash`]: ../../struct.Hash.html
+    pub(crate) fn get<OT>(&self, hash: Hash) -> Option<OT>
+        where OT: ObjectType
+    {
+        let mut entries = self.entries();
+        let object = entries.touch((OT::storage_name(), hash));
+        match object.as_ref().and_then(|object| object.downcast_ref::<OT>()) {
+            Some(object) => {
+                entries.stats.hits += 1;
+                Some(object.clone())
+            }
+            None => {
+                entries.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Adds the `object` with the [`Hash`] `hash`.
+    ///
+    /// [`Hash`]: ../../struct.Hash.html
+    pub(crate) fn insert<OT>(&self, hash: Hash, object: OT)
+        where OT: ObjectType
+    {
+        if self.capacity == 0 {
+            return;
+        }
+        let key = (OT::storage_name(), hash);
+        let mut entries = self.entries();
+        if entries.touch(key).is_some() {
+            return;
+        }
+        while entries.objects.len() >= self.capacity {
+            if let Some((_, oldest)) = entries.used.pop_first() {
+                entries.objects.remove(&oldest);
+            }
+        }
+        let time = entries.time;
+        entries.used.insert(time, key);
+        entries.objects.insert(key, (Arc::new(object), time));
+    }
+
+    /// The number of objects that the cache holds.
+    pub fn len(&self) -> usize {
+        self.entries().objects.len()
+    }
+
+    /// Returns true if the cache holds no objects.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of reads so far, that found the object, and that did not.
+    pub fn stats(&self) -> CacheStats {
+        self.entries().stats
+    }
+}
+
+/// Keeps the last [`capacity`] objects, that have been read from the inner store by the functions
+/// of this crate, decoded in an [`ObjectCache`], e.g. the vertices that a graph refers to by all
+/// its edges. All reads through the same `CachedStore` share the cache, so a server that keeps its
+/// store open keeps the objects that its requests read most often.
+///
+/// Objects are never changed, so the cache does not have to be invalidated when objects are
+/// written. An object that has been removed can still be read from the cache, though.
+///
+/// [`capacity`]: ./constant.DEFAULT_CACHE_CAPACITY.html
+/// [`ObjectCache`]: ./struct.ObjectCache.html
+pub struct CachedStore<S> {
+    inner: S,
+    cache: ObjectCache,
+}
+
+impl<S> CachedStore<S>
+    where S: ObjectStore
+{
+
+    /// Wraps the store `inner`, and keeps at most `capacity` decoded objects.
+    pub fn new(inner: S, capacity: usize) -> CachedStore<S> {
+        CachedStore {
+            inner,
+            cache: ObjectCache::new(capacity),
+        }
+    }
+
+    /// The number of reads so far, that have found the object in the cache, and that have not.
+    pub fn stats(&self) -> CacheStats {
+        self.cache.stats()
+    }
+}
+
+#[async_trait]
+impl<S> ObjectStore for CachedStore<S>
+    where S: ObjectStore
+{
+    async fn put_object(&self, object_type: &str, hash: Hash, content: Vec<u8>) -> Result<()> {
+        self.inner.put_object(object_type, hash, content).await
+    }
+
+    async fn get_object(&self, object_type: &str, hash: Hash) -> Result<Vec<u8>> {
+        self.inner.get_object(object_type, hash).await
+    }
+
+    async fn contains_object(&self, object_type: &str, hash: Hash) -> Result<bool> {
+        self.inner.contains_object(object_type, hash).await
+    }
+
+    async fn object_size(&self, object_type: &str, hash: Hash) -> Result<u64> {
+        self.inner.object_size(object_type, hash).await
+    }
+
+    async fn list_objects(&self, object_type: &str) -> Result<Vec<Hash>> {
+        self.inner.list_objects(object_type).await
+    }
+
+    async fn remove_object(&self, object_type: &str, hash: Hash) -> Result<()> {
+        self.inner.remove_object(object_type, hash).await
+    }
+
+    async fn remove_objects(&self, objects: &[ObjectId]) -> Result<()> {
+        self.inner.remove_objects(objects).await
+    }
+
+    async fn read_ref(&self, name: &str) -> Result<Option<Hash>> {
+        self.inner.read_ref(name).await
+    }
+
+    async fn write_ref(&self, name: &str, hash: Hash) -> Result<()> {
+        self.inner.write_ref(name, hash).await
+    }
+
+    async fn compare_and_swap_ref(&self, name: &str, expected: Option<Hash>, hash: Hash) -> Result<()> {
+        self.inner.compare_and_swap_ref(name, expected, hash).await
+    }
+
+    async fn remove_ref(&self, name: &str) -> Result<()> {
+        self.inner.remove_ref(name).await
+    }
+
+    async fn list_refs(&self) -> Result<Vec<String>> {
+        self.inner.list_refs().await
+    }
+
+    async fn read_head(&self) -> Result<Option<String>> {
+        self.inner.read_head().await
+    }
+
+    async fn write_head(&self, name: &str) -> Result<()> {
+        self.inner.write_head(name).await
+    }
+
+    async fn lock(&self) -> Result<StoreGuard> {
+        self.inner.lock().await
+    }
+
+    async fn repack(&self) -> Result<usize> {
+        self.inner.repack().await
+    }
+
+    async fn remove_leftovers(&self, dry_run: bool) -> Result<u64> {
+        self.inner.remove_leftovers(dry_run).await
+    }
+
+    fn max_concurrency(&self) -> usize {
+        self.inner.max_concurrency()
+    }
+
+    fn hash_algorithm(&self) -> HashAlgorithm {
+        self.inner.hash_algorithm()
+    }
+
+    fn object_cache(&self) -> Option<&ObjectCache> {
+        Some(&self.cache)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tokio::runtime::Runtime;
+    use histo_graph_core::graph::{
+        graph::{VertexId, Edge},
+        attributes::Attributes,
+        attributed_graph::AttributedGraph,
+    };
+
+    use super::*;
+    use crate::{
+        file_storage::{save_graph_as, load_graph, read_object, write_object},
+        object::StoredVertex,
+        store::MemoryStore,
+    };
+
+    #[test]
+    fn test_least_recently_used_object_is_dropped() -> Result<()> {
+        let cache = ObjectCache::new(2);
+        let vertex = |id| StoredVertex::new(VertexId(id), &Attributes::new());
+        let hashes: Vec<Hash> = (0u32..3).map(|i| Hash::sha256(i.to_be_bytes())).collect();
+
+        cache.insert(hashes[0], vertex(0));
+        cache.insert(hashes[1], vertex(1));
+        assert_eq!(cache.get::<StoredVertex>(hashes[0]).map(|vertex| vertex.id), Some(0));
+        cache.insert(hashes[2], vertex(2));
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get::<StoredVertex>(hashes[1]).is_none());
+        assert_eq!(cache.get::<StoredVertex>(hashes[2]).map(|vertex| vertex.id), Some(2));
+        assert_eq!(cache.stats(), CacheStats { hits: 2, misses: 1 });
+        Ok(())
+    }
+
+    #[test]
+    fn test_vertices_are_read_once() -> Result<()> {
+        let mut rt = Runtime::new()?;
+        rt.block_on(async {
+            // all edges refer to the vertex 0
+            let mut graph = AttributedGraph::new();
+            for id in 1..=1000 {
+                graph.add_edge(Edge(VertexId(0), VertexId(id)));
+            }
+
+            let store = CachedStore::new(MemoryStore::new(), DEFAULT_CACHE_CAPACITY);
+            save_graph_as(&store, "main".to_string(), &graph).await?;
+            assert_eq!(load_graph(&store, "main".to_string()).await?, graph);
+            let stats = store.stats();
+            assert!(stats.hits >= 1000, "{}", stats);
+            assert!(stats.misses < 2100, "{}", stats);
+
+            // a second load reads nothing from the inner store
+            let misses = stats.misses;
+            assert_eq!(load_graph(&store, "main".to_string()).await?, graph);
+            assert_eq!(store.stats().misses, misses);
+
+            // the cache holds the decoded objects by their type
+            let hash = write_object(&store, &StoredVertex::new(VertexId(0), &Attributes::new())).await?;
+            let vertex: StoredVertex = read_object(&store, hash).await?;
+            assert_eq!(vertex.id, 0);
+            assert!(read_object::<_, crate::object::HashEdge>(&store, hash).await.is_err());
+            Ok(())
+        })
+    }
+}
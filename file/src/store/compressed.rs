@@ -0,0 +1,184 @@
+//! Implements an [`ObjectStore`] that compresses the content of the objects of another
+//! `ObjectStore`.
+//!
+//! [`ObjectStore`]: ../trait.ObjectStore.html
+
+use async_trait::async_trait;
+use flate2::{
+    Compression as Level,
+    read::DeflateDecoder,
+    write::DeflateEncoder,
+};
+use std::io::{self, Read, Write};
+
+use crate::{
+    Hash,
+    error::{Result, Error::CorruptObject},
+    store::{ObjectStore, ObjectCache, HashAlgorithm, ObjectId, StoreGuard, config::Compression},
+};
+
+/// Compresses the content of the objects with `compression`.
+fn compress(compression: Compression, content: Vec<u8>) -> io::Result<Vec<u8>> {
+    match compression {
+        Compression::None => Ok(content),
+        Compression::Deflate => {
+            let mut encoder = DeflateEncoder::new(Vec::new(), Level::default());
+            encoder.write_all(&content)?;
+            encoder.finish()
+        }
+    }
+}
+
+/// Decompresses content that has been compressed with `compression`. Fails with an `io::Error` of
+/// kind `InvalidData`, if the content cannot be decompressed, which [`get_object`] reports as a
+/// [`CorruptObject`].
+///
+/// [`get_object`]: ../trait.ObjectStore.html#tymethod.get_object
+/// [`CorruptObject`]: ../../error/enum.Error.html#variant.CorruptObject
+fn decompress(compression: Compression, content: Vec<u8>) -> io::Result<Vec<u8>> {
+    match compression {
+        Compression::None => Ok(content),
+        Compression::Deflate => {
+            let mut decompressed: Vec<u8> = Vec::new();
+            DeflateDecoder::new(&content[..]).read_to_end(&mut decompressed)?;
+            Ok(decompressed)
+        }
+    }
+}
+
+/// Compresses the content of the objects, before they are stored in the inner store, and
+/// decompresses it, when it is read. The named references are stored as they are.
+pub struct CompressedStore<S> {
+    inner: S,
+    compression: Compression,
+}
+
+impl<S> CompressedStore<S>
+    where S: ObjectStore
+{
+
+    /// Wraps the store `inner`, so that the content of its objects is compressed with
+    /// `compression`.
+    pub fn new(inner: S, compression: Compression) -> CompressedStore<S> {
+        CompressedStore {
+            inner,
+            compression,
+        }
+    }
+}
+
+#[async_trait]
+impl<S> ObjectStore for CompressedStore<S>
+    where S: ObjectStore
+{
+    async fn put_object(&self, object_type: &str, hash: Hash, content: Vec<u8>) -> Result<()> {
+        let content = compress(self.compression, content)?;
+        self.inner.put_object(object_type, hash, content).await
+    }
+
+    async fn get_object(&self, object_type: &str, hash: Hash) -> Result<Vec<u8>> {
+        let content = self.inner.get_object(object_type, hash).await?;
+        decompress(self.compression, content).map_err(|_| CorruptObject { object_type: object_type.to_string(), hash })
+    }
+
+    async fn contains_object(&self, object_type: &str, hash: Hash) -> Result<bool> {
+        self.inner.contains_object(object_type, hash).await
+    }
+
+    async fn object_size(&self, object_type: &str, hash: Hash) -> Result<u64> {
+        self.inner.object_size(object_type, hash).await
+    }
+
+    async fn list_objects(&self, object_type: &str) -> Result<Vec<Hash>> {
+        self.inner.list_objects(object_type).await
+    }
+
+    async fn remove_object(&self, object_type: &str, hash: Hash) -> Result<()> {
+        self.inner.remove_object(object_type, hash).await
+    }
+
+    async fn remove_objects(&self, objects: &[ObjectId]) -> Result<()> {
+        self.inner.remove_objects(objects).await
+    }
+
+    async fn read_ref(&self, name: &str) -> Result<Option<Hash>> {
+        self.inner.read_ref(name).await
+    }
+
+    async fn write_ref(&self, name: &str, hash: Hash) -> Result<()> {
+        self.inner.write_ref(name, hash).await
+    }
+
+    async fn compare_and_swap_ref(&self, name: &str, expected: Option<Hash>, hash: Hash) -> Result<()> {
+        self.inner.compare_and_swap_ref(name, expected, hash).await
+    }
+
+    async fn remove_ref(&self, name: &str) -> Result<()> {
+        self.inner.remove_ref(name).await
+    }
+
+    async fn list_refs(&self) -> Result<Vec<String>> {
+        self.inner.list_refs().await
+    }
+
+    async fn read_head(&self) -> Result<Option<String>> {
+        self.inner.read_head().await
+    }
+
+    async fn write_head(&self, name: &str) -> Result<()> {
+        self.inner.write_head(name).await
+    }
+
+    async fn lock(&self) -> Result<StoreGuard> {
+        self.inner.lock().await
+    }
+
+    async fn repack(&self) -> Result<usize> {
+        self.inner.repack().await
+    }
+
+    async fn remove_leftovers(&self, dry_run: bool) -> Result<u64> {
+        self.inner.remove_leftovers(dry_run).await
+    }
+
+    fn max_concurrency(&self) -> usize {
+        self.inner.max_concurrency()
+    }
+
+    fn hash_algorithm(&self) -> HashAlgorithm {
+        self.inner.hash_algorithm()
+    }
+
+    fn object_cache(&self) -> Option<&ObjectCache> {
+        self.inner.object_cache()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tokio::runtime::Runtime;
+
+    use super::*;
+    use crate::store::MemoryStore;
+
+    #[test]
+    fn test_compressed_store() -> Result<()> {
+        let mut rt = Runtime::new()?;
+        rt.block_on(async {
+            let inner = MemoryStore::new();
+            let store = CompressedStore::new(inner.clone(), Compression::Deflate);
+            let content: Vec<u8> = vec![7u8; 1000];
+            let hash: Hash = Hash::sha256(&content);
+
+            store.put_object("vertexvec", hash, content.clone()).await?;
+            assert!(inner.get_object("vertexvec", hash).await?.len() < content.len());
+            assert_eq!(store.get_object("vertexvec", hash).await?, content);
+
+            // content that has not been compressed cannot be read
+            let corrupt: Hash = Hash::sha256(b"corrupt");
+            inner.put_object("vertexvec", corrupt, vec![0xff; 10]).await?;
+            assert!(store.get_object("vertexvec", corrupt).await.is_err());
+            Ok(())
+        })
+    }
+}
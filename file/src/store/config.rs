@@ -0,0 +1,331 @@
+//! Defines the configuration of a store on disk, that is chosen when the store is created, and
+//! recorded in the file `config` in the storage directory, together with the format of the store.
+//! The configuration is checked whenever the store is opened, so that a store is never read or
+//! written differently from how it has been created.
+//!
+//! The file consists of lines `<key> = <value>`, e.g.
+//!
+//! ```text
+//! format = 2
+//! backend = directory
+//! hash = sha256
+//! serialization = bincode
+//! compression = deflate
+//! author = Jane Doe <jane@example.com>
+//! ```
+//!
+//! The `author` is optional, and is not a choice of how the store is written, but the default
+//! author of the versions that tools like `refajo` save into the store.
+
+use std::{
+    fmt,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    Hash,
+    error::{Result, Error::VersionMismatch},
+};
+
+/// The backends of a store on disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+
+    /// One file per object, see [`DirectoryStore`].
+    ///
+    /// [`DirectoryStore`]: ../directory/struct.DirectoryStore.html
+    Directory,
+
+    /// A single embedded database, see [`DatabaseStore`].
+    ///
+    /// [`DatabaseStore`]: ../database/struct.DatabaseStore.html
+    Database,
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Backend::Directory => write!(f, "directory"),
+            Backend::Database => write!(f, "database"),
+        }
+    }
+}
+
+/// The compression of the content of stored objects. The [`Hash`] of an object is always the hash
+/// of its uncompressed content, so that objects have the same `Hash` with any compression.
+///
+/// [`Hash`]: ../../struct.Hash.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+
+    /// The content is stored as it is.
+    None,
+
+    /// The content is compressed with deflate.
+    Deflate,
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Compression::None => write!(f, "none"),
+            Compression::Deflate => write!(f, "deflate"),
+        }
+    }
+}
+
+/// The algorithm that computes the [`Hash`] of the serialized content of an object. Both
+/// algorithms compute hashes of 32 bytes, so a `Hash` does not tell which one has computed it: the
+/// algorithm is implied by the store that holds the object.
+///
+/// [`Hash`]: ../../struct.Hash.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+
+    /// SHA-256, as computed by `ring`.
+    Sha256,
+
+    /// BLAKE3, which hashes several times faster than SHA-256.
+    Blake3,
+}
+
+impl Default for HashAlgorithm {
+
+    /// SHA-256, the algorithm of all stores that have been created before another one could be
+    /// selected.
+    fn default() -> HashAlgorithm {
+        HashAlgorithm::Sha256
+    }
+}
+
+impl HashAlgorithm {
+
+    /// Computes the [`Hash`] of `content`.
+    ///
+    /// [`Hash`]: ../../struct.Hash.html
+    pub fn hash<C>(self, content: C) -> Hash
+        where C: AsRef<[u8]>
+    {
+        match self {
+            HashAlgorithm::Sha256 => Hash::sha256(content),
+            HashAlgorithm::Blake3 => Hash::from_bytes(*blake3::hash(content.as_ref()).as_bytes()),
+        }
+    }
+}
+
+impl fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HashAlgorithm::Sha256 => write!(f, "sha256"),
+            HashAlgorithm::Blake3 => write!(f, "blake3"),
+        }
+    }
+}
+
+/// The serialization of the objects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Serialization {
+
+    /// bincode, with its default settings.
+    Bincode,
+}
+
+impl fmt::Display for Serialization {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Serialization::Bincode => write!(f, "bincode"),
+        }
+    }
+}
+
+/// The format of stores that are created by this version. Only stores of this format can be opened.
+/// Stores of an older format are upgraded by [`migrate_store`].
+///
+/// The formats are:
+/// 0. the format of the first version, that stored a graph without its history: the vertices are
+///    stored as their ids, the edges without attributes, and the graph under its name,
+///    `graph/<name>`, instead of a reference to a commit,
+/// 1. objects are stored in flat directories, `<type>/<hash>`,
+/// 2. objects are stored in sharded directories, `<type>/<first two characters of the hash>/<rest
+///    of the hash>`,
+/// 3. the vertices and the edges of a graph are held by trees of chunks, instead of vectors.
+///
+/// [`migrate_store`]: ../migrate/fn.migrate_store.html
+pub const CURRENT_FORMAT: u32 = 3;
+
+/// The format of stores that do not record their format, but have references.
+const INITIAL_FORMAT: u32 = 1;
+
+/// The format of stores that have been written by the first version.
+const ORIGINAL_FORMAT: u32 = 0;
+
+/// The configuration of a store on disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoreConfig {
+
+    /// The format that the store has been written with. This is not chosen, but set to the
+    /// [`CURRENT_FORMAT`] when a store is created, and by a migration.
+    ///
+    /// [`CURRENT_FORMAT`]: ./constant.CURRENT_FORMAT.html
+    pub format: u32,
+
+    pub backend: Backend,
+    pub hash_algorithm: HashAlgorithm,
+    pub serialization: Serialization,
+    pub compression: Compression,
+
+    /// The default author of the versions that are saved into the store, if any.
+    pub author: Option<String>,
+}
+
+impl Default for StoreConfig {
+    fn default() -> StoreConfig {
+        StoreConfig {
+            format: CURRENT_FORMAT,
+            backend: Backend::Directory,
+            hash_algorithm: HashAlgorithm::Sha256,
+            serialization: Serialization::Bincode,
+            compression: Compression::None,
+            author: None,
+        }
+    }
+}
+
+fn invalid_config(message: String) -> crate::error::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message).into()
+}
+
+impl StoreConfig {
+
+    /// Returns the configuration of a store that has been created before the configuration has
+    /// been recorded.
+    pub(crate) fn initial(backend: Backend) -> StoreConfig {
+        StoreConfig {
+            format: INITIAL_FORMAT,
+            backend,
+            ..StoreConfig::default()
+        }
+    }
+
+    /// Returns the configuration of a store that has been written by the first version, which
+    /// stored a graph without its history.
+    pub(crate) fn original() -> StoreConfig {
+        StoreConfig {
+            format: ORIGINAL_FORMAT,
+            ..StoreConfig::initial(Backend::Directory)
+        }
+    }
+
+    /// Returns true if the store has been created with the same choices as `other`, regardless of
+    /// its format and its author.
+    pub(crate) fn is_compatible(&self, other: &StoreConfig) -> bool {
+        StoreConfig { format: other.format, author: other.author.clone(), ..self.clone() } == *other
+    }
+
+    /// Fails with [`VersionMismatch`], if the store has not been written with the
+    /// [`CURRENT_FORMAT`].
+    ///
+    /// [`VersionMismatch`]: ../../error/enum.Error.html#variant.VersionMismatch
+    /// [`CURRENT_FORMAT`]: ./constant.CURRENT_FORMAT.html
+    pub(crate) fn check_format(&self) -> Result<()> {
+        if self.format == CURRENT_FORMAT {
+            Ok(())
+        } else {
+            Err(VersionMismatch { found: self.format, supported: CURRENT_FORMAT })
+        }
+    }
+
+    fn parse(content: &str) -> Result<StoreConfig> {
+        let mut config = StoreConfig::initial(Backend::Directory);
+        for line in content.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let (key, value) = match line.find('=') {
+                Some(index) => (line[..index].trim(), line[index + 1..].trim()),
+                None => return Err(invalid_config(format!("invalid line in config: {}", line))),
+            };
+            match (key, value) {
+                ("format", format) => config.format = format
+                    .parse()
+                    .map_err(|_| invalid_config(format!("invalid format in config: {}", format)))?,
+                ("backend", "directory") => config.backend = Backend::Directory,
+                ("backend", "database") => config.backend = Backend::Database,
+                ("hash", "sha256") => config.hash_algorithm = HashAlgorithm::Sha256,
+                ("hash", "blake3") => config.hash_algorithm = HashAlgorithm::Blake3,
+                ("serialization", "bincode") => config.serialization = Serialization::Bincode,
+                ("compression", "none") => config.compression = Compression::None,
+                ("compression", "deflate") => config.compression = Compression::Deflate,
+                ("author", author) => config.author = Some(author.to_string()),
+                _ => return Err(invalid_config(format!("unsupported {} in config: {}", key, value))),
+            }
+        }
+        Ok(config)
+    }
+}
+
+impl fmt::Display for StoreConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "format = {}", self.format)?;
+        writeln!(f, "backend = {}", self.backend)?;
+        writeln!(f, "hash = {}", self.hash_algorithm)?;
+        writeln!(f, "serialization = {}", self.serialization)?;
+        writeln!(f, "compression = {}", self.compression)?;
+        if let Some(author) = &self.author {
+            writeln!(f, "author = {}", author)?;
+        }
+        Ok(())
+    }
+}
+
+fn config_path(base_path: &Path) -> PathBuf {
+    base_path.join("config")
+}
+
+/// Reads the configuration of the store under `base_path`. Returns `None` if there is no
+/// configuration.
+pub(crate) fn read_config(base_path: &Path) -> Result<Option<StoreConfig>> {
+    match fs::read_to_string(config_path(base_path)) {
+        Ok(content) => Ok(Some(StoreConfig::parse(&content)?)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Writes the configuration of the store under `base_path`. The configuration is written to a
+/// temporary file first, which is synced to the disk and then renamed, so that a migration that is
+/// interrupted, or a crash, does not leave a truncated configuration behind.
+pub(crate) fn write_config(base_path: &Path, config: &StoreConfig) -> Result<()> {
+    fs::create_dir_all(base_path)?;
+    let temp_path = base_path.join(".config.tmp");
+    let mut file = fs::File::create(&temp_path)?;
+    file.write_all(config.to_string().as_bytes())?;
+    file.sync_all()?;
+    fs::rename(temp_path, config_path(base_path))?;
+    if let Ok(dir) = fs::File::open(base_path) {
+        dir.sync_all()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() -> Result<()> {
+        let config = StoreConfig { backend: Backend::Database, compression: Compression::Deflate, ..StoreConfig::default() };
+        assert_eq!(StoreConfig::parse(&config.to_string())?, config);
+        let blake3 = StoreConfig { hash_algorithm: HashAlgorithm::Blake3, ..StoreConfig::default() };
+        assert_eq!(StoreConfig::parse(&blake3.to_string())?, blake3);
+        assert_eq!(StoreConfig::parse("backend = directory")?, StoreConfig::initial(Backend::Directory));
+        assert!(StoreConfig::parse("compression = lzma").is_err());
+        assert!(StoreConfig::parse("hash = md5").is_err());
+        let author = StoreConfig { author: Some("Jane Doe <jane@example.com>".to_string()), ..StoreConfig::default() };
+        assert_eq!(StoreConfig::parse(&author.to_string())?, author);
+        assert!(author.is_compatible(&StoreConfig::default()));
+
+        assert!(StoreConfig::default().check_format().is_ok());
+        assert!(StoreConfig { format: CURRENT_FORMAT + 1, ..config }.check_format().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,113 @@
+//! Implements an [`ObjectStore`] for tests, that counts the calls to a [`MemoryStore`].
+//!
+//! [`ObjectStore`]: ../trait.ObjectStore.html
+//! [`MemoryStore`]: ../memory/struct.MemoryStore.html
+
+use async_trait::async_trait;
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{Mutex, atomic::{AtomicUsize, Ordering}},
+    time::Duration,
+};
+
+use crate::{
+    Hash,
+    error::Result,
+    store::{ObjectStore, MemoryStore, StoreGuard},
+};
+
+/// Counts the objects of each type that are written, and the objects that are read or written at
+/// the same time. Every read or write of an object takes a little time, so that concurrent reads
+/// and writes overlap.
+#[derive(Default)]
+pub(crate) struct CountingStore {
+    inner: MemoryStore,
+    puts: Mutex<HashMap<String, usize>>,
+    current: AtomicUsize,
+    max: AtomicUsize,
+}
+
+impl CountingStore {
+
+    /// Returns the number of objects of type `object_type` that have been written.
+    pub(crate) fn puts(&self, object_type: &str) -> usize {
+        self.puts.lock().unwrap().get(object_type).cloned().unwrap_or(0)
+    }
+
+    /// Returns the largest number of objects that have been read or written at the same time.
+    pub(crate) fn max_concurrent(&self) -> usize {
+        self.max.load(Ordering::SeqCst)
+    }
+
+    async fn count<F, T>(&self, f: F) -> T
+        where F: Future<Output=T>
+    {
+        let current = self.current.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max.fetch_max(current, Ordering::SeqCst);
+        tokio::time::delay_for(Duration::from_micros(100)).await;
+        let result = f.await;
+        self.current.fetch_sub(1, Ordering::SeqCst);
+        result
+    }
+}
+
+#[async_trait]
+impl ObjectStore for CountingStore {
+    async fn put_object(&self, object_type: &str, hash: Hash, content: Vec<u8>) -> Result<()> {
+        *self.puts.lock().unwrap().entry(object_type.to_string()).or_insert(0) += 1;
+        self.count(self.inner.put_object(object_type, hash, content)).await
+    }
+
+    async fn get_object(&self, object_type: &str, hash: Hash) -> Result<Vec<u8>> {
+        self.count(self.inner.get_object(object_type, hash)).await
+    }
+
+    async fn contains_object(&self, object_type: &str, hash: Hash) -> Result<bool> {
+        self.count(self.inner.contains_object(object_type, hash)).await
+    }
+
+    async fn object_size(&self, object_type: &str, hash: Hash) -> Result<u64> {
+        self.inner.object_size(object_type, hash).await
+    }
+
+    async fn list_objects(&self, object_type: &str) -> Result<Vec<Hash>> {
+        self.inner.list_objects(object_type).await
+    }
+
+    async fn remove_object(&self, object_type: &str, hash: Hash) -> Result<()> {
+        self.inner.remove_object(object_type, hash).await
+    }
+
+    async fn read_ref(&self, name: &str) -> Result<Option<Hash>> {
+        self.inner.read_ref(name).await
+    }
+
+    async fn write_ref(&self, name: &str, hash: Hash) -> Result<()> {
+        self.inner.write_ref(name, hash).await
+    }
+
+    async fn compare_and_swap_ref(&self, name: &str, expected: Option<Hash>, hash: Hash) -> Result<()> {
+        self.inner.compare_and_swap_ref(name, expected, hash).await
+    }
+
+    async fn remove_ref(&self, name: &str) -> Result<()> {
+        self.inner.remove_ref(name).await
+    }
+
+    async fn list_refs(&self) -> Result<Vec<String>> {
+        self.inner.list_refs().await
+    }
+
+    async fn read_head(&self) -> Result<Option<String>> {
+        self.inner.read_head().await
+    }
+
+    async fn write_head(&self, name: &str) -> Result<()> {
+        self.inner.write_head(name).await
+    }
+
+    async fn lock(&self) -> Result<StoreGuard> {
+        self.inner.lock().await
+    }
+}
@@ -0,0 +1,216 @@
+//! Implements an [`ObjectStore`] that keeps the objects and references in a single embedded
+//! database, using [sled].
+//!
+//! The objects of each type are kept in a separate tree of the database, named by the type, with
+//! the hex-representation of their hash as key. The named references are kept in the tree `refs`,
+//! and the name of the current branch under the key `HEAD` of the default tree.
+//!
+//! [`ObjectStore`]: ../trait.ObjectStore.html
+//! [sled]: https://docs.rs/sled
+
+use async_trait::async_trait;
+use std::{
+    path::Path,
+    sync::Arc,
+};
+use tokio::sync::Mutex;
+
+use crate::{
+    Hash,
+    error::{Result, Error::{ConcurrentModification, ObjectNotFound, RefNotFound}},
+    store::{ObjectStore, StoreGuard, HashAlgorithm},
+};
+
+/// The name of the tree that holds the named references.
+const REFS_TREE: &str = "refs";
+
+/// The key of the name of the current branch in the default tree.
+const HEAD_KEY: &str = "HEAD";
+
+/// Keeps objects and references in a single database file, instead of one file per object. This
+/// is faster to write and easier to copy than a [`DirectoryStore`], if a graph has many vertices
+/// and edges.
+///
+/// The database can only be opened by one process at a time. Clones of a `DatabaseStore` share
+/// the same database.
+///
+/// [`DirectoryStore`]: ../directory/struct.DirectoryStore.html
+#[derive(Clone)]
+pub struct DatabaseStore {
+    db: sled::Db,
+    lock: Arc<Mutex<()>>,
+    hash_algorithm: HashAlgorithm,
+}
+
+impl DatabaseStore {
+
+    /// Opens the database at `path`, and creates it, if it does not exist.
+    pub fn open<P>(path: P) -> Result<DatabaseStore>
+        where P: AsRef<Path>
+    {
+        Ok(DatabaseStore {
+            db: sled::open(path)?,
+            lock: Arc::new(Mutex::new(())),
+            hash_algorithm: HashAlgorithm::Sha256,
+        })
+    }
+
+    /// Addresses the objects of the store by the [`Hash`]es of `hash_algorithm`, instead of
+    /// SHA256. A store has to be read with the algorithm that it has been written with.
+    ///
+    /// [`Hash`]: ../../struct.Hash.html
+    pub fn with_hash_algorithm(self, hash_algorithm: HashAlgorithm) -> DatabaseStore {
+        DatabaseStore { hash_algorithm, ..self }
+    }
+
+    fn objects(&self, object_type: &str) -> Result<sled::Tree> {
+        Ok(self.db.open_tree(object_type)?)
+    }
+
+    fn refs(&self) -> Result<sled::Tree> {
+        Ok(self.db.open_tree(REFS_TREE)?)
+    }
+
+    /// Writes all changes to the disk, so that a modified reference survives a crash.
+    async fn flush(&self) -> Result<()> {
+        self.db.flush_async().await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ObjectStore for DatabaseStore {
+    async fn put_object(&self, object_type: &str, hash: Hash, content: Vec<u8>) -> Result<()> {
+        self.objects(object_type)?.insert(hash.to_string(), content)?;
+        Ok(())
+    }
+
+    async fn get_object(&self, object_type: &str, hash: Hash) -> Result<Vec<u8>> {
+        self.objects(object_type)?
+            .get(hash.to_string())?
+            .map(|content| content.to_vec())
+            .ok_or_else(|| ObjectNotFound { object_type: object_type.to_string(), hash })
+    }
+
+    async fn contains_object(&self, object_type: &str, hash: Hash) -> Result<bool> {
+        Ok(self.objects(object_type)?.contains_key(hash.to_string())?)
+    }
+
+    async fn object_size(&self, object_type: &str, hash: Hash) -> Result<u64> {
+        self.objects(object_type)?
+            .get(hash.to_string())?
+            .map(|content| content.len() as u64)
+            .ok_or_else(|| ObjectNotFound { object_type: object_type.to_string(), hash })
+    }
+
+    async fn list_objects(&self, object_type: &str) -> Result<Vec<Hash>> {
+        let mut hashes: Vec<Hash> = Vec::new();
+        for key in self.objects(object_type)?.iter().keys() {
+            if let Some(hash) = std::str::from_utf8(&key?).ok().and_then(Hash::from_hex) {
+                hashes.push(hash);
+            }
+        }
+        Ok(hashes)
+    }
+
+    async fn remove_object(&self, object_type: &str, hash: Hash) -> Result<()> {
+        self.objects(object_type)?
+            .remove(hash.to_string())?
+            .map(|_| ())
+            .ok_or_else(|| ObjectNotFound { object_type: object_type.to_string(), hash })
+    }
+
+    async fn read_ref(&self, name: &str) -> Result<Option<Hash>> {
+        match self.refs()?.get(name)? {
+            Some(content) => Ok(Some(bincode::deserialize(&content)?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn write_ref(&self, name: &str, hash: Hash) -> Result<()> {
+        self.refs()?.insert(name, bincode::serialize(&hash)?)?;
+        self.flush().await
+    }
+
+    async fn compare_and_swap_ref(&self, name: &str, expected: Option<Hash>, hash: Hash) -> Result<()> {
+        let expected_content = match expected {
+            Some(expected) => Some(bincode::serialize(&expected)?),
+            None => None,
+        };
+        let swapped = self.refs()?.compare_and_swap(name, expected_content, Some(bincode::serialize(&hash)?))?;
+        if swapped.is_err() {
+            let actual = self.read_ref(name).await?;
+            return Err(ConcurrentModification { name: name.to_string(), expected, actual });
+        }
+        self.flush().await
+    }
+
+    async fn remove_ref(&self, name: &str) -> Result<()> {
+        self.refs()?
+            .remove(name)?
+            .ok_or_else(|| RefNotFound { name: name.to_string() })?;
+        self.flush().await
+    }
+
+    async fn list_refs(&self) -> Result<Vec<String>> {
+        let mut names: Vec<String> = Vec::new();
+        for key in self.refs()?.iter().keys() {
+            names.push(String::from_utf8_lossy(&key?).into_owned());
+        }
+        Ok(names)
+    }
+
+    async fn read_head(&self) -> Result<Option<String>> {
+        Ok(self.db
+            .get(HEAD_KEY)?
+            .map(|name| String::from_utf8_lossy(&name).into_owned()))
+    }
+
+    async fn write_head(&self, name: &str) -> Result<()> {
+        self.db.insert(HEAD_KEY, name)?;
+        self.flush().await
+    }
+
+    /// The database can only be opened by one process at a time, so the lock only needs to
+    /// serialize the modifications within this process.
+    async fn lock(&self) -> Result<StoreGuard> {
+        Ok(StoreGuard::new(self.lock.clone().lock_owned().await))
+    }
+
+    fn hash_algorithm(&self) -> HashAlgorithm {
+        self.hash_algorithm
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tokio::{fs, runtime::Runtime};
+
+    use super::*;
+
+    #[test]
+    fn test_database_store() -> Result<()> {
+        let mut rt = Runtime::new()?;
+        rt.block_on(async {
+            let path = "../target/test/database_store/";
+            let _ = fs::remove_dir_all(path).await;
+            let store = DatabaseStore::open(path)?;
+            let (hash_1, hash_2): (Hash, Hash) = (Hash::sha256(b"1"), Hash::sha256(b"2"));
+
+            store.put_object("vertex", hash_1, b"1".to_vec()).await?;
+            assert_eq!(store.get_object("vertex", hash_1).await?, b"1".to_vec());
+            assert!(!store.contains_object("edge", hash_1).await?);
+            assert_eq!(store.list_objects("vertex").await?, vec![hash_1]);
+
+            store.compare_and_swap_ref("main", None, hash_1).await?;
+            assert!(store.compare_and_swap_ref("main", None, hash_2).await.is_err());
+            store.compare_and_swap_ref("main", Some(hash_1), hash_2).await?;
+            assert_eq!(store.read_ref("main").await?, Some(hash_2));
+            assert_eq!(store.list_refs().await?, vec!["main".to_string()]);
+
+            store.write_head("main").await?;
+            assert_eq!(store.read_head().await?, Some("main".to_string()));
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,716 @@
+//! Implements an [`ObjectStore`] that stores the objects and references as files in a directory.
+//!
+//! Each object is stored in the file `<type>/<hh>/<rest of the hash>`, where `<hh>` are the first
+//! two characters of the hex-representation of its hash, or, in the [flat layout] of older stores,
+//! in the file `<type>/<hash>`. Each named reference is stored in the file
+//! `refs/<name>`, and the name of the current branch in the file `HEAD`. Objects can be moved
+//! into packs in the directory `pack/`, to save files, and are then read from the packs.
+//!
+//! [flat layout]: ./enum.Layout.html#variant.Flat
+//! [`ObjectStore`]: ../trait.ObjectStore.html
+
+use async_trait::async_trait;
+use std::{
+    collections::{BTreeMap, HashSet},
+    io,
+    path::{Component, Path, PathBuf},
+    process,
+    sync::{Arc, Mutex, atomic::{AtomicUsize, Ordering}},
+};
+use tokio::{fs, io::AsyncWriteExt};
+
+use crate::{
+    Hash,
+    error::{Result, Error::{ConcurrentModification, IoError, InvalidRefName, ObjectNotFound, RefNotFound}},
+    lock::FileLock,
+    store::{
+        ObjectStore, ObjectId, StoreGuard, HashAlgorithm,
+        pack::{ObjectKey, Pack, list_packs, write_pack, remove_pack},
+    },
+};
+
+/// The suffix of the names of temporary files.
+const TEMP_SUFFIX: &str = ".tmp";
+
+/// Counts the temporary files written by this process, so that their names are unique.
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns true if `file_name` is the name of a temporary file, that is written by
+/// [`write_atomically`] before it is renamed. Such a file only remains if a write has been
+/// interrupted.
+///
+/// [`write_atomically`]: ./fn.write_atomically.html
+fn is_temp_file(file_name: &str) -> bool {
+    file_name.starts_with('.') && file_name.ends_with(TEMP_SUFFIX)
+}
+
+/// Writes `content` to the file at `path`, so that the file either holds its previous content
+/// or the complete new `content`, even if the write is interrupted, or the system crashes: the
+/// content is written to a temporary file in the same directory first, which is synced to the
+/// disk, and then renamed to `path`. The directory is synced after the rename, so that the rename
+/// is on the disk as well, when the write returns.
+async fn write_atomically<P, C>(path: P, content: C) -> std::result::Result<(), io::Error>
+    where P: AsRef<Path>,
+          C: AsRef<[u8]> + Unpin
+{
+    let path = path.as_ref();
+    let file_name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+    let temp_path = path.with_file_name(format!(
+        ".{}.{}.{}{}",
+        file_name,
+        process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed),
+        TEMP_SUFFIX,
+    ));
+
+    if let Err(e) = write_and_sync(&temp_path, content.as_ref()).await {
+        let _ = fs::remove_file(&temp_path).await;
+        return Err(e);
+    }
+    fs::rename(&temp_path, path).await?;
+    match path.parent() {
+        Some(dir) => sync_dir(dir).await,
+        None => Ok(()),
+    }
+}
+
+/// Writes `content` to a new file at `path`, and waits until it is on the disk.
+async fn write_and_sync(path: &Path, content: &[u8]) -> std::result::Result<(), io::Error> {
+    let mut file = fs::File::create(path).await?;
+    file.write_all(content).await?;
+    file.sync_all().await
+}
+
+/// Waits until the entries of the directory `dir`, e.g. a file that has been renamed into it, are
+/// on the disk. Directories cannot be opened as files on every platform, so nothing is synced
+/// where this fails.
+async fn sync_dir(dir: &Path) -> std::result::Result<(), io::Error> {
+    match fs::File::open(dir).await {
+        Ok(mut dir) => dir.sync_all().await,
+        Err(_) => Ok(()),
+    }
+}
+
+/// Like [`write_atomically`], but creates the directory of the file, if it does not exist.
+///
+/// [`write_atomically`]: ./fn.write_atomically.html
+pub(super) async fn create_dir_and_write_atomically<P, C>(path: P, content: C) -> std::result::Result<(), io::Error>
+    where P: AsRef<Path>,
+          C: AsRef<[u8]> + Unpin + Clone
+{
+    match write_atomically(path.as_ref(), content.clone()).await {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            if let Some(dir) = path.as_ref().parent() {
+                fs::create_dir_all(dir).await?;
+            }
+            write_atomically(path, content).await
+        }
+        result => result,
+    }
+}
+
+/// Returns the names of the files in the directory `dir`, except temporary files. Returns an empty
+/// vector, if the directory does not exist.
+pub(super) async fn list_files(dir: PathBuf) -> Result<Vec<String>> {
+    let mut entries = match fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut names: Vec<String> = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        match entry.file_name().to_str() {
+            Some(name) if !is_temp_file(name) => names.push(name.to_string()),
+            _ => {}
+        }
+    }
+    Ok(names)
+}
+
+/// The number of characters of the hex-representation of a hash that name the directory of a
+/// shard.
+const SHARD_LENGTH: usize = 2;
+
+/// How the files of the objects of a type are arranged in the directory of the type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+
+    /// All files are in the directory of the type. This slows down the file system, if there are
+    /// many objects.
+    Flat,
+
+    /// The files are spread over 256 sub-directories, named by the first two characters of the
+    /// hex-representation of their hash.
+    Sharded,
+}
+
+/// Returns the [`Hash`]es of the files in the directory `dir`. Other files are ignored.
+///
+/// [`Hash`]: ../../struct.Hash.html
+async fn list_flat(dir: PathBuf) -> Result<Vec<Hash>> {
+    Ok(list_files(dir)
+        .await?
+        .iter()
+        .filter_map(|name| Hash::from_hex(name))
+        .collect())
+}
+
+/// Returns the names of the shards in the directory `dir`.
+async fn list_shards(dir: PathBuf) -> Result<Vec<String>> {
+    let mut entries = match fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut shards: Vec<String> = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        match entry.file_name().to_str() {
+            Some(name) if name.len() == SHARD_LENGTH && entry.file_type().await?.is_dir() => shards.push(name.to_string()),
+            _ => {}
+        }
+    }
+    Ok(shards)
+}
+
+/// Stores objects and references as files under a base directory.
+#[derive(Debug, Clone)]
+pub struct DirectoryStore {
+    base_path: PathBuf,
+    layout: Layout,
+    hash_algorithm: HashAlgorithm,
+
+    /// The packs, once they have been loaded. Clones of a `DirectoryStore` share them.
+    packs: Arc<Mutex<Option<Arc<Vec<Pack>>>>>,
+}
+
+impl DirectoryStore {
+
+    /// Creates a store with the [`Layout::Sharded`] under the directory `base_path`. The directory
+    /// is created when the first object is stored.
+    ///
+    /// [`Layout::Sharded`]: ./enum.Layout.html#variant.Sharded
+    pub fn new<P>(base_path: P) -> DirectoryStore
+        where P: AsRef<Path>
+    {
+        DirectoryStore::with_layout(base_path, Layout::Sharded)
+    }
+
+    /// Creates a store with the [`Layout`] `layout` under the directory `base_path`.
+    ///
+    /// [`Layout`]: ./enum.Layout.html
+    pub fn with_layout<P>(base_path: P, layout: Layout) -> DirectoryStore
+        where P: AsRef<Path>
+    {
+        DirectoryStore {
+            base_path: base_path.as_ref().into(),
+            layout,
+            hash_algorithm: HashAlgorithm::Sha256,
+            packs: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Addresses the objects of the store by the [`Hash`]es of `hash_algorithm`, instead of
+    /// SHA256. A store has to be read with the algorithm that it has been written with.
+    ///
+    /// [`Hash`]: ../../struct.Hash.html
+    pub fn with_hash_algorithm(self, hash_algorithm: HashAlgorithm) -> DirectoryStore {
+        DirectoryStore { hash_algorithm, ..self }
+    }
+
+    /// Returns the directory of the store.
+    pub fn base_path(&self) -> &Path {
+        &self.base_path
+    }
+
+    /// Returns the path of the file that holds an object of type `object_type` with the
+    /// [`Hash`] `hash`.
+    ///
+    /// [`Hash`]: ../../struct.Hash.html
+    pub fn object_path(&self, object_type: &str, hash: Hash) -> PathBuf {
+        let hash = hash.to_string();
+        match self.layout {
+            Layout::Flat => self.base_path.join(object_type).join(hash),
+            Layout::Sharded => self.base_path.join(object_type).join(&hash[..SHARD_LENGTH]).join(&hash[SHARD_LENGTH..]),
+        }
+    }
+
+    /// Returns the path of the file that holds the reference `name`. Fails with
+    /// [`InvalidRefName`], if `name` is empty, absolute, or has a component like `..`, that would
+    /// address a file outside of the directory `refs/`.
+    ///
+    /// [`InvalidRefName`]: ../../error/enum.Error.html#variant.InvalidRefName
+    pub fn ref_path(&self, name: &str) -> Result<PathBuf> {
+        let mut components = Path::new(name).components().peekable();
+        if components.peek().is_none() || !components.all(|component| matches!(component, Component::Normal(_))) {
+            return Err(InvalidRefName { name: name.to_string() });
+        }
+        Ok(self.base_path.join("refs").join(name))
+    }
+
+    fn head_path(&self) -> PathBuf {
+        self.base_path.join("HEAD")
+    }
+
+    /// The lock of the whole store, see [`ObjectStore::lock`].
+    ///
+    /// [`ObjectStore::lock`]: ../trait.ObjectStore.html#tymethod.lock
+    fn lock_path(&self) -> PathBuf {
+        self.base_path.join("lock")
+    }
+
+    /// The lock that makes the comparison and the update of a reference atomic.
+    fn refs_lock_path(&self) -> PathBuf {
+        self.base_path.join("refs.lock")
+    }
+
+    fn pack_dir(&self) -> PathBuf {
+        self.base_path.join("pack")
+    }
+
+    /// Returns the packs. They are loaded, if they have not been loaded before, or if `refresh` is
+    /// true and packs have been written or removed since they have been loaded, e.g. by another
+    /// process.
+    async fn packs(&self, refresh: bool) -> Result<Arc<Vec<Pack>>> {
+        let loaded = self.packs.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        let names = match loaded {
+            Some(packs) if !refresh => return Ok(packs),
+            loaded => {
+                let names = list_packs(&self.pack_dir()).await?;
+                match loaded {
+                    Some(packs) if packs.iter().map(|pack| &pack.name).eq(names.iter()) => return Ok(packs),
+                    _ => names,
+                }
+            }
+        };
+
+        let mut packs: Vec<Pack> = Vec::new();
+        for name in names {
+            packs.push(Pack::load(&self.pack_dir(), &name).await?);
+        }
+        let packs = Arc::new(packs);
+        *self.packs.lock().unwrap_or_else(|e| e.into_inner()) = Some(packs.clone());
+        Ok(packs)
+    }
+
+    /// Forgets the loaded packs, after packs have been written or removed.
+    fn forget_packs(&self) {
+        *self.packs.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+
+    /// Returns the content of the object `key` from the packs, or `None` if no pack contains it.
+    async fn read_packed(&self, key: &ObjectKey) -> Result<Option<Vec<u8>>> {
+        for &refresh in [false, true].iter() {
+            for pack in self.packs(refresh).await?.iter() {
+                match pack.read(key).await {
+                    Ok(Some(content)) => return Ok(Some(content)),
+                    Ok(None) => {}
+                    // the pack has been removed in the meantime, so the packs are refreshed
+                    Err(IoError(e)) if e.kind() == io::ErrorKind::NotFound => {}
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the number of bytes of the object `key` in the pack that contains it, or `None` if
+    /// no pack contains it.
+    async fn packed_length(&self, key: &ObjectKey) -> Result<Option<u64>> {
+        for &refresh in [false, true].iter() {
+            if let Some(length) = self.packs(refresh).await?.iter().find_map(|pack| pack.length(key)) {
+                return Ok(Some(length));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns true if a pack contains the object `key`.
+    async fn is_packed(&self, key: &ObjectKey) -> Result<bool> {
+        for &refresh in [false, true].iter() {
+            if self.packs(refresh).await?.iter().any(|pack| pack.contains(key)) {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Removes the `objects` from the packs that contain them, by rewriting these packs without
+    /// them. Returns the objects that have been removed.
+    async fn remove_packed(&self, objects: &HashSet<ObjectKey>) -> Result<HashSet<ObjectKey>> {
+        let mut removed: HashSet<ObjectKey> = HashSet::new();
+        for pack in self.packs(true).await?.iter() {
+            if !pack.objects().any(|key| objects.contains(key)) {
+                continue;
+            }
+            let (remove, keep): (Vec<_>, Vec<_>) = pack
+                .read_all()
+                .await?
+                .into_iter()
+                .partition(|(key, _)| objects.contains(key));
+            if !keep.is_empty() {
+                write_pack(&self.pack_dir(), keep).await?;
+            }
+            remove_pack(&self.pack_dir(), &pack.name).await?;
+            removed.extend(remove.into_iter().map(|(key, _)| key));
+        }
+        self.forget_packs();
+        Ok(removed)
+    }
+
+    /// Returns all objects that are stored as separate files.
+    async fn loose_objects(&self) -> Result<Vec<ObjectKey>> {
+        let mut objects: Vec<ObjectKey> = Vec::new();
+        for (object_type, _) in self.type_dirs().await? {
+            for hash in self.list_loose(&object_type).await? {
+                objects.push((object_type.clone(), hash));
+            }
+        }
+        Ok(objects)
+    }
+
+    /// Returns the [`Hash`]es of the objects of type `object_type` that are stored as separate
+    /// files.
+    ///
+    /// [`Hash`]: ../../struct.Hash.html
+    async fn list_loose(&self, object_type: &str) -> Result<Vec<Hash>> {
+        match self.layout {
+            Layout::Flat => list_flat(self.base_path.join(object_type)).await,
+            Layout::Sharded => {
+                let mut hashes: Vec<Hash> = Vec::new();
+                for shard in list_shards(self.base_path.join(object_type)).await? {
+                    for name in list_files(self.base_path.join(object_type).join(&shard)).await? {
+                        hashes.extend(Hash::from_hex(&format!("{}{}", shard, name)));
+                    }
+                }
+                Ok(hashes)
+            }
+        }
+    }
+
+    /// Moves the files of all objects that are stored in the [`Layout::Flat`] into their shards,
+    /// and returns the number of moved files. The caller must hold the lock of the store. If the
+    /// moving is interrupted, it can be started again.
+    ///
+    /// [`Layout::Flat`]: ./enum.Layout.html#variant.Flat
+    pub(crate) async fn shard(&self) -> Result<usize> {
+        let flat = DirectoryStore::with_layout(&self.base_path, Layout::Flat);
+        let sharded = DirectoryStore::with_layout(&self.base_path, Layout::Sharded);
+        let mut moved: usize = 0;
+        for (object_type, _) in self.type_dirs().await? {
+            for hash in flat.list_loose(&object_type).await? {
+                let path = sharded.object_path(&object_type, hash);
+                if let Some(dir) = path.parent() {
+                    fs::create_dir_all(dir).await?;
+                }
+                fs::rename(flat.object_path(&object_type, hash), path).await?;
+                moved += 1;
+            }
+        }
+        Ok(moved)
+    }
+
+    /// Returns the names and the paths of the directories of the object types.
+    async fn type_dirs(&self) -> Result<Vec<(String, PathBuf)>> {
+        let mut dirs: Vec<(String, PathBuf)> = Vec::new();
+        let mut entries = match fs::read_dir(&self.base_path).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(dirs),
+            Err(e) => return Err(e.into()),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            match entry.file_name().to_str() {
+                Some(name) if name != "refs" && name != "pack" && entry.file_type().await?.is_dir() => {
+                    dirs.push((name.to_string(), entry.path()));
+                }
+                _ => {}
+            }
+        }
+        Ok(dirs)
+    }
+
+    /// Returns the paths of the temporary files that remained from interrupted writes.
+    async fn temp_files(&self) -> Result<Vec<PathBuf>> {
+        let mut dirs: Vec<PathBuf> = vec![self.base_path.clone()];
+        let mut temp_files: Vec<PathBuf> = Vec::new();
+
+        while let Some(dir) = dirs.pop() {
+            let mut entries = match fs::read_dir(&dir).await {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            };
+            while let Some(entry) = entries.next_entry().await? {
+                if entry.file_type().await?.is_dir() {
+                    dirs.push(entry.path());
+                } else if entry.file_name().to_str().is_some_and(is_temp_file) {
+                    temp_files.push(entry.path());
+                }
+            }
+        }
+
+        Ok(temp_files)
+    }
+}
+
+#[async_trait]
+impl ObjectStore for DirectoryStore {
+    async fn put_object(&self, object_type: &str, hash: Hash, content: Vec<u8>) -> Result<()> {
+        create_dir_and_write_atomically(self.object_path(object_type, hash), content).await?;
+        Ok(())
+    }
+
+    /// Reads the object from its file, or from a pack, if there is no such file.
+    async fn get_object(&self, object_type: &str, hash: Hash) -> Result<Vec<u8>> {
+        match fs::read(self.object_path(object_type, hash)).await {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => self
+                .read_packed(&(object_type.to_string(), hash))
+                .await?
+                .ok_or_else(|| ObjectNotFound { object_type: object_type.to_string(), hash }),
+            result => Ok(result?),
+        }
+    }
+
+    async fn contains_object(&self, object_type: &str, hash: Hash) -> Result<bool> {
+        match fs::metadata(self.object_path(object_type, hash)).await {
+            Ok(_) => Ok(true),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => self.is_packed(&(object_type.to_string(), hash)).await,
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn object_size(&self, object_type: &str, hash: Hash) -> Result<u64> {
+        match fs::metadata(self.object_path(object_type, hash)).await {
+            Ok(metadata) => Ok(metadata.len()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => self
+                .packed_length(&(object_type.to_string(), hash))
+                .await?
+                .ok_or_else(|| ObjectNotFound { object_type: object_type.to_string(), hash }),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn list_objects(&self, object_type: &str) -> Result<Vec<Hash>> {
+        let mut hashes = self.list_loose(object_type).await?;
+        let mut listed: HashSet<Hash> = hashes.iter().cloned().collect();
+        for pack in self.packs(true).await?.iter() {
+            for (packed_type, hash) in pack.objects() {
+                if packed_type == object_type && listed.insert(*hash) {
+                    hashes.push(*hash);
+                }
+            }
+        }
+        Ok(hashes)
+    }
+
+    async fn remove_object(&self, object_type: &str, hash: Hash) -> Result<()> {
+        let key: ObjectKey = (object_type.to_string(), hash);
+        let removed_file = match fs::remove_file(self.object_path(object_type, hash)).await {
+            Ok(()) => true,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => false,
+            Err(e) => return Err(e.into()),
+        };
+        let removed_packed = self.remove_packed(&std::iter::once(key).collect()).await?;
+        if !removed_file && removed_packed.is_empty() {
+            return Err(ObjectNotFound { object_type: object_type.to_string(), hash });
+        }
+        Ok(())
+    }
+
+    /// Removes the files of the `objects`, and rewrites each pack that contains some of them only
+    /// once.
+    async fn remove_objects(&self, objects: &[ObjectId]) -> Result<()> {
+        let mut missing: HashSet<ObjectKey> = HashSet::new();
+        for id in objects {
+            match fs::remove_file(self.object_path(id.object_type, id.hash)).await {
+                Ok(()) => {}
+                Err(e) if e.kind() == io::ErrorKind::NotFound => { missing.insert((id.object_type.to_string(), id.hash)); }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        let all: HashSet<ObjectKey> = objects.iter().map(|id| (id.object_type.to_string(), id.hash)).collect();
+        let removed_packed = self.remove_packed(&all).await?;
+        match missing.difference(&removed_packed).next() {
+            Some((object_type, hash)) => Err(ObjectNotFound { object_type: object_type.clone(), hash: *hash }),
+            None => Ok(()),
+        }
+    }
+
+    async fn read_ref(&self, name: &str) -> Result<Option<Hash>> {
+        match fs::read(self.ref_path(name)?).await {
+            Ok(content) => Ok(Some(bincode::deserialize(&content)?)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn write_ref(&self, name: &str, hash: Hash) -> Result<()> {
+        let content: Vec<u8> = bincode::serialize(&hash)?;
+        create_dir_and_write_atomically(self.ref_path(name)?, content).await?;
+        Ok(())
+    }
+
+    async fn compare_and_swap_ref(&self, name: &str, expected: Option<Hash>, hash: Hash) -> Result<()> {
+        self.ref_path(name)?;
+        let _lock = FileLock::acquire(self.refs_lock_path()).await?;
+
+        let actual = self.read_ref(name).await?;
+        if actual != expected {
+            return Err(ConcurrentModification { name: name.to_string(), expected, actual });
+        }
+        self.write_ref(name, hash).await
+    }
+
+    async fn remove_ref(&self, name: &str) -> Result<()> {
+        match fs::remove_file(self.ref_path(name)?).await {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Err(RefNotFound { name: name.to_string() }),
+            result => Ok(result?),
+        }
+    }
+
+    async fn list_refs(&self) -> Result<Vec<String>> {
+        let mut names = list_files(self.base_path.join("refs")).await?;
+        names.sort();
+        Ok(names)
+    }
+
+    async fn read_head(&self) -> Result<Option<String>> {
+        match fs::read_to_string(self.head_path()).await {
+            Ok(name) => Ok(Some(name.trim().to_string())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn write_head(&self, name: &str) -> Result<()> {
+        create_dir_and_write_atomically(self.head_path(), name.to_string()).await?;
+        Ok(())
+    }
+
+    async fn lock(&self) -> Result<StoreGuard> {
+        Ok(StoreGuard::new(FileLock::acquire(self.lock_path()).await?))
+    }
+
+    /// Moves all objects that are stored as separate files, and all packs, into a single new pack.
+    async fn repack(&self) -> Result<usize> {
+        let _guard = self.lock().await?;
+
+        let loose = self.loose_objects().await?;
+        if loose.is_empty() {
+            return Ok(0);
+        }
+
+        let mut objects: BTreeMap<ObjectKey, Vec<u8>> = BTreeMap::new();
+        let packs = self.packs(true).await?;
+        for pack in packs.iter() {
+            objects.extend(pack.read_all().await?);
+        }
+        for (object_type, hash) in loose.iter() {
+            let content = fs::read(self.object_path(object_type, *hash)).await?;
+            objects.insert((object_type.clone(), *hash), content);
+        }
+
+        let name = write_pack(&self.pack_dir(), objects).await?;
+        for (object_type, hash) in loose.iter() {
+            fs::remove_file(self.object_path(object_type, *hash)).await?;
+        }
+        for pack in packs.iter().filter(|pack| pack.name != name) {
+            remove_pack(&self.pack_dir(), &pack.name).await?;
+        }
+        self.forget_packs();
+
+        Ok(loose.len())
+    }
+
+    /// Removes the temporary files that remained from interrupted writes. The caller must hold the
+    /// lock of the store, so that no temporary file is removed that is just being written.
+    async fn remove_leftovers(&self, dry_run: bool) -> Result<u64> {
+        let mut bytes: u64 = 0;
+        for path in self.temp_files().await? {
+            bytes += fs::metadata(&path).await?.len();
+            if !dry_run {
+                fs::remove_file(&path).await?;
+            }
+        }
+        Ok(bytes)
+    }
+
+    fn hash_algorithm(&self) -> HashAlgorithm {
+        self.hash_algorithm
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tokio::runtime::Runtime;
+
+    use super::*;
+
+    #[test]
+    fn test_write_atomically_leaves_no_temporary_file() -> Result<()> {
+        let mut rt = Runtime::new()?;
+        rt.block_on(async {
+            let store = DirectoryStore::new("../target/test/directory_store/");
+            let _ = fs::remove_dir_all(store.base_path()).await;
+
+            let hash: Hash = Hash::sha256(b"content");
+            store.put_object("vertex", hash, b"content".to_vec()).await?;
+            store.write_ref("main", hash).await?;
+            store.write_head("main").await?;
+
+            assert_eq!(store.get_object("vertex", hash).await?, b"content".to_vec());
+            assert_eq!(store.list_objects("vertex").await?, vec![hash]);
+            assert_eq!(store.read_head().await?, Some("main".to_string()));
+            assert!(store.temp_files().await?.is_empty());
+
+            fs::write(store.base_path().join("vertex").join(".abc.1.2.tmp"), b"cont").await?;
+            assert_eq!(store.list_objects("vertex").await?, vec![hash]);
+            assert_eq!(store.remove_leftovers(true).await?, 4);
+            assert_eq!(store.remove_leftovers(false).await?, 4);
+            assert!(store.temp_files().await?.is_empty());
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_repack() -> Result<()> {
+        let mut rt = Runtime::new()?;
+        rt.block_on(async {
+            let store = DirectoryStore::new("../target/test/pack_store/");
+            let _ = fs::remove_dir_all(store.base_path()).await;
+            let (hash_1, hash_2, hash_3): (Hash, Hash, Hash) = (Hash::sha256(b"1"), Hash::sha256(b"2"), Hash::sha256(b"3"));
+
+            store.put_object("vertex", hash_1, b"1".to_vec()).await?;
+            store.put_object("edge", hash_2, b"22".to_vec()).await?;
+            assert_eq!(store.object_size("edge", hash_2).await?, 2);
+            assert_eq!(store.repack().await?, 2);
+            assert!(store.loose_objects().await?.is_empty());
+            assert_eq!(store.repack().await?, 0);
+
+            // another store on the same directory reads the objects from the pack
+            let other = DirectoryStore::new(store.base_path());
+            assert_eq!(other.get_object("vertex", hash_1).await?, b"1".to_vec());
+            assert_eq!(other.get_object("edge", hash_2).await?, b"22".to_vec());
+            assert_eq!(other.object_size("edge", hash_2).await?, 2);
+            assert!(other.object_size("vertex", hash_2).await.is_err());
+            assert!(!other.contains_object("vertex", hash_2).await?);
+
+            // new objects are packed together with the existing pack
+            store.put_object("vertex", hash_3, b"3".to_vec()).await?;
+            assert_eq!(store.repack().await?, 1);
+            assert_eq!(list_packs(&store.pack_dir()).await?.len(), 1);
+            let mut vertices = other.list_objects("vertex").await?;
+            vertices.sort();
+            let mut expected = vec![hash_1, hash_3];
+            expected.sort();
+            assert_eq!(vertices, expected);
+
+            other.remove_object("vertex", hash_1).await?;
+            assert!(!store.contains_object("vertex", hash_1).await?);
+            assert!(store.remove_object("vertex", hash_1).await.is_err());
+            assert_eq!(store.get_object("vertex", hash_3).await?, b"3".to_vec());
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,174 @@
+//! Implements an [`ObjectStore`] that limits the number of objects of another `ObjectStore`, that
+//! are read or written at the same time.
+//!
+//! [`ObjectStore`]: ../trait.ObjectStore.html
+
+use async_trait::async_trait;
+use tokio::sync::Semaphore;
+
+use crate::{
+    Hash,
+    error::Result,
+    store::{ObjectStore, ObjectCache, HashAlgorithm, ObjectId, StoreGuard},
+};
+
+/// Limits the number of objects of the inner store, that are read, written, checked or removed at
+/// the same time, to `max_concurrency`. The functions of this crate read and write at most
+/// [`max_concurrency`] objects at the same time anyway, but a store that is shared by several
+/// tasks, e.g. by the requests of a server, is read and written by all of them at the same time.
+///
+/// [`max_concurrency`]: ../trait.ObjectStore.html#method.max_concurrency
+pub struct LimitedStore<S> {
+    inner: S,
+    max_concurrency: usize,
+    permits: Semaphore,
+}
+
+impl<S> LimitedStore<S>
+    where S: ObjectStore
+{
+
+    /// Wraps the store `inner`, so that at most `max_concurrency` objects are read or written at
+    /// the same time. `max_concurrency` must not be 0.
+    pub fn new(inner: S, max_concurrency: usize) -> LimitedStore<S> {
+        assert!(max_concurrency > 0, "max_concurrency must not be 0");
+        LimitedStore {
+            inner,
+            max_concurrency,
+            permits: Semaphore::new(max_concurrency),
+        }
+    }
+}
+
+#[async_trait]
+impl<S> ObjectStore for LimitedStore<S>
+    where S: ObjectStore
+{
+    async fn put_object(&self, object_type: &str, hash: Hash, content: Vec<u8>) -> Result<()> {
+        let _permit = self.permits.acquire().await;
+        self.inner.put_object(object_type, hash, content).await
+    }
+
+    async fn get_object(&self, object_type: &str, hash: Hash) -> Result<Vec<u8>> {
+        let _permit = self.permits.acquire().await;
+        self.inner.get_object(object_type, hash).await
+    }
+
+    async fn contains_object(&self, object_type: &str, hash: Hash) -> Result<bool> {
+        let _permit = self.permits.acquire().await;
+        self.inner.contains_object(object_type, hash).await
+    }
+
+    async fn object_size(&self, object_type: &str, hash: Hash) -> Result<u64> {
+        let _permit = self.permits.acquire().await;
+        self.inner.object_size(object_type, hash).await
+    }
+
+    async fn list_objects(&self, object_type: &str) -> Result<Vec<Hash>> {
+        self.inner.list_objects(object_type).await
+    }
+
+    async fn remove_object(&self, object_type: &str, hash: Hash) -> Result<()> {
+        let _permit = self.permits.acquire().await;
+        self.inner.remove_object(object_type, hash).await
+    }
+
+    async fn remove_objects(&self, objects: &[ObjectId]) -> Result<()> {
+        self.inner.remove_objects(objects).await
+    }
+
+    async fn read_ref(&self, name: &str) -> Result<Option<Hash>> {
+        self.inner.read_ref(name).await
+    }
+
+    async fn write_ref(&self, name: &str, hash: Hash) -> Result<()> {
+        self.inner.write_ref(name, hash).await
+    }
+
+    async fn compare_and_swap_ref(&self, name: &str, expected: Option<Hash>, hash: Hash) -> Result<()> {
+        self.inner.compare_and_swap_ref(name, expected, hash).await
+    }
+
+    async fn remove_ref(&self, name: &str) -> Result<()> {
+        self.inner.remove_ref(name).await
+    }
+
+    async fn list_refs(&self) -> Result<Vec<String>> {
+        self.inner.list_refs().await
+    }
+
+    async fn read_head(&self) -> Result<Option<String>> {
+        self.inner.read_head().await
+    }
+
+    async fn write_head(&self, name: &str) -> Result<()> {
+        self.inner.write_head(name).await
+    }
+
+    async fn lock(&self) -> Result<StoreGuard> {
+        self.inner.lock().await
+    }
+
+    async fn repack(&self) -> Result<usize> {
+        self.inner.repack().await
+    }
+
+    async fn remove_leftovers(&self, dry_run: bool) -> Result<u64> {
+        self.inner.remove_leftovers(dry_run).await
+    }
+
+    fn max_concurrency(&self) -> usize {
+        self.max_concurrency
+    }
+
+    fn hash_algorithm(&self) -> HashAlgorithm {
+        self.inner.hash_algorithm()
+    }
+
+    fn object_cache(&self) -> Option<&ObjectCache> {
+        self.inner.object_cache()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tokio::runtime::Runtime;
+    use histo_graph_core::graph::{
+        graph::{VertexId, Edge},
+        attributed_graph::AttributedGraph,
+    };
+
+    use super::*;
+    use crate::{
+        file_storage::{save_graph_as, load_graph},
+        store::{DEFAULT_MAX_CONCURRENCY, counting::CountingStore},
+    };
+
+    #[test]
+    fn test_concurrency_is_limited() -> Result<()> {
+        let mut rt = Runtime::new()?;
+        rt.block_on(async {
+            let mut graph = AttributedGraph::new();
+            for id in 0..200 {
+                graph.add_edge(Edge(VertexId(id), VertexId(id + 1)));
+            }
+
+            let store = CountingStore::default();
+            save_graph_as(&store, "main".to_string(), &graph).await?;
+            assert_eq!(load_graph(&store, "main".to_string()).await?, graph);
+            let max = store.max_concurrent();
+            assert!(max > 1 && max <= DEFAULT_MAX_CONCURRENCY, "{} objects at the same time", max);
+
+            // two graphs are loaded at the same time from a store that is limited
+            let store = LimitedStore::new(CountingStore::default(), 4);
+            save_graph_as(&store, "main".to_string(), &graph).await?;
+            let (graph_1, graph_2) = futures::future::try_join(
+                load_graph(&store, "main".to_string()),
+                load_graph(&store, "main".to_string()),
+            ).await?;
+            assert_eq!((graph_1, graph_2), (graph.clone(), graph));
+            assert!(store.inner.max_concurrent() <= 4);
+            Ok(())
+        })
+    }
+}
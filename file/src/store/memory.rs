@@ -0,0 +1,198 @@
+//! Implements an [`ObjectStore`] that holds the objects and references in memory.
+//!
+//! [`ObjectStore`]: ../trait.ObjectStore.html
+
+use async_trait::async_trait;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    Hash,
+    error::{Result, Error::{ConcurrentModification, ObjectNotFound, RefNotFound}},
+    store::{ObjectStore, StoreGuard, HashAlgorithm},
+};
+
+#[derive(Default)]
+struct Content {
+    objects: HashMap<(String, Hash), Vec<u8>>,
+    refs: BTreeMap<String, Hash>,
+    head: Option<String>,
+}
+
+/// Holds objects and references in memory. Clones of a `MemoryStore` share the same content.
+/// This is useful for tests, that do not need to touch the disk.
+#[derive(Clone, Default)]
+pub struct MemoryStore {
+    content: Arc<Mutex<Content>>,
+    lock: Arc<tokio::sync::Mutex<()>>,
+    hash_algorithm: HashAlgorithm,
+}
+
+impl MemoryStore {
+
+    /// Creates an empty store.
+    pub fn new() -> MemoryStore {
+        MemoryStore::default()
+    }
+
+    /// Addresses the objects of the store by the [`Hash`]es of `hash_algorithm`, instead of
+    /// SHA256. Clones that share the content keep the algorithm that they have been cloned with.
+    ///
+    /// [`Hash`]: ../../struct.Hash.html
+    pub fn with_hash_algorithm(self, hash_algorithm: HashAlgorithm) -> MemoryStore {
+        MemoryStore { hash_algorithm, ..self }
+    }
+
+    fn content(&self) -> std::sync::MutexGuard<'_, Content> {
+        // the content is never left in an inconsistent state, so a poisoned lock can be used
+        self.content.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[async_trait]
+impl ObjectStore for MemoryStore {
+    async fn put_object(&self, object_type: &str, hash: Hash, content: Vec<u8>) -> Result<()> {
+        self.content().objects.entry((object_type.to_string(), hash)).or_insert(content);
+        Ok(())
+    }
+
+    async fn get_object(&self, object_type: &str, hash: Hash) -> Result<Vec<u8>> {
+        self.content()
+            .objects
+            .get(&(object_type.to_string(), hash))
+            .cloned()
+            .ok_or_else(|| ObjectNotFound { object_type: object_type.to_string(), hash })
+    }
+
+    async fn contains_object(&self, object_type: &str, hash: Hash) -> Result<bool> {
+        Ok(self.content().objects.contains_key(&(object_type.to_string(), hash)))
+    }
+
+    async fn list_objects(&self, object_type: &str) -> Result<Vec<Hash>> {
+        Ok(self.content()
+            .objects
+            .keys()
+            .filter(|(t, _)| t == object_type)
+            .map(|&(_, hash)| hash)
+            .collect())
+    }
+
+    async fn remove_object(&self, object_type: &str, hash: Hash) -> Result<()> {
+        self.content()
+            .objects
+            .remove(&(object_type.to_string(), hash))
+            .map(|_| ())
+            .ok_or_else(|| ObjectNotFound { object_type: object_type.to_string(), hash })
+    }
+
+    async fn read_ref(&self, name: &str) -> Result<Option<Hash>> {
+        Ok(self.content().refs.get(name).cloned())
+    }
+
+    async fn write_ref(&self, name: &str, hash: Hash) -> Result<()> {
+        self.content().refs.insert(name.to_string(), hash);
+        Ok(())
+    }
+
+    async fn compare_and_swap_ref(&self, name: &str, expected: Option<Hash>, hash: Hash) -> Result<()> {
+        let mut content = self.content();
+        let actual = content.refs.get(name).cloned();
+        if actual != expected {
+            return Err(ConcurrentModification { name: name.to_string(), expected, actual });
+        }
+        content.refs.insert(name.to_string(), hash);
+        Ok(())
+    }
+
+    async fn remove_ref(&self, name: &str) -> Result<()> {
+        self.content()
+            .refs
+            .remove(name)
+            .map(|_| ())
+            .ok_or_else(|| RefNotFound { name: name.to_string() })
+    }
+
+    async fn list_refs(&self) -> Result<Vec<String>> {
+        Ok(self.content().refs.keys().cloned().collect())
+    }
+
+    async fn read_head(&self) -> Result<Option<String>> {
+        Ok(self.content().head.clone())
+    }
+
+    async fn write_head(&self, name: &str) -> Result<()> {
+        self.content().head = Some(name.to_string());
+        Ok(())
+    }
+
+    async fn lock(&self) -> Result<StoreGuard> {
+        Ok(StoreGuard::new(self.lock.clone().lock_owned().await))
+    }
+
+    fn hash_algorithm(&self) -> HashAlgorithm {
+        self.hash_algorithm
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tokio::runtime::Runtime;
+    use histo_graph_core::graph::{
+        graph::{VertexId, Edge},
+        attributed_graph::AttributedGraph,
+    };
+
+    use super::*;
+    use crate::{
+        file_storage::{save_graph_as, load_graph},
+        fsck::fsck,
+        object::{ObjectType, Commit},
+    };
+
+    #[test]
+    fn test_compare_and_swap_ref() -> Result<()> {
+        let mut rt = Runtime::new()?;
+        rt.block_on(async {
+            let store = MemoryStore::new();
+            let (hash_1, hash_2): (Hash, Hash) = (Hash::sha256(b"1"), Hash::sha256(b"2"));
+
+            store.compare_and_swap_ref("main", None, hash_1).await?;
+            assert!(store.compare_and_swap_ref("main", None, hash_2).await.is_err());
+            store.compare_and_swap_ref("main", Some(hash_1), hash_2).await?;
+
+            // an object that exists already is not overwritten
+            store.put_object("vertex", hash_1, b"first".to_vec()).await?;
+            store.put_object("vertex", hash_1, b"second".to_vec()).await?;
+            assert_eq!(store.get_object("vertex", hash_1).await?, b"first".to_vec());
+
+            assert_eq!(store.read_ref("main").await?, Some(hash_2));
+            assert_eq!(store.list_refs().await?, vec!["main".to_string()]);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_objects_are_addressed_by_the_hash_algorithm() -> Result<()> {
+        let mut rt = Runtime::new()?;
+        rt.block_on(async {
+            let mut graph = AttributedGraph::new();
+            graph.add_edge(Edge(VertexId(1), VertexId(2)));
+
+            let sha256 = MemoryStore::new();
+            let store = sha256.clone().with_hash_algorithm(HashAlgorithm::Blake3);
+            let commit_hash = save_graph_as(&store, "main".to_string(), &graph).await?;
+            assert_eq!(load_graph(&store, "main".to_string()).await?, graph);
+            assert!(fsck(&store).await?.is_ok());
+
+            let content = store.get_object(Commit::storage_name(), commit_hash).await?;
+            assert_eq!(HashAlgorithm::Blake3.hash(&content), commit_hash);
+
+            // the same content, read with SHA256, is corrupt
+            assert!(load_graph(&sha256, "main".to_string()).await.is_err());
+            assert!(!fsck(&sha256).await?.is_ok());
+            Ok(())
+        })
+    }
+}
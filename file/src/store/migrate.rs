@@ -0,0 +1,378 @@
+//! Upgrades stores of an older format to the [`CURRENT_FORMAT`], in place.
+//!
+//! Every change of the format of a store, e.g. of the layout of its directories, or of the
+//! serialization of its objects, increases the `CURRENT_FORMAT` by one, and adds a step to
+//! [`upgrade`], that upgrades a store of the previous format. A store is migrated by running the
+//! steps from its format up to the `CURRENT_FORMAT`, one after the other. The new format is
+//! recorded after each step, so that a migration that has been interrupted can be started again.
+//!
+//! [`CURRENT_FORMAT`]: ../config/constant.CURRENT_FORMAT.html
+//! [`upgrade`]: ./fn.upgrade.html
+
+use serde::{Serialize, Deserialize};
+use std::{
+    collections::HashMap,
+    path::Path,
+    time::UNIX_EPOCH,
+};
+use tokio::fs;
+
+use crate::{
+    Hash,
+    error::{Result, Error::{NotInitialized, VersionMismatch}},
+    file_storage::{read_object, read_ref, write_object},
+    lock::FileLock,
+    object::{ObjectType, StoredVertex, StoredAttributes, HashEdge, HashVec, GraphHash, Commit},
+    store::{
+        ObjectStore, DirectoryStore, Layout, detect_config, open_unchecked,
+        config::{Backend, StoreConfig, CURRENT_FORMAT, write_config},
+        directory::list_files,
+    },
+    tree::write_tree,
+};
+
+/// An edge of the format 0, that is stored without attributes.
+#[derive(Serialize, Deserialize)]
+struct PlainEdge {
+    from: Hash,
+    to: Hash,
+}
+
+/// Converts the vertex with the [`Hash`] `hash`, that is stored as its id, to a [`StoredVertex`]
+/// without attributes, unless it has been `converted` already. Returns the `Hash` of the
+/// converted vertex.
+///
+/// [`Hash`]: ../../struct.Hash.html
+/// [`StoredVertex`]: ../../object/struct.StoredVertex.html
+async fn convert_vertex(store: &DirectoryStore, hash: Hash, converted: &mut HashMap<Hash, Hash>) -> Result<Hash> {
+    if let Some(&vertex_hash) = converted.get(&hash) {
+        return Ok(vertex_hash);
+    }
+    let id: u64 = bincode::deserialize(&store.get_object(StoredVertex::storage_name(), hash).await?)?;
+    let vertex_hash = write_object(store, &StoredVertex { id, attributes: StoredAttributes::new() }).await?;
+    converted.insert(hash, vertex_hash);
+    Ok(vertex_hash)
+}
+
+/// Converts the graph of the format 0 that is stored under the name `name`, to a [`Commit`] of the
+/// graph with [`HashVec`]s of its vertices and of its edges, without attributes. The commit has no
+/// parents, and the time when the graph has been saved. Returns the [`Hash`] of the commit.
+///
+/// [`Commit`]: ../../object/struct.Commit.html
+/// [`HashVec`]: ../../object/struct.HashVec.html
+/// [`Hash`]: ../../struct.Hash.html
+async fn convert_named_graph(store: &DirectoryStore, name: &str) -> Result<Hash> {
+    let path = store.base_path().join(GraphHash::storage_name()).join(name);
+    // the graph of the format 0 refers to the `HashVec`s by two hashes as well
+    let graph: GraphHash = bincode::deserialize(&fs::read(&path).await?)?;
+    let timestamp = fs::metadata(&path)
+        .await?
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+
+    let mut converted: HashMap<Hash, Hash> = HashMap::new();
+    let vertex_vec: HashVec<StoredVertex> = read_object(store, graph.vertex_tree_hash).await?;
+    let mut vertex_hashes: Vec<Hash> = Vec::new();
+    for hash in vertex_vec.0 {
+        vertex_hashes.push(convert_vertex(store, hash, &mut converted).await?);
+    }
+
+    let edge_vec: HashVec<HashEdge> = read_object(store, graph.edge_tree_hash).await?;
+    let mut edge_hashes: Vec<Hash> = Vec::new();
+    for hash in edge_vec.0 {
+        let PlainEdge { from, to } = bincode::deserialize(&store.get_object(HashEdge::storage_name(), hash).await?)?;
+        let edge = HashEdge {
+            from: convert_vertex(store, from, &mut converted).await?,
+            to: convert_vertex(store, to, &mut converted).await?,
+            attributes: StoredAttributes::new(),
+        };
+        edge_hashes.push(write_object(store, &edge).await?);
+    }
+
+    let graph = GraphHash {
+        vertex_tree_hash: write_object(store, &HashVec::<StoredVertex>::new(vertex_hashes)).await?,
+        edge_tree_hash: write_object(store, &HashVec::<HashEdge>::new(edge_hashes)).await?,
+    };
+    let graph_hash = write_object(store, &graph).await?;
+    let commit = Commit {
+        parents: Vec::new(),
+        graph_hash,
+        timestamp,
+        author: String::new(),
+        message: format!("Migrate the graph '{}'", name),
+    };
+    write_object(store, &commit).await
+}
+
+/// Converts all graphs of the format 0, and refers to each converted graph by a reference with the
+/// name of the graph. The file that held a graph under its name is removed once the reference has
+/// been written, so that a migration that has been interrupted converts only the remaining graphs.
+/// The objects of the former graphs are left to the garbage collection.
+async fn convert_named_graphs(base_path: &Path) -> Result<()> {
+    let store = DirectoryStore::with_layout(base_path, Layout::Flat);
+    let names = list_files(base_path.join(GraphHash::storage_name())).await?;
+    // the converted graphs are stored in the same directory, under their hashes
+    for name in names.iter().filter(|name| Hash::from_hex(name).is_none()) {
+        let commit_hash = convert_named_graph(&store, name).await?;
+        store.write_ref(name, commit_hash).await?;
+        fs::remove_file(base_path.join(GraphHash::storage_name()).join(name)).await?;
+    }
+    Ok(())
+}
+
+/// Converts the graph with the [`Hash`] `graph_hash`, that refers to a [`HashVec`] of its vertices
+/// and of its edges, to a graph that refers to a tree of its vertices and of its edges. Returns
+/// the `Hash` of the converted graph, or `None` if the graph has already been converted by a
+/// migration that has been interrupted.
+///
+/// [`Hash`]: ../../struct.Hash.html
+/// [`HashVec`]: ../../object/struct.HashVec.html
+async fn convert_graph(store: &dyn ObjectStore, graph_hash: Hash) -> Result<Option<Hash>> {
+    // a graph of the format 2 consists of two hashes as well, so it is read as a `GraphHash`
+    let graph: GraphHash = read_object(store, graph_hash).await?;
+    if !store.contains_object(HashVec::<StoredVertex>::storage_name(), graph.vertex_tree_hash).await? {
+        return Ok(None);
+    }
+
+    let vertex_vec: HashVec<StoredVertex> = read_object(store, graph.vertex_tree_hash).await?;
+    let edge_vec: HashVec<HashEdge> = read_object(store, graph.edge_tree_hash).await?;
+    let graph = GraphHash {
+        vertex_tree_hash: write_tree::<_, StoredVertex>(store, vertex_vec.0).await?,
+        edge_tree_hash: write_tree::<_, HashEdge>(store, edge_vec.0).await?,
+    };
+    Ok(Some(write_object(store, &graph).await?))
+}
+
+/// Converts the commit with the [`Hash`] `commit_hash`, and all its ancestors, that have not been
+/// `converted` yet. A converted commit refers to the converted graph and the converted parents.
+/// Returns the `Hash` of the converted commit.
+///
+/// [`Hash`]: ../../struct.Hash.html
+async fn convert_history(store: &dyn ObjectStore, commit_hash: Hash, converted: &mut HashMap<Hash, Hash>) -> Result<Hash> {
+    let mut pending: Vec<Hash> = vec![commit_hash];
+
+    while let Some(&hash) = pending.last() {
+        if converted.contains_key(&hash) {
+            pending.pop();
+            continue;
+        }
+
+        // the parents are converted before the commit
+        let commit: Commit = read_object(store, hash).await?;
+        let parents: Vec<Hash> = commit.parents.iter().filter(|parent| !converted.contains_key(parent)).cloned().collect();
+        if !parents.is_empty() {
+            pending.extend(parents);
+            continue;
+        }
+
+        let new_hash = match convert_graph(store, commit.graph_hash).await? {
+            Some(graph_hash) => {
+                let parents = commit.parents.iter().map(|parent| converted[parent]).collect();
+                write_object(store, &Commit { parents, graph_hash, ..commit }).await?
+            }
+            None => hash,
+        };
+        converted.insert(hash, new_hash);
+        pending.pop();
+    }
+
+    Ok(converted[&commit_hash])
+}
+
+/// Converts the histories of all named references, and updates the references to the converted
+/// commits. The objects of the former histories are left to the garbage collection.
+async fn convert_histories(store: &dyn ObjectStore) -> Result<()> {
+    let mut converted: HashMap<Hash, Hash> = HashMap::new();
+    for name in store.list_refs().await? {
+        let commit_hash = read_ref(store, &name).await?;
+        let converted_hash = convert_history(store, commit_hash, &mut converted).await?;
+        if converted_hash != commit_hash {
+            store.write_ref(&name, converted_hash).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Upgrades the store under `base_path`, with the configuration `config`, from the format
+/// `config.format` to the next format.
+async fn upgrade(base_path: &Path, config: &StoreConfig) -> Result<()> {
+    match (config.format, config.backend) {
+        // 0 to 1: the graphs that are stored under their names are converted to commits, that
+        // references refer to, and their vertices and edges are stored with attributes
+        (0, Backend::Directory) => convert_named_graphs(base_path).await,
+
+        // 1 to 2: the files of the objects are moved into sharded directories
+        (1, Backend::Directory) => {
+            DirectoryStore::new(base_path).shard().await?;
+            Ok(())
+        }
+        (1, Backend::Database) => Ok(()),
+
+        // 2 to 3: the vectors of the vertices and of the edges are replaced by trees of chunks
+        (2, _) => {
+            let store = open_unchecked(base_path, config.clone())?;
+            convert_histories(&*store).await
+        }
+
+        (format, _) => Err(VersionMismatch { found: format, supported: CURRENT_FORMAT }),
+    }
+}
+
+/// Upgrades the store under the directory `base_path` to the [`CURRENT_FORMAT`], in place.
+/// Returns the format that the store had before, which is the `CURRENT_FORMAT`, if the store is
+/// up to date. Fails with [`NotInitialized`], if there is no store, with [`UnknownStore`], if the
+/// directory holds something else, and with [`VersionMismatch`], if the store has been written by
+/// a newer version.
+///
+/// The store is locked while it is upgraded, so that it is not modified concurrently.
+///
+/// [`CURRENT_FORMAT`]: ../config/constant.CURRENT_FORMAT.html
+/// [`NotInitialized`]: ../../error/enum.Error.html#variant.NotInitialized
+/// [`UnknownStore`]: ../../error/enum.Error.html#variant.UnknownStore
+/// [`VersionMismatch`]: ../../error/enum.Error.html#variant.VersionMismatch
+pub async fn migrate_store<P>(base_path: P) -> Result<u32>
+    where P: AsRef<Path>
+{
+    let base_path = base_path.as_ref();
+    let mut config = match detect_config(base_path)? {
+        Some(config) => config,
+        None => return Err(NotInitialized { path: base_path.to_path_buf() }),
+    };
+    let format = config.format;
+    if format > CURRENT_FORMAT {
+        return Err(VersionMismatch { found: format, supported: CURRENT_FORMAT });
+    }
+    if format == CURRENT_FORMAT {
+        return Ok(format);
+    }
+
+    let _lock = FileLock::acquire(base_path.join("lock")).await?;
+    // the format is recorded before the first step, so that a store whose format is detected by
+    // its content is still recognised, if the step is interrupted
+    write_config(base_path, &config)?;
+    while config.format < CURRENT_FORMAT {
+        upgrade(base_path, &config).await?;
+        config.format += 1;
+        write_config(base_path, &config)?;
+    }
+
+    Ok(format)
+}
+
+#[cfg(test)]
+mod test {
+    use tokio::runtime::Runtime;
+    use histo_graph_core::graph::{
+        graph::{VertexId, Edge},
+        attributed_graph::AttributedGraph,
+    };
+
+    use super::*;
+    use crate::{
+        branch::DEFAULT_BRANCH,
+        error::Error::UnknownStore,
+        file_storage::load_graph,
+        fsck::fsck,
+        gc::collect_garbage,
+        store::{create_store, open_store},
+    };
+
+    /// Writes `content` to a file of the type `object_type`, that is named by its SHA256 hash, as
+    /// the first version did. Returns the hash.
+    async fn write_file_of_format_0(base_path: &Path, object_type: &str, content: Vec<u8>) -> Result<Hash> {
+        let hash = Hash::sha256(&content);
+        let dir = base_path.join(object_type);
+        fs::create_dir_all(&dir).await?;
+        fs::write(dir.join(hash.to_string()), content).await?;
+        Ok(hash)
+    }
+
+    /// Saves `graph` under the name `name`, as the first version did: the vertices as their ids,
+    /// the edges as the hashes of their vertices, a vector of the hashes of the vertices and one of
+    /// the edges, and the hashes of both vectors in the file `graph/<name>`.
+    async fn save_graph_of_format_0(base_path: &Path, name: &str, graph: &AttributedGraph) -> Result<()> {
+        let mut vertex_hashes: Vec<Hash> = Vec::new();
+        for vertex_id in graph.vertices() {
+            vertex_hashes.push(write_file_of_format_0(base_path, "vertex", bincode::serialize(&vertex_id.0)?).await?);
+        }
+        let mut edge_hashes: Vec<Hash> = Vec::new();
+        for edge in graph.edges() {
+            let edge = PlainEdge {
+                from: Hash::sha256(bincode::serialize(&(edge.0).0)?),
+                to: Hash::sha256(bincode::serialize(&(edge.1).0)?),
+            };
+            edge_hashes.push(write_file_of_format_0(base_path, "edge", bincode::serialize(&edge)?).await?);
+        }
+
+        let vertex_vec_hash = write_file_of_format_0(base_path, "vertexvec", bincode::serialize(&vertex_hashes)?).await?;
+        let edge_vec_hash = write_file_of_format_0(base_path, "edgevec", bincode::serialize(&edge_hashes)?).await?;
+        fs::create_dir_all(base_path.join("graph")).await?;
+        fs::write(base_path.join("graph").join(name), bincode::serialize(&(vertex_vec_hash, edge_vec_hash))?).await?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrate_original_store() -> Result<()> {
+        let mut rt = Runtime::new()?;
+        rt.block_on(async {
+            let base_path = Path::new("../target/test/original_store/");
+            let _ = fs::remove_dir_all(base_path).await;
+
+            // a store of the first version, that has neither references, nor a configuration
+            let mut graph = AttributedGraph::new();
+            graph.add_edge(Edge(VertexId(27), VertexId(2)));
+            graph.add_edge(Edge(VertexId(2), VertexId(3)));
+            graph.add_vertex(VertexId(4));
+            save_graph_of_format_0(base_path, DEFAULT_BRANCH, &graph).await?;
+            let mut other = AttributedGraph::new();
+            other.add_vertex(VertexId(27));
+            save_graph_of_format_0(base_path, "other", &other).await?;
+            // the vertex 27 has the hash that the first version computed
+            assert!(base_path.join("vertex/4d159113222bfeb85fbe717cc2393ee8a6a85b7ce5ac1791c4eade5e3dd6de41").exists());
+
+            assert!(matches!(open_store(base_path), Err(VersionMismatch { found: 0, .. })));
+            assert!(matches!(create_store(base_path, StoreConfig::default()), Err(VersionMismatch { found: 0, .. })));
+            assert!(!base_path.join("config").exists());
+
+            assert_eq!(migrate_store(base_path).await?, 0);
+            assert_eq!(migrate_store(base_path).await?, CURRENT_FORMAT);
+            assert!(!base_path.join("graph").join(DEFAULT_BRANCH).exists());
+
+            // every graph has become a commit without parents, that its name refers to
+            let store = open_store(base_path)?;
+            assert_eq!(load_graph(&*store, DEFAULT_BRANCH.to_string()).await?, graph);
+            assert_eq!(load_graph(&*store, "other".to_string()).await?, other);
+            let commit: Commit = read_object(&*store, read_ref(&*store, "other").await?).await?;
+            assert!(commit.parents.is_empty());
+            assert!(commit.timestamp > 0);
+            assert_eq!(commit.message, "Migrate the graph 'other'");
+
+            // the former objects are left to the garbage collection
+            assert!(!fsck(&*store).await?.is_ok());
+            collect_garbage(&*store, false).await?;
+            assert!(fsck(&*store).await?.is_ok());
+            assert_eq!(load_graph(&*store, DEFAULT_BRANCH.to_string()).await?, graph);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_migrate_unknown_store() -> Result<()> {
+        let mut rt = Runtime::new()?;
+        rt.block_on(async {
+            let base_path = Path::new("../target/test/unknown_store/");
+            let _ = fs::remove_dir_all(base_path).await;
+            assert!(matches!(migrate_store(base_path).await, Err(NotInitialized { .. })));
+
+            fs::create_dir_all(base_path).await?;
+            fs::write(base_path.join("notes.txt"), b"not a store").await?;
+            assert!(matches!(migrate_store(base_path).await, Err(UnknownStore { .. })));
+            assert!(matches!(create_store(base_path, StoreConfig::default()), Err(UnknownStore { .. })));
+            assert!(matches!(open_store(base_path), Err(UnknownStore { .. })));
+            assert!(!base_path.join("config").exists());
+            Ok(())
+        })
+    }
+}
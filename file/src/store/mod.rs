@@ -0,0 +1,446 @@
+//! Defines the trait [`ObjectStore`], that abstracts the storage of objects and named references,
+//! and provides three implementations of it: the [`DirectoryStore`], that stores objects as files
+//! in a directory, the [`DatabaseStore`], that keeps them in a single embedded database, and the
+//! [`MemoryStore`], that holds them in memory.
+//!
+//! The content of the objects can be compressed by wrapping a store in a [`CompressedStore`]. The
+//! number of concurrent reads and writes of objects is limited by wrapping a store in a
+//! [`LimitedStore`].
+//!
+//! A store on disk is created with [`create_store`], which selects the [`Backend`] and the
+//! [`Compression`], and opened with [`open_store`], which uses the [`StoreConfig`] it has been
+//! created with. Stores of an older format are upgraded with [`migrate_store`].
+//!
+//! [`ObjectStore`]: ./trait.ObjectStore.html
+//! [`DirectoryStore`]: ./directory/struct.DirectoryStore.html
+//! [`DatabaseStore`]: ./database/struct.DatabaseStore.html
+//! [`MemoryStore`]: ./memory/struct.MemoryStore.html
+//! [`create_store`]: ./fn.create_store.html
+//! [`open_store`]: ./fn.open_store.html
+//! [`CompressedStore`]: ./compressed/struct.CompressedStore.html
+//! [`LimitedStore`]: ./limited/struct.LimitedStore.html
+//! [`Backend`]: ./config/enum.Backend.html
+//! [`Compression`]: ./config/enum.Compression.html
+//! [`StoreConfig`]: ./config/struct.StoreConfig.html
+//! [`migrate_store`]: ./migrate/fn.migrate_store.html
+
+use async_trait::async_trait;
+use std::{
+    any::Any,
+    fmt,
+    fs,
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    Hash,
+    error::{Result, Error::{NotInitialized, ConfigMismatch, UnknownStore}},
+    object::{ObjectType, GraphHash},
+};
+
+pub mod directory;
+pub mod database;
+pub mod memory;
+pub mod compressed;
+pub mod limited;
+pub mod cached;
+pub mod config;
+pub mod migrate;
+mod pack;
+#[cfg(test)]
+pub(crate) mod counting;
+
+pub use directory::{DirectoryStore, Layout};
+pub use database::DatabaseStore;
+pub use memory::MemoryStore;
+pub use compressed::CompressedStore;
+pub use limited::LimitedStore;
+pub use cached::{CachedStore, ObjectCache, CacheStats, DEFAULT_CACHE_CAPACITY};
+pub use config::{StoreConfig, Backend, HashAlgorithm, Serialization, Compression, CURRENT_FORMAT};
+pub use migrate::migrate_store;
+use config::{read_config, write_config};
+
+/// Identifies a stored object by its type and its [`Hash`].
+///
+/// [`Hash`]: ../struct.Hash.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ObjectId {
+
+    /// The name of the type of the object, like `vertex` or `commit`.
+    pub object_type: &'static str,
+
+    pub hash: Hash,
+}
+
+impl fmt::Display for ObjectId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.object_type, self.hash)
+    }
+}
+
+/// Keeps a store locked until it is dropped. See [`ObjectStore::lock`].
+///
+/// [`ObjectStore::lock`]: ./trait.ObjectStore.html#tymethod.lock
+pub struct StoreGuard {
+    _guard: Box<dyn Any + Send + Sync>,
+}
+
+impl StoreGuard {
+
+    /// Wraps the value that keeps a store locked, as long as it exists.
+    pub fn new<G>(guard: G) -> StoreGuard
+        where G: Any + Send + Sync
+    {
+        StoreGuard {
+            _guard: Box::new(guard),
+        }
+    }
+}
+
+/// The number of objects that the functions of this crate read or write at the same time, unless
+/// the store limits it otherwise. Reading or writing all objects of a large graph at once would
+/// exceed the number of files that a process may open.
+pub const DEFAULT_MAX_CONCURRENCY: usize = 64;
+
+/// A storage of objects and named references.
+///
+/// Objects are identified by the name of their type and the [`Hash`] of their content, and are
+/// never modified. Named references refer to an object by its `Hash`, and can be moved. In
+/// addition, the store holds the name of the current branch, the `HEAD`.
+///
+/// [`Hash`]: ../struct.Hash.html
+#[async_trait]
+pub trait ObjectStore: Send + Sync {
+
+    /// Stores the `content` of the object of type `object_type` with the [`Hash`] `hash`.
+    /// Storing an object that already exists has no effect.
+    ///
+    /// [`Hash`]: ../struct.Hash.html
+    async fn put_object(&self, object_type: &str, hash: Hash, content: Vec<u8>) -> Result<()>;
+
+    /// Returns the content of the object of type `object_type` with the [`Hash`] `hash`.
+    /// Fails with [`ObjectNotFound`], if there is no such object.
+    ///
+    /// [`Hash`]: ../struct.Hash.html
+    /// [`ObjectNotFound`]: ../error/enum.Error.html#variant.ObjectNotFound
+    async fn get_object(&self, object_type: &str, hash: Hash) -> Result<Vec<u8>>;
+
+    /// Returns true if the object of type `object_type` with the [`Hash`] `hash` exists.
+    ///
+    /// [`Hash`]: ../struct.Hash.html
+    async fn contains_object(&self, object_type: &str, hash: Hash) -> Result<bool>;
+
+    /// Returns the number of bytes that the object of type `object_type` with the [`Hash`] `hash`
+    /// takes in the store, e.g. after compression. Fails with [`ObjectNotFound`], if there is no
+    /// such object. Stores that can tell the size without reading the content override this.
+    ///
+    /// [`Hash`]: ../struct.Hash.html
+    /// [`ObjectNotFound`]: ../error/enum.Error.html#variant.ObjectNotFound
+    async fn object_size(&self, object_type: &str, hash: Hash) -> Result<u64> {
+        Ok(self.get_object(object_type, hash).await?.len() as u64)
+    }
+
+    /// Returns the [`Hash`]es of all objects of type `object_type`.
+    ///
+    /// [`Hash`]: ../struct.Hash.html
+    async fn list_objects(&self, object_type: &str) -> Result<Vec<Hash>>;
+
+    /// Removes the object of type `object_type` with the [`Hash`] `hash`. Fails with
+    /// [`ObjectNotFound`], if there is no such object.
+    ///
+    /// [`Hash`]: ../struct.Hash.html
+    /// [`ObjectNotFound`]: ../error/enum.Error.html#variant.ObjectNotFound
+    async fn remove_object(&self, object_type: &str, hash: Hash) -> Result<()>;
+
+    /// Returns the [`Hash`] that the reference `name` refers to, or `None` if there is no
+    /// reference with that name.
+    ///
+    /// [`Hash`]: ../struct.Hash.html
+    async fn read_ref(&self, name: &str) -> Result<Option<Hash>>;
+
+    /// Sets the reference `name` to refer to `hash`.
+    async fn write_ref(&self, name: &str, hash: Hash) -> Result<()>;
+
+    /// Sets the reference `name` to refer to `hash`, but only if it currently refers to
+    /// `expected`. `None` means that the reference must not exist.
+    /// Fails with [`ConcurrentModification`] otherwise. The comparison and the update are atomic.
+    ///
+    /// [`ConcurrentModification`]: ../error/enum.Error.html#variant.ConcurrentModification
+    async fn compare_and_swap_ref(&self, name: &str, expected: Option<Hash>, hash: Hash) -> Result<()>;
+
+    /// Removes the `objects`. Stores that can remove many objects at once faster than one by one
+    /// override this.
+    async fn remove_objects(&self, objects: &[ObjectId]) -> Result<()> {
+        for id in objects {
+            self.remove_object(id.object_type, id.hash).await?;
+        }
+        Ok(())
+    }
+
+    /// Removes the reference `name`. Fails with [`RefNotFound`], if there is no such reference.
+    ///
+    /// [`RefNotFound`]: ../error/enum.Error.html#variant.RefNotFound
+    async fn remove_ref(&self, name: &str) -> Result<()>;
+
+    /// Returns the names of all references, in sorted order.
+    async fn list_refs(&self) -> Result<Vec<String>>;
+
+    /// Returns the name of the current branch, or `None` if it has never been set.
+    async fn read_head(&self) -> Result<Option<String>>;
+
+    /// Sets the name of the current branch.
+    async fn write_head(&self, name: &str) -> Result<()>;
+
+    /// Locks the store for modifications that consist of several calls, like writing the objects
+    /// of a commit and moving a reference to it, so that a garbage collection does not remove
+    /// objects that are not yet referenced. The lock is held until the returned [`StoreGuard`] is
+    /// dropped. The lock is not reentrant.
+    ///
+    /// [`StoreGuard`]: ./struct.StoreGuard.html
+    async fn lock(&self) -> Result<StoreGuard>;
+
+    /// Bundles objects that are stored separately, e.g. one file per object, so that they take
+    /// less space and are faster to read, and returns the number of objects that have been
+    /// bundled. Takes the [`lock`] of the store. Stores that do not store objects separately have
+    /// nothing to bundle.
+    ///
+    /// [`lock`]: ./trait.ObjectStore.html#tymethod.lock
+    async fn repack(&self) -> Result<usize> {
+        Ok(0)
+    }
+
+    /// Removes what is left over from writes that have been interrupted, and returns the number of
+    /// bytes that are freed. If `dry_run` is true, nothing is removed, and the number of bytes that
+    /// would be freed is returned.
+    async fn remove_leftovers(&self, _dry_run: bool) -> Result<u64> {
+        Ok(0)
+    }
+
+    /// The number of objects that the functions of this crate read or write at the same time,
+    /// when they read or write many objects, e.g. the vertices of a graph.
+    fn max_concurrency(&self) -> usize {
+        DEFAULT_MAX_CONCURRENCY
+    }
+
+    /// The algorithm that computes the [`Hash`]es of the objects of the store, which is SHA256,
+    /// unless the store has been created with another one, e.g. by
+    /// [`DirectoryStore::with_hash_algorithm`]. Stores that wrap another store return its
+    /// algorithm.
+    ///
+    /// [`Hash`]: ../struct.Hash.html
+    /// [`DirectoryStore::with_hash_algorithm`]: ./directory/struct.DirectoryStore.html#method.with_hash_algorithm
+    fn hash_algorithm(&self) -> HashAlgorithm {
+        HashAlgorithm::Sha256
+    }
+
+    /// The cache of decoded objects, that the functions of this crate read objects through, if
+    /// the store has one, e.g. a [`CachedStore`].
+    ///
+    /// [`CachedStore`]: ./cached/struct.CachedStore.html
+    fn object_cache(&self) -> Option<&ObjectCache> {
+        None
+    }
+}
+
+/// A boxed store, e.g. as returned by [`open_store`], can be wrapped by another store.
+///
+/// [`open_store`]: ./fn.open_store.html
+#[async_trait]
+impl<S> ObjectStore for Box<S>
+    where S: ObjectStore + ?Sized
+{
+    async fn put_object(&self, object_type: &str, hash: Hash, content: Vec<u8>) -> Result<()> {
+        (**self).put_object(object_type, hash, content).await
+    }
+
+    async fn get_object(&self, object_type: &str, hash: Hash) -> Result<Vec<u8>> {
+        (**self).get_object(object_type, hash).await
+    }
+
+    async fn contains_object(&self, object_type: &str, hash: Hash) -> Result<bool> {
+        (**self).contains_object(object_type, hash).await
+    }
+
+    async fn object_size(&self, object_type: &str, hash: Hash) -> Result<u64> {
+        (**self).object_size(object_type, hash).await
+    }
+
+    async fn list_objects(&self, object_type: &str) -> Result<Vec<Hash>> {
+        (**self).list_objects(object_type).await
+    }
+
+    async fn remove_object(&self, object_type: &str, hash: Hash) -> Result<()> {
+        (**self).remove_object(object_type, hash).await
+    }
+
+    async fn remove_objects(&self, objects: &[ObjectId]) -> Result<()> {
+        (**self).remove_objects(objects).await
+    }
+
+    async fn read_ref(&self, name: &str) -> Result<Option<Hash>> {
+        (**self).read_ref(name).await
+    }
+
+    async fn write_ref(&self, name: &str, hash: Hash) -> Result<()> {
+        (**self).write_ref(name, hash).await
+    }
+
+    async fn compare_and_swap_ref(&self, name: &str, expected: Option<Hash>, hash: Hash) -> Result<()> {
+        (**self).compare_and_swap_ref(name, expected, hash).await
+    }
+
+    async fn remove_ref(&self, name: &str) -> Result<()> {
+        (**self).remove_ref(name).await
+    }
+
+    async fn list_refs(&self) -> Result<Vec<String>> {
+        (**self).list_refs().await
+    }
+
+    async fn read_head(&self) -> Result<Option<String>> {
+        (**self).read_head().await
+    }
+
+    async fn write_head(&self, name: &str) -> Result<()> {
+        (**self).write_head(name).await
+    }
+
+    async fn lock(&self) -> Result<StoreGuard> {
+        (**self).lock().await
+    }
+
+    async fn repack(&self) -> Result<usize> {
+        (**self).repack().await
+    }
+
+    async fn remove_leftovers(&self, dry_run: bool) -> Result<u64> {
+        (**self).remove_leftovers(dry_run).await
+    }
+
+    fn max_concurrency(&self) -> usize {
+        (**self).max_concurrency()
+    }
+
+    fn hash_algorithm(&self) -> HashAlgorithm {
+        (**self).hash_algorithm()
+    }
+
+    fn object_cache(&self) -> Option<&ObjectCache> {
+        (**self).object_cache()
+    }
+}
+
+/// The database of a store with the backend [`Backend::Database`], within the storage directory.
+///
+/// [`Backend::Database`]: ./config/enum.Backend.html#variant.Database
+fn database_path(base_path: &Path) -> PathBuf {
+    base_path.join("db")
+}
+
+/// Returns the configuration of the store under `base_path`, or `None` if the directory does not
+/// exist or is empty. Stores that have been created before the configuration has been recorded are
+/// detected by their content: the first version stored graphs under their names in `graph/`, and
+/// had no references. Fails with [`UnknownStore`], if the directory holds anything else.
+///
+/// [`UnknownStore`]: ../error/enum.Error.html#variant.UnknownStore
+fn detect_config(base_path: &Path) -> Result<Option<StoreConfig>> {
+    if let Some(config) = read_config(base_path)? {
+        Ok(Some(config))
+    } else if database_path(base_path).exists() {
+        Ok(Some(StoreConfig::initial(Backend::Database)))
+    } else if base_path.join("refs").exists() {
+        Ok(Some(StoreConfig::initial(Backend::Directory)))
+    } else if base_path.join(GraphHash::storage_name()).is_dir() {
+        Ok(Some(StoreConfig::original()))
+    } else if is_empty_dir(base_path)? {
+        Ok(None)
+    } else {
+        Err(UnknownStore { path: base_path.to_path_buf() })
+    }
+}
+
+/// Returns true if the directory `path` does not exist, or is empty.
+fn is_empty_dir(path: &Path) -> Result<bool> {
+    match fs::read_dir(path) {
+        Ok(mut entries) => Ok(entries.next().is_none()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(true),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Creates a store with the configuration `config` under the directory `base_path`, in the
+/// [`CURRENT_FORMAT`], or opens it, if it already exists. Fails with [`ConfigMismatch`], if there
+/// is a store with a different configuration, with [`VersionMismatch`], if there is a store of
+/// a different format, and with [`UnknownStore`], if the directory holds anything else.
+///
+/// [`ConfigMismatch`]: ../error/enum.Error.html#variant.ConfigMismatch
+/// [`UnknownStore`]: ../error/enum.Error.html#variant.UnknownStore
+/// [`VersionMismatch`]: ../error/enum.Error.html#variant.VersionMismatch
+/// [`CURRENT_FORMAT`]: ./config/constant.CURRENT_FORMAT.html
+pub fn create_store<P>(base_path: P, config: StoreConfig) -> Result<Box<dyn ObjectStore>>
+    where P: AsRef<Path>
+{
+    let base_path = base_path.as_ref();
+    match detect_config(base_path)? {
+        Some(existing) if !existing.is_compatible(&config) => Err(ConfigMismatch { path: base_path.to_path_buf(), existing }),
+        Some(existing) => open_with_config(base_path, existing),
+        None => {
+            let config = StoreConfig { format: CURRENT_FORMAT, ..config };
+            write_config(base_path, &config)?;
+            open_with_config(base_path, config)
+        }
+    }
+}
+
+/// Opens the store under the directory `base_path`, with the configuration that it has been
+/// created with. Fails with [`NotInitialized`], if there is no store, with [`UnknownStore`], if
+/// the directory holds something else, and with [`VersionMismatch`], if the store has not been
+/// written in the [`CURRENT_FORMAT`].
+///
+/// [`NotInitialized`]: ../error/enum.Error.html#variant.NotInitialized
+/// [`UnknownStore`]: ../error/enum.Error.html#variant.UnknownStore
+/// [`VersionMismatch`]: ../error/enum.Error.html#variant.VersionMismatch
+/// [`CURRENT_FORMAT`]: ./config/constant.CURRENT_FORMAT.html
+pub fn open_store<P>(base_path: P) -> Result<Box<dyn ObjectStore>>
+    where P: AsRef<Path>
+{
+    let base_path = base_path.as_ref();
+    match detect_config(base_path)? {
+        Some(config) => open_with_config(base_path, config),
+        None => Err(NotInitialized { path: base_path.to_path_buf() }),
+    }
+}
+
+/// Reads the configuration of the store under the directory `base_path`, e.g. to find the
+/// default author of the versions that are saved into it. Fails with [`NotInitialized`], if there
+/// is no store.
+///
+/// [`NotInitialized`]: ../error/enum.Error.html#variant.NotInitialized
+pub fn read_store_config<P>(base_path: P) -> Result<StoreConfig>
+    where P: AsRef<Path>
+{
+    let base_path = base_path.as_ref();
+    detect_config(base_path)?.ok_or_else(|| NotInitialized { path: base_path.to_path_buf() })
+}
+
+fn open_with_config(base_path: &Path, config: StoreConfig) -> Result<Box<dyn ObjectStore>> {
+    config.check_format()?;
+    open_unchecked(base_path, config)
+}
+
+/// Opens the store under `base_path` with the configuration `config`, whatever its format is.
+/// This is only used by the migration, that reads and writes stores of older formats.
+fn open_unchecked(base_path: &Path, config: StoreConfig) -> Result<Box<dyn ObjectStore>> {
+    let hash_algorithm = config.hash_algorithm;
+    Ok(match (config.backend, config.compression) {
+        (Backend::Directory, Compression::None) =>
+            Box::new(DirectoryStore::new(base_path).with_hash_algorithm(hash_algorithm)),
+        (Backend::Directory, compression) =>
+            Box::new(CompressedStore::new(DirectoryStore::new(base_path).with_hash_algorithm(hash_algorithm), compression)),
+        (Backend::Database, Compression::None) =>
+            Box::new(DatabaseStore::open(database_path(base_path))?.with_hash_algorithm(hash_algorithm)),
+        (Backend::Database, compression) =>
+            Box::new(CompressedStore::new(DatabaseStore::open(database_path(base_path))?.with_hash_algorithm(hash_algorithm), compression)),
+    })
+}
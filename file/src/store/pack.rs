@@ -0,0 +1,143 @@
+//! Implements pack files, that bundle many objects of a [`DirectoryStore`] in a single file.
+//!
+//! A pack consists of two files in the sub-directory `pack/` of the storage directory:
+//! `<name>.pack` holds the contents of the objects, one after the other, and `<name>.idx` holds
+//! the index, that maps the type and the [`Hash`] of every object to the offset and the length of
+//! its content in the pack. The name of a pack is the `Hash` of its index. The index is written
+//! after the pack, so that every index refers to a complete pack.
+//!
+//! [`DirectoryStore`]: ../directory/struct.DirectoryStore.html
+//! [`Hash`]: ../../struct.Hash.html
+
+use serde::{Serialize, Deserialize};
+use std::{
+    collections::HashMap,
+    io::SeekFrom,
+    path::{Path, PathBuf},
+};
+use tokio::{
+    fs,
+    io::AsyncReadExt,
+};
+
+use crate::{
+    Hash,
+    error::Result,
+    store::directory::{create_dir_and_write_atomically, list_files},
+};
+
+const PACK_SUFFIX: &str = ".pack";
+const INDEX_SUFFIX: &str = ".idx";
+
+/// Identifies an object by the name of its type and its [`Hash`].
+///
+/// [`Hash`]: ../../struct.Hash.html
+pub(crate) type ObjectKey = (String, Hash);
+
+/// An entry of the index of a pack.
+#[derive(Serialize, Deserialize)]
+struct IndexEntry {
+    object_type: String,
+    hash: Hash,
+    offset: u64,
+    length: u64,
+}
+
+/// A pack, whose index has been loaded into memory.
+#[derive(Debug)]
+pub(crate) struct Pack {
+    pub(crate) name: String,
+    pack_path: PathBuf,
+    index: HashMap<ObjectKey, (u64, u64)>,
+}
+
+impl Pack {
+
+    /// Loads the index of the pack `name` in the directory `dir`.
+    pub(crate) async fn load(dir: &Path, name: &str) -> Result<Pack> {
+        let entries: Vec<IndexEntry> = bincode::deserialize(&fs::read(dir.join(format!("{}{}", name, INDEX_SUFFIX))).await?)?;
+        Ok(Pack {
+            name: name.to_string(),
+            pack_path: dir.join(format!("{}{}", name, PACK_SUFFIX)),
+            index: entries
+                .into_iter()
+                .map(|entry| ((entry.object_type, entry.hash), (entry.offset, entry.length)))
+                .collect(),
+        })
+    }
+
+    /// Returns true if the pack contains the object `key`.
+    pub(crate) fn contains(&self, key: &ObjectKey) -> bool {
+        self.index.contains_key(key)
+    }
+
+    /// Returns the number of bytes of the object `key`, or `None` if the pack does not contain it.
+    pub(crate) fn length(&self, key: &ObjectKey) -> Option<u64> {
+        self.index.get(key).map(|&(_, length)| length)
+    }
+
+    /// Returns the objects that the pack contains.
+    pub(crate) fn objects(&self) -> impl Iterator<Item=&ObjectKey> {
+        self.index.keys()
+    }
+
+    /// Returns the content of the object `key`, or `None` if the pack does not contain it.
+    pub(crate) async fn read(&self, key: &ObjectKey) -> Result<Option<Vec<u8>>> {
+        let (offset, length) = match self.index.get(key) {
+            Some(&location) => location,
+            None => return Ok(None),
+        };
+        let mut file = fs::File::open(&self.pack_path).await?;
+        file.seek(SeekFrom::Start(offset)).await?;
+        let mut content: Vec<u8> = vec![0u8; length as usize];
+        file.read_exact(&mut content).await?;
+        Ok(Some(content))
+    }
+
+    /// Returns the contents of all objects that the pack contains.
+    pub(crate) async fn read_all(&self) -> Result<Vec<(ObjectKey, Vec<u8>)>> {
+        let pack = fs::read(&self.pack_path).await?;
+        Ok(self.index
+            .iter()
+            .map(|(key, &(offset, length))| (key.clone(), pack[offset as usize..(offset + length) as usize].to_vec()))
+            .collect())
+    }
+}
+
+/// Returns the names of the packs in the directory `dir`, in sorted order.
+pub(crate) async fn list_packs(dir: &Path) -> Result<Vec<String>> {
+    let mut names: Vec<String> = list_files(dir.into())
+        .await?
+        .into_iter()
+        .filter(|file_name| file_name.ends_with(INDEX_SUFFIX))
+        .map(|file_name| file_name.trim_end_matches(INDEX_SUFFIX).to_string())
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+/// Writes a pack of the `objects` to the directory `dir`, and returns its name.
+pub(crate) async fn write_pack<I>(dir: &Path, objects: I) -> Result<String>
+    where I: IntoIterator<Item=(ObjectKey, Vec<u8>)>
+{
+    let mut pack: Vec<u8> = Vec::new();
+    let mut entries: Vec<IndexEntry> = Vec::new();
+    for ((object_type, hash), content) in objects {
+        entries.push(IndexEntry { object_type, hash, offset: pack.len() as u64, length: content.len() as u64 });
+        pack.extend(content);
+    }
+
+    let index: Vec<u8> = bincode::serialize(&entries)?;
+    let name = Hash::sha256(&index).to_string();
+    create_dir_and_write_atomically(dir.join(format!("{}{}", name, PACK_SUFFIX)), pack).await?;
+    create_dir_and_write_atomically(dir.join(format!("{}{}", name, INDEX_SUFFIX)), index).await?;
+    Ok(name)
+}
+
+/// Removes the pack `name` from the directory `dir`. The index is removed first, so that no index
+/// refers to a removed pack.
+pub(crate) async fn remove_pack(dir: &Path, name: &str) -> Result<()> {
+    fs::remove_file(dir.join(format!("{}{}", name, INDEX_SUFFIX))).await?;
+    fs::remove_file(dir.join(format!("{}{}", name, PACK_SUFFIX))).await?;
+    Ok(())
+}
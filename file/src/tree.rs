@@ -0,0 +1,304 @@
+//! Implements the trees of [`HashChunk`]s, that hold the [`Hash`]es of the vertices and of the
+//! edges of a stored graph.
+//!
+//! The `Hash`es are sorted, and split into chunks after every `Hash` that is a boundary. Whether a
+//! `Hash` is a boundary only depends on the `Hash` itself, so two versions of a graph, that differ
+//! by a few vertices, are split into the same chunks, except for the chunks that hold the changed
+//! vertices. The `Hash`es of the chunks are split into the chunks of the next level in the same
+//! way, until a level consists of a single chunk, the root of the tree. Saving a version, that
+//! differs from its parent by a single vertex, writes one chunk per level, and the two versions
+//! share all other chunks.
+//!
+//! A graph can contain equal edges, which have the same `Hash`, so a tree holds a `Hash` as often
+//! as the graph contains the object.
+//!
+//! [`HashChunk`]: ../object/struct.HashChunk.html
+//! [`Hash`]: ../struct.Hash.html
+
+use std::{
+    collections::BTreeMap,
+    convert::TryInto,
+};
+
+use crate::{
+    Hash,
+    error::Result,
+    file::File,
+    file_storage::{to_files, write_all_files, read_all_objects, read_object},
+    object::{ObjectType, HashChunk},
+    store::ObjectStore,
+};
+
+/// A chunk holds this number of `Hash`es on average.
+const CHUNK_FACTOR: u8 = 64;
+
+/// Returns true if a chunk ends with `hash`.
+fn is_boundary(hash: &Hash) -> bool {
+    hash.as_bytes()[31].is_multiple_of(CHUNK_FACTOR)
+}
+
+/// Splits the sorted `hashes` into the chunks of the given `level`. Returns a single empty chunk,
+/// if there are no `hashes`.
+fn split<OT>(level: u32, hashes: Vec<Hash>) -> Vec<HashChunk<OT>>
+    where OT: ObjectType
+{
+    let mut chunks: Vec<HashChunk<OT>> = Vec::new();
+    let mut current: Vec<Hash> = Vec::new();
+    for hash in hashes {
+        current.push(hash);
+        if is_boundary(&hash) {
+            chunks.push(HashChunk::new(level, std::mem::take(&mut current)));
+        }
+    }
+    if !current.is_empty() || chunks.is_empty() {
+        chunks.push(HashChunk::new(level, current));
+    }
+    chunks
+}
+
+/// Writes the tree of the `hashes` of objects of type `OT`, unless its chunks already exist. A
+/// `Hash` that is repeated in `hashes` is kept as often as it is repeated.
+/// Returns the [`Hash`] of the root chunk.
+///
+/// [`Hash`]: ../struct.Hash.html
+pub(crate) async fn write_tree<S, OT>(store: &S, mut hashes: Vec<Hash>) -> Result<Hash>
+    where S: ObjectStore + ?Sized,
+          OT: ObjectType,
+          HashChunk<OT>: ObjectType
+{
+    hashes.sort();
+
+    let mut level = 0;
+    loop {
+        let chunks = split::<OT>(level, hashes);
+        hashes = write_all_files(store, to_files(chunks.iter(), store.hash_algorithm())).await?;
+        if hashes.len() == 1 {
+            return Ok(hashes[0]);
+        }
+        level += 1;
+    }
+}
+
+/// Reads the tree with the root chunk `root_hash`. Returns the sorted [`Hash`]es of the objects
+/// of type `OT`, each as often as it has been written.
+///
+/// [`Hash`]: ../struct.Hash.html
+pub(crate) async fn read_tree<S, OT>(store: &S, root_hash: Hash) -> Result<Vec<Hash>>
+    where S: ObjectStore + ?Sized,
+          HashChunk<OT>: ObjectType,
+          for<'a> &'a File<HashChunk<OT>>: TryInto<HashChunk<OT>, Error=bincode::Error>
+{
+    let mut hashes = vec![root_hash];
+    loop {
+        let chunks: Vec<HashChunk<OT>> = read_all_objects(store, hashes).await?;
+        let is_leaf = chunks.iter().all(|chunk| chunk.level == 0);
+        hashes = chunks.into_iter().flat_map(|chunk| chunk.hashes).collect();
+        if is_leaf {
+            return Ok(hashes);
+        }
+    }
+}
+
+/// The [`Hash`]es that one side of a comparison of two trees has reached, but not yet found on the
+/// other side, with the number of times that they have been reached.
+///
+/// [`Hash`]: ../struct.Hash.html
+struct Frontier {
+
+    /// The level of the chunks plus one, or 0 if the `hashes` are the ones of objects.
+    height: u32,
+
+    hashes: BTreeMap<Hash, usize>,
+}
+
+impl Frontier {
+
+    /// Starts at the root chunk `root_hash`.
+    async fn root<S, OT>(store: &S, root_hash: Hash) -> Result<Frontier>
+        where S: ObjectStore + ?Sized,
+              HashChunk<OT>: ObjectType,
+              for<'a> &'a File<HashChunk<OT>>: TryInto<HashChunk<OT>, Error=bincode::Error>
+    {
+        let root: HashChunk<OT> = read_object(store, root_hash).await?;
+        Ok(Frontier { height: root.level + 1, hashes: Some((root_hash, 1)).into_iter().collect() })
+    }
+
+    /// Replaces the chunks by the `Hash`es they hold, one level below. Each chunk is read once,
+    /// however often it has been reached.
+    async fn expand<S, OT>(self, store: &S) -> Result<Frontier>
+        where S: ObjectStore + ?Sized,
+              HashChunk<OT>: ObjectType,
+              for<'a> &'a File<HashChunk<OT>>: TryInto<HashChunk<OT>, Error=bincode::Error>
+    {
+        let chunks: Vec<HashChunk<OT>> = read_all_objects(store, self.hashes.keys().cloned().collect()).await?;
+        let mut hashes = BTreeMap::new();
+        for (chunk, count) in chunks.into_iter().zip(self.hashes.values()) {
+            for hash in chunk.hashes {
+                *hashes.entry(hash).or_insert(0) += count;
+            }
+        }
+        Ok(Frontier { height: self.height - 1, hashes })
+    }
+
+    /// Returns the `Hash`es, each as often as it has been reached, in sorted order.
+    fn into_hashes(self) -> Vec<Hash> {
+        self.hashes
+            .into_iter()
+            .flat_map(|(hash, count)| std::iter::repeat_n(hash, count))
+            .collect()
+    }
+}
+
+/// Compares the trees with the root chunks `root_hash_1` and `root_hash_2`. Returns the
+/// [`Hash`]es of the objects of type `OT` that are only in the first tree, and the ones that are
+/// only in the second tree. A `Hash` that the first tree holds more often than the second one is
+/// returned as often as it is held in excess, and vice versa.
+///
+/// Chunks that are in both trees hold the same objects, so they are not read. Comparing two trees
+/// that differ by a few objects reads a few chunks per level, however large the trees are.
+///
+/// [`Hash`]: ../struct.Hash.html
+pub(crate) async fn diff_trees<S, OT>(store: &S, root_hash_1: Hash, root_hash_2: Hash) -> Result<(Vec<Hash>, Vec<Hash>)>
+    where S: ObjectStore + ?Sized,
+          HashChunk<OT>: ObjectType,
+          for<'a> &'a File<HashChunk<OT>>: TryInto<HashChunk<OT>, Error=bincode::Error>
+{
+    if root_hash_1 == root_hash_2 {
+        return Ok((Vec::new(), Vec::new()));
+    }
+
+    let mut frontier_1 = Frontier::root::<S, OT>(store, root_hash_1).await?;
+    let mut frontier_2 = Frontier::root::<S, OT>(store, root_hash_2).await?;
+    loop {
+        // the same chunk, at the same level, holds the same objects on both sides
+        if frontier_1.height == frontier_2.height {
+            let common: Vec<(Hash, usize)> = frontier_1.hashes
+                .iter()
+                .filter_map(|(hash, &count)| frontier_2.hashes.get(hash).map(|&other| (*hash, count.min(other))))
+                .collect();
+            for (hash, count) in common {
+                for frontier in [&mut frontier_1, &mut frontier_2] {
+                    let remaining = frontier.hashes[&hash] - count;
+                    if remaining == 0 {
+                        frontier.hashes.remove(&hash);
+                    } else {
+                        frontier.hashes.insert(hash, remaining);
+                    }
+                }
+            }
+        }
+
+        let height = frontier_1.height.max(frontier_2.height);
+        if height == 0 {
+            break;
+        }
+        if frontier_1.height == height {
+            frontier_1 = frontier_1.expand::<S, OT>(store).await?;
+        }
+        if frontier_2.height == height {
+            frontier_2 = frontier_2.expand::<S, OT>(store).await?;
+        }
+    }
+
+    Ok((frontier_1.into_hashes(), frontier_2.into_hashes()))
+}
+
+#[cfg(test)]
+mod test {
+    use tokio::runtime::Runtime;
+
+    use super::*;
+    use crate::{
+        object::{StoredVertex, HashEdge},
+        store::MemoryStore,
+    };
+
+    fn hashes(range: std::ops::Range<u32>) -> Vec<Hash> {
+        range.map(|i| Hash::sha256(i.to_be_bytes())).collect()
+    }
+
+    #[test]
+    fn test_write_read_tree() -> Result<()> {
+        let mut rt = Runtime::new()?;
+        rt.block_on(async {
+            let store = MemoryStore::new();
+            let chunk_type = HashChunk::<StoredVertex>::storage_name();
+
+            let empty = write_tree::<_, StoredVertex>(&store, Vec::new()).await?;
+            assert!(read_tree::<_, StoredVertex>(&store, empty).await?.is_empty());
+
+            let mut sorted = hashes(0..2000);
+            let mut reversed = sorted.clone();
+            reversed.reverse();
+            sorted.sort();
+            let root_hash = write_tree::<_, StoredVertex>(&store, reversed).await?;
+            assert_eq!(write_tree::<_, StoredVertex>(&store, sorted.clone()).await?, root_hash);
+            assert_eq!(read_tree::<_, StoredVertex>(&store, root_hash).await?, sorted);
+
+            let root: HashChunk<StoredVertex> = read_object(&store, root_hash).await?;
+            assert!(root.level > 0);
+
+            // adding a hash writes at most two chunks per level
+            let chunk_count = store.list_objects(chunk_type).await?.len();
+            sorted.extend(hashes(2000..2001));
+            let new_root_hash = write_tree::<_, StoredVertex>(&store, sorted).await?;
+            let new_root: HashChunk<StoredVertex> = read_object(&store, new_root_hash).await?;
+            let written = store.list_objects(chunk_type).await?.len() - chunk_count;
+            assert!(written <= 2 * (new_root.level as usize + 1), "{} chunks written", written);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_diff_trees() -> Result<()> {
+        let mut rt = Runtime::new()?;
+        rt.block_on(async {
+            let store = MemoryStore::new();
+
+            let mut hashes_1 = hashes(0..3000);
+            let mut hashes_2 = hashes(10..3000);
+            hashes_2.extend(hashes(5000..5003));
+            let root_1 = write_tree::<_, StoredVertex>(&store, hashes_1.clone()).await?;
+            let root_2 = write_tree::<_, StoredVertex>(&store, hashes_2).await?;
+            let empty = write_tree::<_, StoredVertex>(&store, Vec::new()).await?;
+
+            let (mut removed, mut added) = (hashes(0..10), hashes(5000..5003));
+            removed.sort();
+            added.sort();
+            assert_eq!(diff_trees::<_, StoredVertex>(&store, root_1, root_2).await?, (removed.clone(), added.clone()));
+            assert_eq!(diff_trees::<_, StoredVertex>(&store, root_2, root_1).await?, (added, removed));
+            assert_eq!(diff_trees::<_, StoredVertex>(&store, root_1, root_1).await?, (Vec::new(), Vec::new()));
+
+            hashes_1.sort();
+            assert_eq!(diff_trees::<_, StoredVertex>(&store, empty, root_1).await?, (Vec::new(), hashes_1));
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_repeated_hashes() -> Result<()> {
+        let mut rt = Runtime::new()?;
+        rt.block_on(async {
+            let store = MemoryStore::new();
+
+            // a hash that ends a chunk, repeated, to split equal chunks
+            let boundary = (0..).map(|i: u32| Hash::sha256(i.to_be_bytes())).find(is_boundary).unwrap();
+            let repeated = hashes(0..1);
+            let mut hashes_1 = hashes(0..1000);
+            hashes_1.extend(repeated.iter().cloned());
+            hashes_1.extend(vec![boundary; 3]);
+            hashes_1.sort();
+            let root_1 = write_tree::<_, HashEdge>(&store, hashes_1.clone()).await?;
+            assert_eq!(read_tree::<_, HashEdge>(&store, root_1).await?, hashes_1);
+
+            let mut hashes_2 = hashes(0..1000);
+            hashes_2.push(boundary);
+            let root_2 = write_tree::<_, HashEdge>(&store, hashes_2).await?;
+            let mut extra = vec![repeated[0], boundary, boundary];
+            extra.sort();
+            assert_eq!(diff_trees::<_, HashEdge>(&store, root_1, root_2).await?, (extra.clone(), Vec::new()));
+            assert_eq!(diff_trees::<_, HashEdge>(&store, root_2, root_1).await?, (Vec::new(), extra));
+            Ok(())
+        })
+    }
+}
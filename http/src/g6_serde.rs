@@ -2,13 +2,18 @@
 //! [AntV G6]: https://g6.antv.vision
 
 use serde::Serialize;
-use histo_graph_core::graph::directed_graph::DirectedGraph;
+use histo_graph_core::graph::attributed_graph::AttributedGraph;
+use histo_graph_core::graph::attributes::Attributes;
 use histo_graph_core::graph::graph::{VertexId, Edge};
+use histo_graph_serde::attributes_serde::{AttributesSer, to_attributes_ser};
+use std::collections::BTreeMap;
 
 #[derive(Serialize)]
 pub struct VertexG6 {
     id: String,
     label: String,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    attributes: AttributesSer,
 }
 
 #[derive(Serialize)]
@@ -16,6 +21,8 @@ pub struct EdgeG6 {
     source: String,
     target: String,
     label: String,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    attributes: AttributesSer,
 }
 
 #[derive(Serialize)]
@@ -24,22 +31,46 @@ pub struct DirectedGraphG6 {
     edges: Vec<EdgeG6>,
 }
 
-impl From<&DirectedGraph> for DirectedGraphG6 {
-    fn from(graph: &DirectedGraph) -> DirectedGraphG6 {
+/// Returns the value of the attribute "label", or `default`, if there is no such attribute.
+fn label(attributes: Option<&Attributes>, default: String) -> String {
+    attributes
+        .and_then(|attributes| attributes.get("label"))
+        .map(|value| value.to_string())
+        .unwrap_or(default)
+}
+
+fn attributes_ser(attributes: Option<&Attributes>) -> AttributesSer {
+    attributes
+        .map(to_attributes_ser)
+        .unwrap_or_default()
+}
+
+impl From<&AttributedGraph> for DirectedGraphG6 {
+    fn from(graph: &AttributedGraph) -> DirectedGraphG6 {
         DirectedGraphG6 {
             nodes: graph
                 .vertices()
-                .map(|&VertexId(id)| VertexG6 {
-                    id: id.to_string(),
-                    label: id.to_string(),
+                .map(|&vertex_id| {
+                    let VertexId(id) = vertex_id;
+                    let attributes = graph.vertex_attributes(vertex_id);
+                    VertexG6 {
+                        id: id.to_string(),
+                        label: label(attributes, id.to_string()),
+                        attributes: attributes_ser(attributes),
+                    }
                 })
                 .collect(),
             edges: graph
                 .edges()
-                .map(|&Edge(VertexId(id_1), VertexId(id_2))| EdgeG6 {
-                    source: id_1.to_string(),
-                    target: id_2.to_string(),
-                    label: "edge".to_string(),
+                .map(|&edge| {
+                    let Edge(VertexId(id_1), VertexId(id_2)) = edge;
+                    let attributes = graph.edge_attributes(edge);
+                    EdgeG6 {
+                        source: id_1.to_string(),
+                        target: id_2.to_string(),
+                        label: label(attributes, "edge".to_string()),
+                        attributes: attributes_ser(attributes),
+                    }
                 })
                 .collect()
         }
@@ -1,18 +1,38 @@
-use warp::Filter;
+use warp::{Filter, Reply, http::StatusCode, reply::Response};
 use histo_graph_file::file_storage::*;
-use std::path::{PathBuf, Path};
+use histo_graph_file::branch::read_head;
+use histo_graph_file::error::{Error, Result};
+use histo_graph_file::store::{ObjectStore, LimitedStore, CachedStore, DEFAULT_MAX_CONCURRENCY, DEFAULT_CACHE_CAPACITY, open_store};
 use histo_graph_serde::directed_graph_serde::DirectedGraphSer;
 use g6_serde::DirectedGraphG6;
 use histo_graph_core::graph::graph::{VertexId, Edge};
+use std::sync::Arc;
 
 mod g6_serde;
 
 #[tokio::main]
 async fn main() {
+    // the store is opened once, because a database can only be opened once, and it is limited, so
+    // that all requests together do not open too many files at the same time; the requests share
+    // the cache of the objects they read
+    let store = match open_store(".store/") {
+        Ok(store) => LimitedStore::new(store, DEFAULT_MAX_CONCURRENCY),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            if let Some(hint) = hint(&e) {
+                eprintln!("hint: {}", hint);
+            }
+            std::process::exit(1);
+        }
+    };
+    let store: Arc<dyn ObjectStore> = Arc::new(CachedStore::new(store, DEFAULT_CACHE_CAPACITY));
+    let with_store = warp::any().map(move || store.clone());
+
     // get, /show
     let show =
         warp::get()
             .and(warp::path("show"))
+            .and(with_store.clone())
             .and_then(fn_show);
 
     // get, /
@@ -25,6 +45,7 @@ async fn main() {
     let get_g6 =
         warp::get()
             .and(warp::path("g6"))
+            .and(with_store.clone())
             .and_then(fn_get_g6);
 
     // post, /add-vertex/:vertex_id
@@ -32,6 +53,7 @@ async fn main() {
         warp::post()
             .and(warp::path("add-vertex"))
             .and(warp::path::param::<u64>())
+            .and(with_store.clone())
             .and_then(fn_add_vertex);
 
     // post, /add-edge/:vertex_id/:vertex_id
@@ -40,6 +62,7 @@ async fn main() {
             .and(warp::path("add-edge"))
             .and(warp::path::param::<u64>())
             .and(warp::path::param::<u64>())
+            .and(with_store)
             .and_then(fn_add_edge);
 
     let all =
@@ -52,48 +75,78 @@ async fn main() {
     warp::serve(all).run(([127, 0, 0, 1], 3030)).await;
 }
 
-async fn fn_show() -> Result<impl warp::Reply, std::convert::Infallible> {
-    let base_dir: PathBuf = Path::new(".store/").into();
-    let name = "current".to_string();
+/// Suggests how the store can be made usable, if it cannot be opened for a usual reason.
+fn hint(e: &Error) -> Option<&'static str> {
+    match e {
+        Error::NotInitialized { .. } => Some("create a store with 'refajo init' in the working directory"),
+        Error::VersionMismatch { found, supported } if found < supported => Some("upgrade the store with 'refajo migrate'"),
+        _ => None,
+    }
+}
+
+/// Turns an error into a response, with a status code that tells what went wrong.
+fn error_response(e: Error) -> Response {
+    let status = match e {
+        Error::RefNotFound { .. } => StatusCode::NOT_FOUND,
+        Error::ConcurrentModification { .. } => StatusCode::CONFLICT,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    eprintln!("{}", e);
+    warp::reply::with_status(e.to_string(), status).into_response()
+}
+
+fn into_response<R>(result: Result<R>) -> std::result::Result<Response, std::convert::Infallible>
+    where R: Reply
+{
+    Ok(result.map_or_else(error_response, Reply::into_response))
+}
+
+async fn fn_show(store: Arc<dyn ObjectStore>) -> std::result::Result<Response, std::convert::Infallible> {
+    into_response(async {
+        let name = read_head(&*store).await?;
 
-    let graph = load_graph(base_dir, name).await.unwrap();
-    let ser: DirectedGraphSer = (&graph).into();
-    Ok(warp::reply::json(&ser))
+        let graph = load_graph(&*store, name).await?;
+        let ser: DirectedGraphSer = (&graph).into();
+        Ok(warp::reply::json(&ser))
+    }.await)
 }
 
-async fn fn_get_g6() -> Result<impl warp::Reply, std::convert::Infallible> {
-    let base_dir: PathBuf = Path::new(".store/").into();
-    let name = "current".to_string();
+async fn fn_get_g6(store: Arc<dyn ObjectStore>) -> std::result::Result<Response, std::convert::Infallible> {
+    into_response(async {
+        let name = read_head(&*store).await?;
 
-    let graph = load_graph(base_dir, name).await.unwrap();
-    let ser: DirectedGraphG6 = (&graph).into();
-    Ok(warp::reply::json(&ser))
+        let graph = load_graph(&*store, name).await?;
+        let ser: DirectedGraphG6 = (&graph).into();
+        Ok(warp::reply::json(&ser))
+    }.await)
 }
 
-async fn fn_add_vertex(vertex_id: u64) -> Result<impl warp::Reply, std::convert::Infallible> {
-    let base_dir: PathBuf = Path::new(".store/").into();
-    let name = "current".to_string();
+async fn fn_add_vertex(vertex_id: u64, store: Arc<dyn ObjectStore>) -> std::result::Result<Response, std::convert::Infallible> {
+    into_response(async {
+        let name = read_head(&*store).await?;
 
-    let vertex_id = VertexId(vertex_id);
+        let vertex_id = VertexId(vertex_id);
 
-    let mut graph = load_graph(base_dir.clone(), name.clone()).await.unwrap();
+        let (mut graph, parent) = load_graph_with_commit(&*store, name.clone()).await?;
 
-    graph.add_vertex(vertex_id);
+        graph.add_vertex(vertex_id);
 
-    save_graph_as(base_dir, name, &graph).await.unwrap();
-    Ok(warp::reply::reply())
+        save_graph_with_parent(&*store, name, Some(parent), &graph).await?;
+        Ok(warp::reply::reply())
+    }.await)
 }
 
-async fn fn_add_edge(vertex_id_from: u64, vertex_id_to: u64) -> Result<impl warp::Reply, std::convert::Infallible> {
-    let base_dir: PathBuf = Path::new(".store/").into();
-    let name = "current".to_string();
+async fn fn_add_edge(vertex_id_from: u64, vertex_id_to: u64, store: Arc<dyn ObjectStore>) -> std::result::Result<Response, std::convert::Infallible> {
+    into_response(async {
+        let name = read_head(&*store).await?;
 
-    let edge = Edge(VertexId(vertex_id_from), VertexId(vertex_id_to));
+        let edge = Edge(VertexId(vertex_id_from), VertexId(vertex_id_to));
 
-    let mut graph = load_graph(base_dir.clone(), name.clone()).await.unwrap();
+        let (mut graph, parent) = load_graph_with_commit(&*store, name.clone()).await?;
 
-    graph.add_edge(edge);
+        graph.add_edge(edge);
 
-    save_graph_as(base_dir, name, &graph).await.unwrap();
-    Ok(warp::reply::reply())
-}
\ No newline at end of file
+        save_graph_with_parent(&*store, name, Some(parent), &graph).await?;
+        Ok(warp::reply::reply())
+    }.await)
+}
@@ -4,9 +4,56 @@ pub enum Error {
     SerdeJsonError(serde_json::error::Error),
     IoError(std::io::Error),
     ParseIntError(std::num::ParseIntError),
+
+    /// The arguments cannot be combined, e.g. a branch and the hash of a version to show.
+    InvalidArguments(String),
 }
 
 use Error::*;
+use histo_graph_file::error::Error::{NotInitialized, ConfigMismatch, UnknownStore, VersionMismatch, RefNotFound, BranchExists, CurrentBranch, AmbiguousHash, VersionNotFound};
+
+impl Error {
+
+    /// Suggests how the user can resolve the error, if there is a usual way.
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            FileError(NotInitialized { .. }) => Some("create a store with 'refajo init'"),
+            FileError(VersionMismatch { found, supported }) if found < supported => Some("upgrade the store with 'refajo migrate'"),
+            FileError(ConfigMismatch { .. }) => Some("leave out the options of 'refajo init' that differ, or init in another directory"),
+            FileError(UnknownStore { .. }) => Some("move the files out of the directory, or run refajo in another directory"),
+            FileError(RefNotFound { .. }) => Some("list the branches with 'refajo branch'"),
+            FileError(BranchExists { .. }) => Some("choose another name, or delete the branch with 'refajo branch -d'"),
+            FileError(CurrentBranch { .. }) => Some("switch to another branch with 'refajo switch' first"),
+            FileError(AmbiguousHash { .. }) => Some("type more characters of the hash"),
+            FileError(VersionNotFound { .. }) => Some("list the versions and their times with 'refajo log'"),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            FileError(e) => write!(f, "{}", e),
+            SerdeJsonError(e) => write!(f, "cannot serialize the graph: {}", e),
+            IoError(e) => write!(f, "{}", e),
+            ParseIntError(e) => write!(f, "invalid vertex id: {}", e),
+            InvalidArguments(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FileError(e) => Some(e),
+            SerdeJsonError(e) => Some(e),
+            IoError(e) => Some(e),
+            ParseIntError(e) => Some(e),
+            InvalidArguments(_) => None,
+        }
+    }
+}
 
 impl From<histo_graph_file::error::Error> for Error {
     fn from(e: histo_graph_file::error::Error) -> Error {
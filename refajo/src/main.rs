@@ -1,28 +1,120 @@
 use clap::{App, SubCommand, Arg};
 use histo_graph_file::file_storage::*;
-use std::path::{PathBuf, Path};
+use histo_graph_file::branch::*;
+use histo_graph_file::merge::*;
+use histo_graph_file::gc::*;
+use histo_graph_file::fsck::*;
+use histo_graph_file::diff::*;
+use histo_graph_file::history::*;
+use histo_graph_file::store::{StoreConfig, Backend, Compression, HashAlgorithm, CURRENT_FORMAT, ObjectStore, CachedStore, DEFAULT_CACHE_CAPACITY, create_store, open_store, read_store_config, migrate_store};
 use histo_graph_serde::directed_graph_serde::DirectedGraphSer;
 use tokio::runtime::Runtime;
-use error::Result;
-use histo_graph_core::graph::directed_graph::DirectedGraph;
+use error::{Result, Error::InvalidArguments};
+use histo_graph_core::graph::attributed_graph::AttributedGraph;
 use histo_graph_core::graph::graph::{VertexId, Edge};
+use std::time::{Duration, UNIX_EPOCH};
+use futures::{StreamExt, TryStreamExt};
 
 mod error;
 
-fn main() -> Result<()> {
+/// The environment variable that names the author of the versions that are saved, instead of the
+/// author in the configuration of the store.
+const AUTHOR_VARIABLE: &str = "REFAJO_AUTHOR";
+
+/// Returns the author of the versions that are saved into the store under `base_dir`: the value of
+/// the environment variable `REFAJO_AUTHOR`, or else the author in the configuration of the store,
+/// or else no author.
+fn author(base_dir: &str) -> Result<String> {
+    match std::env::var(AUTHOR_VARIABLE) {
+        Ok(author) if !author.trim().is_empty() => Ok(author.trim().to_string()),
+        _ => Ok(read_store_config(base_dir)?.author.unwrap_or_default()),
+    }
+}
+
+/// Formats a timestamp, in seconds since the UNIX epoch, as a UTC date and time, e.g.
+/// `2020-09-13T12:26:40Z`.
+fn format_timestamp(timestamp: i64) -> String {
+    let time = UNIX_EPOCH + Duration::from_secs(timestamp.max(0) as u64);
+    humantime::format_rfc3339_seconds(time).to_string()
+}
+
+/// Parses `at` as a point in time: a UTC date and time, e.g. `2020-09-13T12:26:40Z` or
+/// `2020-09-13 12:26:40`, a UTC date, which means its midnight, or `@` followed by the seconds since
+/// the UNIX epoch. Returns the seconds since the UNIX epoch, or `None`, if `at` is not a time.
+fn parse_time(at: &str) -> Option<i64> {
+    if let Some(seconds) = at.strip_prefix('@') {
+        return seconds.parse().ok();
+    }
+    let time = humantime::parse_rfc3339_weak(at)
+        .or_else(|_| humantime::parse_rfc3339_weak(&format!("{} 00:00:00", at)))
+        .ok()?;
+    time.duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs() as i64)
+}
+
+fn message_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("message")
+        .short("m")
+        .long("message")
+        .takes_value(true)
+        .help("describes the change")
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("error: {}", e);
+        if let Some(hint) = e.hint() {
+            eprintln!("hint: {}", hint);
+        }
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<()> {
     let matches = App::new("histo-graph")
         .version("0.1.0")
         .about("Historizes graphs")
         .subcommand(SubCommand::with_name("init")
-            .about("initializes a new graph"))
+            .about("initializes a new graph")
+            .arg(Arg::with_name("backend")
+                .long("backend")
+                .takes_value(true)
+                .possible_values(&["directory", "database"])
+                .default_value("directory")
+                .help("stores the objects as one file each, or in a single database"))
+            .arg(Arg::with_name("compression")
+                .long("compression")
+                .takes_value(true)
+                .possible_values(&["none", "deflate"])
+                .default_value("none")
+                .help("compresses the content of the stored objects"))
+            .arg(Arg::with_name("hash")
+                .long("hash")
+                .takes_value(true)
+                .possible_values(&["sha256", "blake3"])
+                .default_value("sha256")
+                .help("the algorithm that computes the hashes of the stored objects"))
+            .arg(Arg::with_name("author")
+                .long("author")
+                .takes_value(true)
+                .help("the default author of the versions that are saved, unless REFAJO_AUTHOR is set"))
+        )
         .subcommand(SubCommand::with_name("show")
             .about("shows a graph")
+            .arg(Arg::with_name("commit")
+                .index(1)
+                .help("a branch, or the hash of a commit, which may be abbreviated to its first characters; the current branch by default"))
+            .arg(Arg::with_name("at")
+                .long("at")
+                .takes_value(true)
+                .value_name("version|time")
+                .help("shows the version with this hash instead, without a <commit>, or the version of the branch at this UTC time, e.g. 2020-09-13T12:26:40Z, 2020-09-13 or @1600000000"))
         )
         .subcommand(SubCommand::with_name("add-vertex")
             .about("adds a vertex")
             .arg(Arg::with_name("vertexId")
                 .required(true)
                 .index(1))
+            .arg(message_arg())
         )
         .subcommand(SubCommand::with_name("add-edge")
             .about("adds an edge")
@@ -32,36 +124,158 @@ fn main() -> Result<()> {
             .arg(Arg::with_name("vertexId_to")
                 .required(true)
                 .index(2))
+            .arg(message_arg())
+        )
+        .subcommand(SubCommand::with_name("log")
+            .about("shows the versions of a graph, newest first, with their author, time and message")
+            .arg(Arg::with_name("commit")
+                .index(1)
+                .help("a branch, or the hash of a commit, which may be abbreviated to its first characters; the current branch by default"))
+            .arg(Arg::with_name("max-count")
+                .short("n")
+                .long("max-count")
+                .takes_value(true)
+                .validator(|count| count.parse::<usize>().map(|_| ()).map_err(|e| e.to_string()))
+                .help("shows only the latest versions"))
+        )
+        .subcommand(SubCommand::with_name("branch")
+            .about("lists, creates, renames or deletes branches")
+            .arg(Arg::with_name("delete")
+                .short("d")
+                .long("delete")
+                .help("deletes the branch")
+                .conflicts_with("move"))
+            .arg(Arg::with_name("move")
+                .short("m")
+                .long("move")
+                .help("renames the branch to <new_name>")
+                .requires("new_name"))
+            .arg(Arg::with_name("name")
+                .index(1))
+            .arg(Arg::with_name("new_name")
+                .index(2))
+        )
+        .subcommand(SubCommand::with_name("switch")
+            .about("switches to a branch")
+            .arg(Arg::with_name("create")
+                .short("c")
+                .long("create")
+                .help("creates the branch from the current branch before switching to it"))
+            .arg(Arg::with_name("name")
+                .required(true)
+                .index(1))
         )
+        .subcommand(SubCommand::with_name("merge")
+            .about("merges a branch into the current branch")
+            .arg(Arg::with_name("branch")
+                .required(true)
+                .index(1))
+        )
+        .subcommand(SubCommand::with_name("diff")
+            .about("shows the vertices and edges that differ between two branches or commits")
+            .arg(Arg::with_name("from")
+                .required(true)
+                .index(1))
+            .arg(Arg::with_name("to")
+                .index(2)
+                .help("the branch or commit to compare with, the current branch by default"))
+        )
+        .subcommand(SubCommand::with_name("gc")
+            .about("removes objects that are not reachable from any branch")
+            .arg(Arg::with_name("dry-run")
+                .short("n")
+                .long("dry-run")
+                .help("only reports what would be removed"))
+        )
+        .subcommand(SubCommand::with_name("fsck")
+            .about("verifies the integrity of the stored objects"))
+        .subcommand(SubCommand::with_name("migrate")
+            .about("upgrades the store to the current format"))
+        .subcommand(SubCommand::with_name("repack")
+            .about("moves the objects that are stored as separate files into a pack"))
         .get_matches();
 
-    let base_dir: PathBuf = Path::new(".store/").into();
-    let name = "current".to_string();
+    let base_dir = ".store/";
 
-    if let Some(_) = matches.subcommand_matches("show") {
-        println!("Running sub-command 'show' ");
+    if let Some(matches) = matches.subcommand_matches("init") {
+        println!("Running sub-command 'init' ");
+        let backend = match matches.value_of("backend") {
+            Some("database") => Backend::Database,
+            _ => Backend::Directory,
+        };
+        let compression = match matches.value_of("compression") {
+            Some("deflate") => Compression::Deflate,
+            _ => Compression::None,
+        };
+        let hash_algorithm = match matches.value_of("hash") {
+            Some("blake3") => HashAlgorithm::Blake3,
+            _ => HashAlgorithm::Sha256,
+        };
+        let default_author = matches.value_of("author").map(str::to_string);
+        let store = create_store(base_dir, StoreConfig { backend, compression, hash_algorithm, author: default_author, ..StoreConfig::default() })?;
+        let info = CommitInfo::new(author(base_dir)?, "Initialize the graph");
         return {
             let mut rt = Runtime::new()?;
             rt.block_on(async {
-                let graph = load_graph(base_dir, name).await?;
-                let ser: DirectedGraphSer = (&graph).into();
-                let str = serde_json::to_string(&ser)?;
-                println!("{}", str);
+                let name = read_head(&*store).await?;
+                let graph = AttributedGraph::new();
+                save_graph_as_with_info(&*store, name, &graph, &info).await?;
                 Ok(())
             })
-        }
+        };
     }
 
-    if let Some(_) = matches.subcommand_matches("init") {
-        println!("Running sub-command 'init' ");
+    if matches.subcommand_matches("migrate").is_some() {
+        println!("Running sub-command 'migrate' ");
+        let mut rt = Runtime::new()?;
+        return rt.block_on(async {
+            match migrate_store(base_dir).await? {
+                CURRENT_FORMAT => println!("The store is up to date"),
+                format => println!("Upgraded the store from format {} to {}", format, CURRENT_FORMAT),
+            }
+            Ok(())
+        });
+    }
+
+    // the vertices are read once, although every edge refers to two of them
+    let store: Box<dyn ObjectStore> = Box::new(CachedStore::new(open_store(base_dir)?, DEFAULT_CACHE_CAPACITY));
+
+    if let Some(matches) = matches.subcommand_matches("show") {
+        println!("Running sub-command 'show' ");
         return {
             let mut rt = Runtime::new()?;
             rt.block_on(async {
-                let graph = DirectedGraph::new();
-                save_graph_as(base_dir, name, &graph).await?;
+                let revision = match matches.value_of("commit") {
+                    Some(revision) => revision.to_string(),
+                    None => read_head(&*store).await?,
+                };
+                let commit_hash = match matches.value_of("at") {
+                    Some(at) => match parse_time(at) {
+                        Some(timestamp) => {
+                            let commit_hash = commit_as_of(&*store, &revision, timestamp).await?;
+                            println!("Showing '{}' as of {}: commit {}", revision, format_timestamp(timestamp), commit_hash);
+                            commit_hash
+                        }
+                        None => match matches.value_of("commit") {
+                            Some(commit) => return Err(InvalidArguments(format!(
+                                "--at {} is not a time, but a version, which cannot be shown together with '{}'; pass a time to --at, or leave out '{}'",
+                                at, commit, commit,
+                            ))),
+                            None => resolve_commit(&*store, at).await?,
+                        },
+                    },
+                    None => resolve_commit(&*store, &revision).await?,
+                };
+                let graph = read_commit_graph(&*store, commit_hash).await?;
+                let ser: DirectedGraphSer = (&graph).into();
+                let str = serde_json::to_string(&ser)?;
+                println!("{}", str);
+                if let Some(cache) = store.object_cache() {
+                    eprintln!("Object cache: {}", cache.stats());
+                }
                 Ok(())
             })
-        };
+        }
     }
 
     if let Some(matches) = matches.subcommand_matches("add-vertex") {
@@ -73,13 +287,19 @@ fn main() -> Result<()> {
                     println!("Adding vertex '{}'", vertex_id);
 
                     let vertex_id: u64 = std::str::FromStr::from_str(vertex_id)?;
+                    let message = match matches.value_of("message") {
+                        Some(message) => message.to_string(),
+                        None => format!("Add vertex {}", vertex_id),
+                    };
+                    let info = CommitInfo::new(author(base_dir)?, message);
                     let vertex_id = VertexId(vertex_id);
 
-                    let mut graph = load_graph(base_dir.clone(), name.clone()).await?;
+                    let name = read_head(&*store).await?;
+                    let (mut graph, parent) = load_graph_with_commit(&*store, name.clone()).await?;
 
                     graph.add_vertex(vertex_id);
 
-                    save_graph_as(base_dir, name, &graph).await?;
+                    save_graph_with_parent_and_info(&*store, name, Some(parent), &graph, &info).await?;
                     Ok(())
                 })
             } else {
@@ -97,14 +317,138 @@ fn main() -> Result<()> {
 
                     let vertex_id_from: u64 = std::str::FromStr::from_str(vertex_id_from)?;
                     let vertex_id_to: u64 = std::str::FromStr::from_str(vertex_id_to)?;
+                    let message = match matches.value_of("message") {
+                        Some(message) => message.to_string(),
+                        None => format!("Add edge {} -> {}", vertex_id_from, vertex_id_to),
+                    };
+                    let info = CommitInfo::new(author(base_dir)?, message);
 
                     let edge = Edge(VertexId(vertex_id_from), VertexId(vertex_id_to));
 
-                    let mut graph = load_graph(base_dir.clone(), name.clone()).await?;
+                    let name = read_head(&*store).await?;
+                    let (mut graph, parent) = load_graph_with_commit(&*store, name.clone()).await?;
 
                     graph.add_edge(edge);
 
-                    save_graph_as(base_dir, name, &graph).await?;
+                    save_graph_with_parent_and_info(&*store, name, Some(parent), &graph, &info).await?;
+                    Ok(())
+                })
+            } else {
+                Ok(())
+            };
+    }
+
+    if let Some(matches) = matches.subcommand_matches("log") {
+        println!("Running sub-command 'log' ");
+        let mut rt = Runtime::new()?;
+        return rt.block_on(async {
+            let revision = match matches.value_of("commit") {
+                Some(revision) => revision.to_string(),
+                None => read_head(&*store).await?,
+            };
+            let max_count = matches.value_of("max-count").and_then(|count| count.parse().ok()).unwrap_or(usize::MAX);
+            let commit_hash = resolve_commit(&*store, &revision).await?;
+            let entries = history(&*store, commit_hash).take(max_count);
+            futures::pin_mut!(entries);
+            while let Some(entry) = entries.try_next().await? {
+                println!("commit {}", entry.hash);
+                if entry.parents.len() > 1 {
+                    let parents: Vec<String> = entry.parents.iter().map(|parent| parent.to_string()).collect();
+                    println!("Merge:  {}", parents.join(" "));
+                }
+                if !entry.author.is_empty() {
+                    println!("Author: {}", entry.author);
+                }
+                println!("Date:   {}", format_timestamp(entry.timestamp));
+                println!();
+                for line in entry.message.lines() {
+                    println!("    {}", line);
+                }
+                if !entry.message.is_empty() {
+                    println!();
+                }
+            }
+            Ok(())
+        });
+    }
+
+    if let Some(matches) = matches.subcommand_matches("branch") {
+        println!("Running sub-command 'branch' ");
+        let mut rt = Runtime::new()?;
+        return rt.block_on(async {
+            let head = read_head(&*store).await?;
+            match (matches.value_of("name"), matches.value_of("new_name")) {
+                (Some(name), _) if matches.is_present("delete") => {
+                    println!("Deleting branch '{}'", name);
+                    delete_branch(&*store, name).await?;
+                }
+                (Some(name), Some(new_name)) if matches.is_present("move") => {
+                    println!("Renaming branch '{}' to '{}'", name, new_name);
+                    rename_branch(&*store, name, new_name).await?;
+                }
+                (Some(name), None) => {
+                    println!("Creating branch '{}'", name);
+                    create_branch(&*store, name, &head).await?;
+                }
+                (Some(name), Some(new_name)) => {
+                    return Err(InvalidArguments(format!(
+                        "a branch is created from the current branch only; pass -m to rename '{}' to '{}'",
+                        name, new_name,
+                    )));
+                }
+                _ => {
+                    for name in list_branches(&*store).await? {
+                        let marker = if name == head { "*" } else { " " };
+                        println!("{} {}", marker, name);
+                    }
+                }
+            }
+            Ok(())
+        });
+    }
+
+    if let Some(matches) = matches.subcommand_matches("switch") {
+        println!("Running sub-command 'switch' ");
+        return
+            if let Some(name) = matches.value_of("name") {
+                let mut rt = Runtime::new()?;
+                rt.block_on(async {
+                    if matches.is_present("create") {
+                        println!("Creating branch '{}'", name);
+                        let head = read_head(&*store).await?;
+                        create_branch(&*store, name, &head).await?;
+                    }
+                    println!("Switching to branch '{}'", name);
+                    switch_branch(&*store, name).await?;
+                    Ok(())
+                })
+            } else {
+                Ok(())
+            };
+    }
+
+    if let Some(matches) = matches.subcommand_matches("merge") {
+        println!("Running sub-command 'merge' ");
+        return
+            if let Some(branch) = matches.value_of("branch") {
+                let mut rt = Runtime::new()?;
+                rt.block_on(async {
+                    let head = read_head(&*store).await?;
+                    println!("Merging branch '{}' into '{}'", branch, head);
+
+                    let info = CommitInfo::new(author(base_dir)?, format!("Merge branch '{}' into '{}'", branch, head));
+                    match merge_branch_with_info(&*store, &head, branch, &info).await? {
+                        MergeOutcome::UpToDate => println!("Already up to date"),
+                        MergeOutcome::FastForward(hash) => println!("Fast-forward to {}", hash),
+                        MergeOutcome::Merged(hash) => println!("Merged as {}", hash),
+                        MergeOutcome::Conflicts(conflicts) => {
+                            println!("Merge failed with {} conflict(s):", conflicts.len());
+                            for conflict in conflicts {
+                                println!("  {}", conflict);
+                            }
+                            std::process::exit(1);
+                        }
+                    }
                     Ok(())
                 })
             } else {
@@ -112,5 +456,77 @@ fn main() -> Result<()> {
             };
     }
 
+    if let Some(matches) = matches.subcommand_matches("diff") {
+        println!("Running sub-command 'diff' ");
+        return
+            if let Some(from) = matches.value_of("from") {
+                let mut rt = Runtime::new()?;
+                rt.block_on(async {
+                    let to = match matches.value_of("to") {
+                        Some(to) => to.to_string(),
+                        None => read_head(&*store).await?,
+                    };
+                    println!("Comparing '{}' with '{}'", from, to);
+
+                    let diff = diff_commits(&*store, resolve_commit(&*store, from).await?, resolve_commit(&*store, &to).await?).await?;
+                    for (VertexId(id), attributes) in diff.removed_vertices.iter() {
+                        println!("- vertex {} {:?}", id, attributes);
+                    }
+                    for (VertexId(id), attributes) in diff.added_vertices.iter() {
+                        println!("+ vertex {} {:?}", id, attributes);
+                    }
+                    for (Edge(VertexId(from), VertexId(to)), attributes) in diff.removed_edges.iter() {
+                        println!("- edge {} -> {} {:?}", from, to, attributes);
+                    }
+                    for (Edge(VertexId(from), VertexId(to)), attributes) in diff.added_edges.iter() {
+                        println!("+ edge {} -> {} {:?}", from, to, attributes);
+                    }
+                    Ok(())
+                })
+            } else {
+                Ok(())
+            };
+    }
+
+    if let Some(matches) = matches.subcommand_matches("gc") {
+        println!("Running sub-command 'gc' ");
+        let mut rt = Runtime::new()?;
+        return rt.block_on(async {
+            let dry_run = matches.is_present("dry-run");
+            let report = collect_garbage(&*store, dry_run).await?;
+            for object in report.unreachable.iter() {
+                println!("{} {}", if dry_run { "Would remove" } else { "Removed" }, object);
+            }
+            println!("{} {} object(s), {} bytes", if dry_run { "Would free" } else { "Freed" }, report.unreachable.len(), report.bytes);
+            Ok(())
+        });
+    }
+
+    if matches.subcommand_matches("fsck").is_some() {
+        println!("Running sub-command 'fsck' ");
+        let mut rt = Runtime::new()?;
+        return rt.block_on(async {
+            let report = fsck(&*store).await?;
+            for problem in report.problems.iter() {
+                println!("{}", problem);
+            }
+            println!("Checked {} object(s), found {} problem(s)", report.checked, report.problems.len());
+            if !report.is_ok() {
+                std::process::exit(1);
+            }
+            Ok(())
+        });
+    }
+
+    if matches.subcommand_matches("repack").is_some() {
+        println!("Running sub-command 'repack' ");
+        let mut rt = Runtime::new()?;
+        return rt.block_on(async {
+            let packed = store.repack().await?;
+            println!("Packed {} object(s)", packed);
+            Ok(())
+        });
+    }
+
     Ok(())
 }
@@ -0,0 +1,75 @@
+use serde::{Serialize, Deserialize};
+use histo_graph_core::graph::attributes::{Attributes, AttributeValue};
+use std::collections::BTreeMap;
+
+/// Serializes an [`AttributeValue`] as a plain value, e.g. `true`, `3`, `1.5` or `"text"`.
+///
+/// [`AttributeValue`]: ../../histo_graph_core/graph/attributes/enum.AttributeValue.html
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(untagged)]
+pub enum AttributeValueSer {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+}
+
+pub type AttributesSer = BTreeMap<String, AttributeValueSer>;
+
+impl From<&AttributeValue> for AttributeValueSer {
+    fn from(value: &AttributeValue) -> AttributeValueSer {
+        match value {
+            AttributeValue::Bool(b) => AttributeValueSer::Bool(*b),
+            AttributeValue::Int(i) => AttributeValueSer::Int(*i),
+            AttributeValue::Float(f) => AttributeValueSer::Float(*f),
+            AttributeValue::Str(s) => AttributeValueSer::Str(s.clone()),
+        }
+    }
+}
+
+impl From<&AttributeValueSer> for AttributeValue {
+    fn from(value: &AttributeValueSer) -> AttributeValue {
+        match value {
+            AttributeValueSer::Bool(b) => AttributeValue::Bool(*b),
+            AttributeValueSer::Int(i) => AttributeValue::Int(*i),
+            AttributeValueSer::Float(f) => AttributeValue::Float(*f),
+            AttributeValueSer::Str(s) => AttributeValue::Str(s.clone()),
+        }
+    }
+}
+
+pub fn to_attributes_ser(attributes: &Attributes) -> AttributesSer {
+    attributes
+        .iter()
+        .map(|(key, value)| (key.clone(), value.into()))
+        .collect()
+}
+
+pub fn from_attributes_ser(attributes: &AttributesSer) -> Attributes {
+    attributes
+        .iter()
+        .map(|(key, value)| (key.clone(), value.into()))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use histo_graph_core::graph::attributes::{Attributes, AttributeValue};
+    use crate::attributes_serde::{AttributesSer, to_attributes_ser, from_attributes_ser};
+
+    #[test]
+    fn test_plain_values() -> Result<(), serde_json::error::Error> {
+        let mut attributes = Attributes::new();
+        attributes.insert("a".to_string(), AttributeValue::Bool(true));
+        attributes.insert("b".to_string(), AttributeValue::Int(3));
+        attributes.insert("c".to_string(), AttributeValue::Float(1.5));
+        attributes.insert("d".to_string(), AttributeValue::Str("x".to_string()));
+
+        let str = serde_json::to_string(&to_attributes_ser(&attributes))?;
+        assert_eq!(str, r#"{"a":true,"b":3,"c":1.5,"d":"x"}"#);
+
+        let attributes_ser: AttributesSer = serde_json::from_str(&str)?;
+        assert_eq!(from_attributes_ser(&attributes_ser), attributes);
+        Ok(())
+    }
+}
@@ -1,11 +1,20 @@
 use serde::{Serialize, Deserialize};
 use histo_graph_core::graph::directed_graph::DirectedGraph;
+use histo_graph_core::graph::attributed_graph::AttributedGraph;
 use histo_graph_core::graph::graph::{VertexId, Edge};
+use std::collections::BTreeMap;
+use crate::attributes_serde::{AttributesSer, to_attributes_ser, from_attributes_ser};
 
+/// Serializes a graph. Only the vertices and edges that have attributes are listed in
+/// `vertex_attributes` and `edge_attributes`, and these fields are omitted if they are empty.
 #[derive(Serialize, Deserialize)]
 pub struct DirectedGraphSer {
     vertices: Vec<u64>,
     edges: Vec<(u64, u64)>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    vertex_attributes: BTreeMap<u64, AttributesSer>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    edge_attributes: Vec<(u64, u64, AttributesSer)>,
 }
 
 impl From<&DirectedGraph> for DirectedGraphSer {
@@ -18,11 +27,40 @@ impl From<&DirectedGraph> for DirectedGraphSer {
             edges: graph
                 .edges()
                 .map(|&Edge(VertexId(id_1), VertexId(id_2))| (id_1, id_2))
-                .collect()
+                .collect(),
+            vertex_attributes: BTreeMap::new(),
+            edge_attributes: Vec::new(),
         }
     }
 }
 
+impl From<&AttributedGraph> for DirectedGraphSer {
+    fn from(graph: &AttributedGraph) -> DirectedGraphSer {
+        let mut graph_ser: DirectedGraphSer = graph.graph().into();
+
+        graph_ser.vertex_attributes = graph
+            .vertices()
+            .filter_map(|&vertex_id| graph
+                .vertex_attributes(vertex_id)
+                .filter(|attributes| !attributes.is_empty())
+                .map(|attributes| (vertex_id.0, to_attributes_ser(attributes))))
+            .collect();
+
+        let mut edges: Vec<Edge> = graph.edges().cloned().collect();
+        edges.sort();
+        edges.dedup();
+        graph_ser.edge_attributes = edges
+            .into_iter()
+            .filter_map(|edge| graph
+                .edge_attributes(edge)
+                .filter(|attributes| !attributes.is_empty())
+                .map(|attributes| ((edge.0).0, (edge.1).0, to_attributes_ser(attributes))))
+            .collect();
+
+        graph_ser
+    }
+}
+
 impl From<&DirectedGraphSer> for DirectedGraph {
     fn from(graph_ser: &DirectedGraphSer) -> DirectedGraph {
         let mut graph = DirectedGraph::new();
@@ -39,9 +77,28 @@ impl From<&DirectedGraphSer> for DirectedGraph {
     }
 }
 
+impl From<&DirectedGraphSer> for AttributedGraph {
+    fn from(graph_ser: &DirectedGraphSer) -> AttributedGraph {
+        let mut graph: AttributedGraph = DirectedGraph::from(graph_ser).into();
+
+        for (&v_id, attributes) in &graph_ser.vertex_attributes {
+            graph.add_vertex_with_attributes(VertexId(v_id), from_attributes_ser(attributes));
+        }
+
+        for (v_id_1, v_id_2, attributes) in &graph_ser.edge_attributes {
+            for (key, value) in from_attributes_ser(attributes) {
+                graph.set_edge_attribute(Edge(VertexId(*v_id_1), VertexId(*v_id_2)), key, value);
+            }
+        }
+
+        graph
+    }
+}
+
 #[cfg(test)]
 mod test {
     use histo_graph_core::graph::directed_graph::DirectedGraph;
+    use histo_graph_core::graph::attributed_graph::AttributedGraph;
     use histo_graph_core::graph::graph::{Edge, VertexId};
     use crate::directed_graph_serde::DirectedGraphSer;
 
@@ -63,4 +120,38 @@ mod test {
         assert_eq!(graph, ser_de_graph);
         Ok(())
     }
+
+    #[test]
+    fn test_without_attributes() -> Result<(), serde_json::error::Error> {
+        let mut graph = AttributedGraph::new();
+        graph.add_edge(Edge(VertexId(0), VertexId(1)));
+
+        let graph_ser: DirectedGraphSer = (&graph).into();
+        let str = serde_json::to_string(&graph_ser)?;
+
+        assert!(!str.contains("attributes"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_attributes() -> Result<(), serde_json::error::Error> {
+        let mut graph = AttributedGraph::new();
+        graph.add_edge(Edge(VertexId(0), VertexId(1)));
+        graph.add_edge(Edge(VertexId(0), VertexId(1)));
+        graph.add_edge(Edge(VertexId(1), VertexId(2)));
+        graph.set_vertex_attribute(VertexId(0), "name".to_string(), "zero".into());
+        graph.set_edge_attribute(Edge(VertexId(0), VertexId(1)), "label".to_string(), "knows".into());
+        graph.set_edge_attribute(Edge(VertexId(0), VertexId(1)), "weight".to_string(), 0.5.into());
+
+        let graph_ser: DirectedGraphSer = (&graph).into();
+
+        let str = serde_json::to_string(&graph_ser)?;
+
+        let graph_ser = serde_json::from_str(&str)?;
+
+        let ser_de_graph = AttributedGraph::from(&graph_ser);
+
+        assert_eq!(graph, ser_de_graph);
+        Ok(())
+    }
 }
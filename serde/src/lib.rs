@@ -1 +1,2 @@
+pub mod attributes_serde;
 pub mod directed_graph_serde;